@@ -0,0 +1,208 @@
+//! Supplying the memory libk4a allocates for capture buffers.
+//!
+//! libk4a allocates the buffers behind the images of each capture it reads from a device. An
+//! `ImageAllocator` installed with `set_allocator` takes over those allocations, eg. to serve them
+//! from a pool, and the crate keeps `AllocationStats` for every buffer it allocates.
+
+use crate::error::SetAllocatorError;
+use k4a_sys_temp as k4a_sys;
+
+use std::alloc::{self, Layout};
+use std::os::raw::{c_int, c_void};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// An allocator for libk4a's capture buffers.
+///
+/// # Safety
+///
+/// `allocate` must return null or a pointer to `size` bytes that stay valid and unaliased until
+/// `free` is called with that pointer. Both are called from libk4a's threads, concurrently.
+pub unsafe trait ImageAllocator: Send + Sync + 'static {
+    /// Allocate `size` bytes, or return null to fail the allocation.
+    fn allocate(&self, size: usize) -> *mut u8;
+
+    /// Free a buffer returned by `allocate` with the same `size`.
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must come from `allocate(size)` on this allocator and is only freed once.
+    unsafe fn free(&self, buffer: *mut u8, size: usize);
+}
+
+/// Allocates from the Rust global allocator. Installing it is the simplest way to get
+/// `AllocationStats` without changing where memory comes from.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SystemAllocator;
+
+/// libk4a copies and parses image buffers with SIMD, so align to a cache line.
+const SYSTEM_ALIGNMENT: usize = 64;
+
+unsafe impl ImageAllocator for SystemAllocator {
+    fn allocate(&self, size: usize) -> *mut u8 {
+        match Layout::from_size_align(size.max(1), SYSTEM_ALIGNMENT) {
+            Ok(layout) => unsafe { alloc::alloc(layout) },
+            Err(_) => std::ptr::null_mut(),
+        }
+    }
+
+    unsafe fn free(&self, buffer: *mut u8, size: usize) {
+        alloc::dealloc(buffer, Layout::from_size_align_unchecked(size.max(1), SYSTEM_ALIGNMENT));
+    }
+}
+
+/// Counts of the buffers allocated through `ImageAllocator`s since the process started.
+///
+/// Buffers libk4a allocates with its default allocator aren't counted.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct AllocationStats {
+    /// Successful allocations.
+    pub allocations: u64,
+    /// Allocations the allocator returned null for.
+    pub failed_allocations: u64,
+    /// Buffers freed.
+    pub frees: u64,
+    /// Bytes of all successful allocations.
+    pub total_bytes: u64,
+    /// Buffers currently allocated.
+    pub outstanding_allocations: usize,
+    /// Bytes currently allocated.
+    pub outstanding_bytes: usize,
+    /// The most bytes allocated at once.
+    pub peak_bytes: usize,
+}
+
+struct Counters {
+    allocations: AtomicU64,
+    failed_allocations: AtomicU64,
+    frees: AtomicU64,
+    total_bytes: AtomicU64,
+    outstanding_allocations: AtomicUsize,
+    outstanding_bytes: AtomicUsize,
+    peak_bytes: AtomicUsize,
+}
+
+static COUNTERS: Counters = Counters {
+    allocations: AtomicU64::new(0),
+    failed_allocations: AtomicU64::new(0),
+    frees: AtomicU64::new(0),
+    total_bytes: AtomicU64::new(0),
+    outstanding_allocations: AtomicUsize::new(0),
+    outstanding_bytes: AtomicUsize::new(0),
+    peak_bytes: AtomicUsize::new(0),
+};
+
+/// The installed allocator. Each allocation holds its own reference to the allocator it came
+/// from, so buffers allocated before `set_allocator` or `reset_allocator` are still freed by
+/// the right allocator.
+static ALLOCATOR: RwLock<Option<Arc<dyn ImageAllocator>>> = RwLock::new(None);
+
+/// What `free` needs to know about a buffer, passed through libk4a's per-allocation context.
+struct Allocation {
+    allocator: Arc<dyn ImageAllocator>,
+    size: usize,
+}
+
+/// Allocate libk4a's capture buffers with `allocator`.
+///
+/// Buffers already allocated are freed by the allocator that allocated them.
+pub fn set_allocator<A: ImageAllocator>(allocator: A) -> Result<(), SetAllocatorError> {
+    let previous = swap_allocator(Some(Arc::new(allocator)));
+
+    // NB: libk4a may be allocating on another thread, which takes the read lock, so the lock must
+    // not be held across this call.
+    let result = unsafe { k4a_sys::k4a_set_allocator(Some(allocate_buffer), Some(free_buffer)) };
+
+    if let Err(error) = check(result) {
+        swap_allocator(previous);
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Go back to libk4a's default allocator.
+pub fn reset_allocator() -> Result<(), SetAllocatorError> {
+    let result = unsafe { k4a_sys::k4a_set_allocator(None, None) };
+    check(result)?;
+
+    // NB: Only uninstalled once libk4a stopped calling `allocate_buffer`, which would otherwise
+    // fail allocations in between.
+    swap_allocator(None);
+    Ok(())
+}
+
+/// Install `allocator`, returning the allocator it replaces. The lock is only held for the swap.
+fn swap_allocator(allocator: Option<Arc<dyn ImageAllocator>>) -> Option<Arc<dyn ImageAllocator>> {
+    let mut installed = ALLOCATOR.write().unwrap_or_else(|error| error.into_inner());
+    std::mem::replace(&mut *installed, allocator)
+}
+
+/// Statistics for the buffers allocated through `ImageAllocator`s so far.
+pub fn allocation_stats() -> AllocationStats {
+    AllocationStats {
+        allocations: COUNTERS.allocations.load(Ordering::Relaxed),
+        failed_allocations: COUNTERS.failed_allocations.load(Ordering::Relaxed),
+        frees: COUNTERS.frees.load(Ordering::Relaxed),
+        total_bytes: COUNTERS.total_bytes.load(Ordering::Relaxed),
+        outstanding_allocations: COUNTERS.outstanding_allocations.load(Ordering::Relaxed),
+        outstanding_bytes: COUNTERS.outstanding_bytes.load(Ordering::Relaxed),
+        peak_bytes: COUNTERS.peak_bytes.load(Ordering::Relaxed),
+    }
+}
+
+fn check(result: k4a_sys::k4a_result_t) -> Result<(), SetAllocatorError> {
+    match result {
+        k4a_sys::k4a_result_t_K4A_RESULT_SUCCEEDED => Ok(()),
+        k4a_sys::k4a_result_t_K4A_RESULT_FAILED => Err(SetAllocatorError::FailedError),
+        _ => {
+            // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
+            // Linux uses u32 and Windows uses i32.
+            // This should be fixed in the `k4a-sys` build script.
+            Err(SetAllocatorError::UnexpectedError(result as i32))
+        },
+    }
+}
+
+unsafe extern "C" fn allocate_buffer(size: c_int, context: *mut *mut c_void) -> *mut u8 {
+    let allocator = match ALLOCATOR.read().unwrap_or_else(|error| error.into_inner()).as_ref() {
+        Some(allocator) => allocator.clone(),
+        None => return std::ptr::null_mut(),
+    };
+    if size < 0 {
+        return std::ptr::null_mut();
+    }
+    let size = size as usize;
+
+    // Unwinding into libk4a is undefined behavior, so a panic fails the allocation.
+    let buffer = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| allocator.allocate(size)))
+        .unwrap_or(std::ptr::null_mut());
+
+    if buffer.is_null() {
+        COUNTERS.failed_allocations.fetch_add(1, Ordering::Relaxed);
+        return buffer;
+    }
+
+    COUNTERS.allocations.fetch_add(1, Ordering::Relaxed);
+    COUNTERS.total_bytes.fetch_add(size as u64, Ordering::Relaxed);
+    COUNTERS.outstanding_allocations.fetch_add(1, Ordering::Relaxed);
+    let outstanding = COUNTERS.outstanding_bytes.fetch_add(size, Ordering::Relaxed) + size;
+    COUNTERS.peak_bytes.fetch_max(outstanding, Ordering::Relaxed);
+
+    *context = Box::into_raw(Box::new(Allocation { allocator, size })) as *mut c_void;
+    buffer
+}
+
+unsafe extern "C" fn free_buffer(buffer: *mut c_void, context: *mut c_void) {
+    if context.is_null() {
+        return;
+    }
+    let allocation = Box::from_raw(context as *mut Allocation);
+
+    let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        allocation.allocator.free(buffer as *mut u8, allocation.size)
+    }));
+
+    COUNTERS.frees.fetch_add(1, Ordering::Relaxed);
+    COUNTERS.outstanding_allocations.fetch_sub(1, Ordering::Relaxed);
+    COUNTERS.outstanding_bytes.fetch_sub(allocation.size, Ordering::Relaxed);
+}
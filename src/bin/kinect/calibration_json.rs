@@ -0,0 +1,82 @@
+//! Calibration as JSON, for `kinect info`.
+
+use crate::config::{ColorResolution, DepthMode};
+use kinect::k4a_sys;
+use kinect::{Calibration, CalibrationType, Extrinsics};
+
+use serde_json::{json, Value};
+
+const SENSORS: [(CalibrationType, &str); 4] = [
+    (CalibrationType::Depth, "depth"),
+    (CalibrationType::Color, "color"),
+    (CalibrationType::Gyro, "gyro"),
+    (CalibrationType::Accel, "accel"),
+];
+
+pub fn calibration_to_json(calibration: &Calibration) -> Value {
+    let mut extrinsics = serde_json::Map::new();
+    for (source, source_name) in SENSORS.iter() {
+        for (target, target_name) in SENSORS.iter() {
+            if source != target {
+                extrinsics.insert(format!("{}_to_{}", source_name, target_name),
+                                  extrinsics_to_json(&calibration.get_extrinsics(*source, *target)));
+            }
+        }
+    }
+
+    json!({
+        "depth_mode": DepthMode::name_of(calibration.0.depth_mode),
+        "color_resolution": ColorResolution::name_of(calibration.0.color_resolution),
+        "depth_camera": camera_to_json(&calibration.0.depth_camera_calibration),
+        "color_camera": camera_to_json(&calibration.0.color_camera_calibration),
+        "extrinsics": extrinsics,
+    })
+}
+
+fn camera_to_json(camera: &k4a_sys::k4a_calibration_camera_t) -> Value {
+    // NB: This is a union field, so we have to use unsafe access
+    let parameters = unsafe { camera.intrinsics.parameters.param };
+
+    json!({
+        "resolution_width": camera.resolution_width,
+        "resolution_height": camera.resolution_height,
+        "metric_radius": camera.metric_radius,
+        "intrinsics": {
+            "model": model_name(camera.intrinsics.type_),
+            "parameter_count": camera.intrinsics.parameter_count,
+            "cx": parameters.cx,
+            "cy": parameters.cy,
+            "fx": parameters.fx,
+            "fy": parameters.fy,
+            "k1": parameters.k1,
+            "k2": parameters.k2,
+            "k3": parameters.k3,
+            "k4": parameters.k4,
+            "k5": parameters.k5,
+            "k6": parameters.k6,
+            "codx": parameters.codx,
+            "cody": parameters.cody,
+            "p1": parameters.p1,
+            "p2": parameters.p2,
+            "metric_radius": parameters.metric_radius,
+        },
+        "extrinsics": extrinsics_to_json(&camera.extrinsics.into()),
+    })
+}
+
+fn extrinsics_to_json(extrinsics: &Extrinsics) -> Value {
+    json!({
+        "rotation": extrinsics.rotation,
+        "translation_mm": extrinsics.translation,
+    })
+}
+
+fn model_name(model: k4a_sys::k4a_calibration_model_type_t) -> &'static str {
+    match model {
+        k4a_sys::k4a_calibration_model_type_t_K4A_CALIBRATION_LENS_DISTORTION_MODEL_THETA => "theta",
+        k4a_sys::k4a_calibration_model_type_t_K4A_CALIBRATION_LENS_DISTORTION_MODEL_POLYNOMIAL_3K => "polynomial_3k",
+        k4a_sys::k4a_calibration_model_type_t_K4A_CALIBRATION_LENS_DISTORTION_MODEL_RATIONAL_6KT => "rational_6kt",
+        k4a_sys::k4a_calibration_model_type_t_K4A_CALIBRATION_LENS_DISTORTION_MODEL_BROWN_CONRADY => "brown_conrady",
+        _ => "unknown",
+    }
+}
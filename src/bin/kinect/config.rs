@@ -0,0 +1,195 @@
+//! Command line options for choosing and configuring a device.
+
+use clap::{Args, ValueEnum};
+use kinect::k4a_sys;
+use kinect::{Device, DeviceConfiguration};
+
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Args, Debug)]
+pub struct DeviceArgs {
+    /// Index of the device to open.
+    #[arg(long, short, default_value_t = 0, conflicts_with = "serial")]
+    pub device: u32,
+    /// Serial number of the device to open, instead of an index.
+    #[arg(long, short)]
+    pub serial: Option<String>,
+}
+
+impl DeviceArgs {
+    pub fn open(&self) -> Result<Device, Box<dyn Error>> {
+        match &self.serial {
+            Some(serial_number) => Ok(Device::open_by_serial(serial_number)?),
+            None => Ok(Device::open(self.device)?),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ConfigurationArgs {
+    /// A YAML (or JSON) file with any of the keys `depth_mode`, `color_resolution`,
+    /// `color_format` and `fps`, taking the same values as the options. Missing keys get the
+    /// options' defaults.
+    #[arg(long, conflicts_with_all = ["depth_mode", "color_resolution", "color_format", "fps"])]
+    pub config: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t = DepthMode::NfovUnbinned)]
+    pub depth_mode: DepthMode,
+    #[arg(long, value_enum, default_value_t = ColorResolution::R720p)]
+    pub color_resolution: ColorResolution,
+    #[arg(long, value_enum, default_value_t = ColorFormat::Bgra32)]
+    pub color_format: ColorFormat,
+    #[arg(long, value_enum, default_value_t = Fps::Fps30)]
+    pub fps: Fps,
+}
+
+impl ConfigurationArgs {
+    pub fn to_configuration(&self) -> Result<DeviceConfiguration, Box<dyn Error>> {
+        let mut depth_mode = self.depth_mode;
+        let mut color_resolution = self.color_resolution;
+        let mut color_format = self.color_format;
+        let mut fps = self.fps;
+
+        if let Some(path) = &self.config {
+            let file: serde_yaml::Value = serde_yaml::from_str(&fs::read_to_string(path)?)?;
+            read_key(&file, "depth_mode", &mut depth_mode)?;
+            read_key(&file, "color_resolution", &mut color_resolution)?;
+            read_key(&file, "color_format", &mut color_format)?;
+            read_key(&file, "fps", &mut fps)?;
+        }
+
+        let mut configuration = DeviceConfiguration::init_disable_all();
+        configuration.0.depth_mode = depth_mode.to_k4a();
+        configuration.0.color_resolution = color_resolution.to_k4a();
+        configuration.0.color_format = color_format.to_k4a();
+        configuration.0.camera_fps = fps.to_k4a();
+        configuration.0.synchronized_images_only = depth_mode != DepthMode::Off
+            && color_resolution != ColorResolution::Off;
+        Ok(configuration)
+    }
+}
+
+/// Overwrite `value` with `key` from a configuration file, if it's there.
+fn read_key<T: ValueEnum>(file: &serde_yaml::Value, key: &str, value: &mut T) -> Result<(), Box<dyn Error>> {
+    let text = match file.get(key) {
+        None => return Ok(()),
+        Some(serde_yaml::Value::String(text)) => text.clone(),
+        // Unquoted numbers like `fps: 30` aren't strings in YAML.
+        Some(serde_yaml::Value::Number(number)) => number.to_string(),
+        Some(other) => return Err(format!("invalid {}: {:?}", key, other).into()),
+    };
+    *value = T::from_str(&text, true).map_err(|_| format!("invalid {}: {}", key, text))?;
+    Ok(())
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DepthMode {
+    Off,
+    #[value(name = "nfov-binned")]
+    NfovBinned,
+    #[value(name = "nfov-unbinned")]
+    NfovUnbinned,
+    #[value(name = "wfov-binned")]
+    WfovBinned,
+    #[value(name = "wfov-unbinned")]
+    WfovUnbinned,
+    #[value(name = "passive-ir")]
+    PassiveIr,
+}
+
+impl DepthMode {
+    pub fn to_k4a(self) -> k4a_sys::k4a_depth_mode_t {
+        match self {
+            DepthMode::Off => k4a_sys::k4a_depth_mode_t_K4A_DEPTH_MODE_OFF,
+            DepthMode::NfovBinned => k4a_sys::k4a_depth_mode_t_K4A_DEPTH_MODE_NFOV_2X2BINNED,
+            DepthMode::NfovUnbinned => k4a_sys::k4a_depth_mode_t_K4A_DEPTH_MODE_NFOV_UNBINNED,
+            DepthMode::WfovBinned => k4a_sys::k4a_depth_mode_t_K4A_DEPTH_MODE_WFOV_2X2BINNED,
+            DepthMode::WfovUnbinned => k4a_sys::k4a_depth_mode_t_K4A_DEPTH_MODE_WFOV_UNBINNED,
+            DepthMode::PassiveIr => k4a_sys::k4a_depth_mode_t_K4A_DEPTH_MODE_PASSIVE_IR,
+        }
+    }
+
+    pub fn name_of(depth_mode: k4a_sys::k4a_depth_mode_t) -> String {
+        Self::value_variants().iter()
+            .find(|mode| mode.to_k4a() == depth_mode)
+            .and_then(|mode| mode.to_possible_value())
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_else(|| format!("unknown ({})", depth_mode))
+    }
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorResolution {
+    Off,
+    #[value(name = "720p")]
+    R720p,
+    #[value(name = "1080p")]
+    R1080p,
+    #[value(name = "1440p")]
+    R1440p,
+    #[value(name = "1536p")]
+    R1536p,
+    #[value(name = "2160p")]
+    R2160p,
+    #[value(name = "3072p")]
+    R3072p,
+}
+
+impl ColorResolution {
+    pub fn to_k4a(self) -> k4a_sys::k4a_color_resolution_t {
+        match self {
+            ColorResolution::Off => k4a_sys::k4a_color_resolution_t_K4A_COLOR_RESOLUTION_OFF,
+            ColorResolution::R720p => k4a_sys::k4a_color_resolution_t_K4A_COLOR_RESOLUTION_720P,
+            ColorResolution::R1080p => k4a_sys::k4a_color_resolution_t_K4A_COLOR_RESOLUTION_1080P,
+            ColorResolution::R1440p => k4a_sys::k4a_color_resolution_t_K4A_COLOR_RESOLUTION_1440P,
+            ColorResolution::R1536p => k4a_sys::k4a_color_resolution_t_K4A_COLOR_RESOLUTION_1536P,
+            ColorResolution::R2160p => k4a_sys::k4a_color_resolution_t_K4A_COLOR_RESOLUTION_2160P,
+            ColorResolution::R3072p => k4a_sys::k4a_color_resolution_t_K4A_COLOR_RESOLUTION_3072P,
+        }
+    }
+
+    pub fn name_of(color_resolution: k4a_sys::k4a_color_resolution_t) -> String {
+        Self::value_variants().iter()
+            .find(|resolution| resolution.to_k4a() == color_resolution)
+            .and_then(|resolution| resolution.to_possible_value())
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_else(|| format!("unknown ({})", color_resolution))
+    }
+}
+
+/// Color formats the CLI can save.
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorFormat {
+    Bgra32,
+    Mjpg,
+}
+
+impl ColorFormat {
+    pub fn to_k4a(self) -> k4a_sys::k4a_image_format_t {
+        match self {
+            ColorFormat::Bgra32 => k4a_sys::k4a_image_format_t_K4A_IMAGE_FORMAT_COLOR_BGRA32,
+            ColorFormat::Mjpg => k4a_sys::k4a_image_format_t_K4A_IMAGE_FORMAT_COLOR_MJPG,
+        }
+    }
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Fps {
+    #[value(name = "5")]
+    Fps5,
+    #[value(name = "15")]
+    Fps15,
+    #[value(name = "30")]
+    Fps30,
+}
+
+impl Fps {
+    pub fn to_k4a(self) -> k4a_sys::k4a_fps_t {
+        match self {
+            Fps::Fps5 => k4a_sys::k4a_fps_t_K4A_FRAMES_PER_SECOND_5,
+            Fps::Fps15 => k4a_sys::k4a_fps_t_K4A_FRAMES_PER_SECOND_15,
+            Fps::Fps30 => k4a_sys::k4a_fps_t_K4A_FRAMES_PER_SECOND_30,
+        }
+    }
+}
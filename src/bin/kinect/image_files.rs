@@ -0,0 +1,124 @@
+//! Writing images as files any tool can open: 16-bit PGM for depth and IR, PNG for color.
+
+use kinect::Image;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Write a 16-bit image (`Depth16` or `Ir16`) as a binary PGM, which stores samples big endian.
+pub fn write_pgm16(image: &Image, path: &Path) -> Result<(), Box<dyn Error>> {
+    let width = image.get_width_pixels();
+    let height = image.get_height_pixels();
+    let stride = image.get_stride_bytes();
+    let data = image.get_data();
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    write!(writer, "P5\n{} {}\n65535\n", width, height)?;
+    for y in 0..height {
+        for pixel in data[y * stride..y * stride + width * 2].chunks_exact(2) {
+            writer.write_all(&[pixel[1], pixel[0]])?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write a `ColorBgra32` image as an RGB PNG, dropping alpha.
+///
+/// The image data is stored uncompressed, which keeps this dependency free at the cost of file
+/// size (about the size of the raw image).
+pub fn write_png(image: &Image, path: &Path) -> Result<(), Box<dyn Error>> {
+    let width = image.get_width_pixels();
+    let height = image.get_height_pixels();
+    let stride = image.get_stride_bytes();
+    let data = image.get_data();
+
+    // Each scanline starts with its filter type, 0 (none).
+    let mut scanlines = Vec::with_capacity(height * (width * 3 + 1));
+    for y in 0..height {
+        scanlines.push(0);
+        for pixel in data[y * stride..y * stride + width * 4].chunks_exact(4) {
+            scanlines.extend_from_slice(&[pixel[2], pixel[1], pixel[0]]);
+        }
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per channel, RGB, deflate, adaptive filtering, no interlacing.
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+    write_chunk(&mut writer, b"IHDR", &header)?;
+    write_chunk(&mut writer, b"IDAT", &zlib_stored(&scanlines))?;
+    write_chunk(&mut writer, b"IEND", &[])?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> Result<(), Box<dyn Error>> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let crc = crc32(&[&kind[..], data]);
+    writer.write_all(&crc.to_be_bytes())?;
+    Ok(())
+}
+
+/// A zlib stream of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = 65535;
+
+    let mut stream = Vec::with_capacity(data.len() + data.len() / MAX_BLOCK * 5 + 11);
+    stream.extend_from_slice(&[0x78, 0x01]);
+
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none() as u8;
+        let length = block.len() as u16;
+        stream.push(last);
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+        }
+        *entry = c;
+    }
+
+    let mut crc = 0xffff_ffffu32;
+    for byte in parts.iter().flat_map(|part| part.iter()) {
+        crc = table[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    crc ^ 0xffff_ffff
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 bytes is the most that can be summed before b could overflow.
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
@@ -0,0 +1,315 @@
+//! `kinect`: inspect Azure Kinect devices and save snapshots, without the C SDK tools.
+
+mod calibration_json;
+mod config;
+mod image_files;
+#[cfg(feature = "record")]
+mod record;
+mod snapshot;
+
+use crate::config::{ConfigurationArgs, DeviceArgs};
+use clap::{Parser, Subcommand, ValueEnum};
+use kinect::{Device, DeviceInfo};
+
+use serde_json::{json, Value};
+use std::error::Error;
+use std::path::PathBuf;
+use std::process;
+
+#[derive(Parser, Debug)]
+#[command(name = "kinect", version, about = "Inspect Azure Kinect devices and save snapshots")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List installed devices with their serial numbers and firmware versions.
+    List {
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Print a device's calibration for a depth mode and color resolution.
+    Info {
+        #[command(flatten)]
+        device: DeviceArgs,
+        #[command(flatten)]
+        configuration: ConfigurationArgs,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Json)]
+        format: OutputFormat,
+    },
+    /// Save the depth, IR and color images of one capture.
+    Snapshot {
+        #[command(flatten)]
+        device: DeviceArgs,
+        #[command(flatten)]
+        configuration: ConfigurationArgs,
+        /// Directory to write the images to.
+        #[arg(long, short, default_value = ".")]
+        output: PathBuf,
+        /// Captures to discard first, so auto exposure can settle.
+        #[arg(long, default_value_t = 30)]
+        warmup: u32,
+        #[arg(long, default_value_t = 1000)]
+        timeout_ms: i32,
+    },
+    /// Record captures to a Matroska (.mkv) file.
+    #[cfg(feature = "record")]
+    Record {
+        #[command(flatten)]
+        device: DeviceArgs,
+        #[command(flatten)]
+        configuration: ConfigurationArgs,
+        /// The recording to create.
+        output: PathBuf,
+        /// How long to record, in seconds.
+        #[arg(long, default_value_t = 10.0)]
+        duration: f64,
+        #[arg(long, default_value_t = 1000)]
+        timeout_ms: i32,
+    },
+    /// Write the frames of part of a recording as images, with its calibration and timestamps.
+    #[cfg(feature = "record")]
+    Export {
+        /// The recording to read.
+        input: PathBuf,
+        /// Seconds into the recording of the first frame.
+        #[arg(long, default_value_t = 0.0)]
+        from: f64,
+        /// Seconds into the recording of the last frame; the end of the recording if not given.
+        #[arg(long)]
+        to: Option<f64>,
+        /// Directory to write the frames to.
+        #[arg(long, short)]
+        out: PathBuf,
+    },
+    /// Show which synchronization jacks of each device are connected.
+    SyncStatus {
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    Text,
+    Json,
+    Yaml,
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    if let Err(error) = run(cli.command) {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
+
+fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::List { format } => list(format),
+        Command::Info { device, configuration, format } => info(&device, &configuration, format),
+        Command::Snapshot { device, configuration, output, warmup, timeout_ms } =>
+            snapshot::snapshot(&device, &configuration, &output, warmup, timeout_ms),
+        #[cfg(feature = "record")]
+        Command::Record { device, configuration, output, duration, timeout_ms } =>
+            record::record(&device, &configuration, &output, duration, timeout_ms),
+        #[cfg(feature = "record")]
+        Command::Export { input, from, to, out } => record::export(&input, from, to, &out),
+        Command::SyncStatus { format } => sync_status(format),
+    }
+}
+
+fn list(format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let devices = probe_devices();
+
+    if format != OutputFormat::Text {
+        let devices: Vec<Value> = devices.iter()
+            .map(|(index, device)| device.as_ref().map(device_to_json).unwrap_or_else(|error| error_to_json(*index, error)))
+            .collect();
+        return print_structured(&Value::from(devices), format);
+    }
+
+    if devices.is_empty() {
+        println!("No devices installed.");
+    }
+    for (index, device) in devices.iter() {
+        match device {
+            Ok(device) => println!("{}\t{}\t{}", index, device.serial_number, device.firmware),
+            Err(error) => println!("{}\terror: {}", index, error),
+        }
+    }
+    Ok(())
+}
+
+fn info(device_args: &DeviceArgs, configuration: &ConfigurationArgs, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let configuration = configuration.to_configuration()?;
+    let device = device_args.open()?;
+    let calibration = device.get_calibration(configuration.0.depth_mode, configuration.0.color_resolution)?;
+
+    let serial_number = device.get_serial_number()?;
+    let firmware = device.get_version()?;
+
+    let info = json!({
+        "serial_number": serial_number,
+        "firmware": firmware.to_string(),
+        "calibration": calibration_json::calibration_to_json(&calibration),
+    });
+
+    if format == OutputFormat::Text {
+        print!("{}", text(&info, 0));
+        return Ok(());
+    }
+    print_structured(&info, format)
+}
+
+fn sync_status(format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let devices = probe_devices();
+
+    if format != OutputFormat::Text {
+        let statuses: Vec<Value> = devices.iter()
+            .map(|(index, device)| match device {
+                Ok(device) => json!({
+                    "index": device.index,
+                    "serial_number": device.serial_number,
+                    "sync_in_jack_connected": device.synchronization_jack_status.sync_in_jack_connected,
+                    "sync_out_jack_connected": device.synchronization_jack_status.sync_out_jack_connected,
+                }),
+                Err(error) => error_to_json(*index, error),
+            })
+            .collect();
+        return print_structured(&Value::from(statuses), format);
+    }
+
+    for (index, device) in devices.iter() {
+        match device {
+            Ok(device) => {
+                let status = device.synchronization_jack_status;
+                println!("{}\t{}\tsync in: {}\tsync out: {}",
+                         index,
+                         device.serial_number,
+                         connected(status.sync_in_jack_connected),
+                         connected(status.sync_out_jack_connected));
+            },
+            Err(error) => println!("{}\terror: {}", index, error),
+        }
+    }
+    Ok(())
+}
+
+/// Every installed device by index, with the error for those that can't be opened or queried
+/// (eg. because another process is using them).
+fn probe_devices() -> Vec<(u32, Result<DeviceInfo, kinect::Error>)> {
+    (0..Device::get_installed_count())
+        .map(|index| (index, Device::probe(index)))
+        .collect()
+}
+
+fn device_to_json(device: &DeviceInfo) -> Value {
+    json!({
+        "index": device.index,
+        "serial_number": device.serial_number,
+        "firmware": {
+            "rgb": device.firmware.rgb.to_string(),
+            "depth": device.firmware.depth.to_string(),
+            "audio": device.firmware.audio.to_string(),
+            "depth_sensor": device.firmware.depth_sensor.to_string(),
+            "build": device.firmware.firmware_build.to_string(),
+            "signature": device.firmware.firmware_signature.to_string(),
+        },
+    })
+}
+
+fn error_to_json(index: u32, error: &kinect::Error) -> Value {
+    json!({
+        "index": index,
+        "error": error.to_string(),
+    })
+}
+
+/// The same fields as the JSON and YAML output, one per line, nested by indentation.
+fn text(value: &Value, depth: usize) -> String {
+    let indent = "  ".repeat(depth);
+    let nested = |value: &Value| match value {
+        Value::Object(_) => true,
+        Value::Array(values) => values.iter().any(|value| value.is_object() || value.is_array()),
+        _ => false,
+    };
+
+    let mut lines = String::new();
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                if nested(value) {
+                    lines += &format!("{}{}:\n{}", indent, key, text(value, depth + 1));
+                } else {
+                    lines += &format!("{}{}: {}\n", indent, key, scalar_text(value));
+                }
+            }
+        },
+        Value::Array(values) if nested(value) => {
+            for (index, value) in values.iter().enumerate() {
+                lines += &format!("{}[{}]:\n{}", indent, index, text(value, depth + 1));
+            }
+        },
+        value => lines += &format!("{}{}\n", indent, scalar_text(value)),
+    }
+    lines
+}
+
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        Value::Array(values) => {
+            let values: Vec<String> = values.iter().map(scalar_text).collect();
+            format!("[{}]", values.join(", "))
+        },
+        value => value.to_string(),
+    }
+}
+
+fn print_structured(value: &Value, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    match format {
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(value)?),
+        _ => println!("{}", serde_json::to_string_pretty(value)?),
+    }
+    Ok(())
+}
+
+fn connected(connected: bool) -> &'static str {
+    if connected { "connected" } else { "disconnected" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_nests_the_structured_fields() {
+        let value = json!({
+            "serial_number": "000123",
+            "calibration": {
+                "depth_mode": "nfov_unbinned",
+                "extrinsics": { "rotation": [1.0, 0.0, 0.5], "translation_mm": [0.0, -32.5, 4.0] },
+            },
+            "devices": [{ "index": 0 }, { "index": 1, "error": "busy" }],
+        });
+
+        assert_eq!(text(&value, 0), "\
+calibration:
+  depth_mode: nfov_unbinned
+  extrinsics:
+    rotation: [1.0, 0.0, 0.5]
+    translation_mm: [0.0, -32.5, 4.0]
+devices:
+  [0]:
+    index: 0
+  [1]:
+    error: busy
+    index: 1
+serial_number: 000123
+");
+    }
+}
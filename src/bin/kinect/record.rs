@@ -0,0 +1,136 @@
+//! `kinect record` and `kinect export`: recording to .mkv and dumping a range of a recording as
+//! image files.
+//!
+//! An export directory has one `frame_NNNNNN_{depth,ir}.pgm` and `frame_NNNNNN_color.png` per
+//! capture (whichever images it has), `calibration.json` in the format of `kinect info`, and
+//! `timestamps.csv` with each frame's device timestamps.
+
+use crate::calibration_json::calibration_to_json;
+use crate::config::{ConfigurationArgs, DeviceArgs};
+use crate::image_files::{write_pgm16, write_png};
+use kinect::{Image, ImageFormat, Playback, Recorder, SeekOrigin};
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub fn record(device_args: &DeviceArgs,
+              configuration: &ConfigurationArgs,
+              output: &Path,
+              duration_secs: f64,
+              timeout_ms: i32) -> Result<(), Box<dyn Error>>
+{
+    let configuration = configuration.to_configuration()?;
+    let device = device_args.open()?;
+
+    let mut recorder = Recorder::create(output, &device, &configuration)?;
+    recorder.write_header()?;
+
+    device.start_cameras(&configuration)?;
+    let duration = Duration::from_secs_f64(duration_secs);
+    let start = Instant::now();
+    let mut captures = 0;
+    let result = (|| -> Result<(), Box<dyn Error>> {
+        while start.elapsed() < duration {
+            recorder.write_capture(&device.get_capture(timeout_ms)?)?;
+            captures += 1;
+        }
+        Ok(())
+    })();
+    device.stop_cameras();
+    result?;
+
+    recorder.flush()?;
+    println!("{}: {} captures", output.display(), captures);
+    Ok(())
+}
+
+pub fn export(input: &Path, from_secs: f64, to_secs: Option<f64>, output: &Path) -> Result<(), Box<dyn Error>> {
+    check_range(from_secs, to_secs)?;
+    let mut playback = Playback::open(input)?;
+    let start_usec = playback.get_record_configuration()?.start_timestamp_offset_usec as u64;
+    let to_usec = to_secs.map(|to| (to * 1e6) as u64);
+
+    // MJPG recordings are decoded, so every color image can be written as PNG.
+    playback.set_color_conversion(ImageFormat::ColorBgra32)?;
+    playback.seek_timestamp((from_secs * 1e6) as i64, SeekOrigin::Begin)?;
+
+    fs::create_dir_all(output)?;
+    let calibration = calibration_to_json(&playback.get_calibration()?);
+    fs::write(output.join("calibration.json"), serde_json::to_string_pretty(&calibration)?)?;
+
+    let mut timestamps = BufWriter::new(File::create(output.join("timestamps.csv"))?);
+    writeln!(timestamps, "frame,depth_timestamp_usec,ir_timestamp_usec,color_timestamp_usec")?;
+
+    let mut frame = 0;
+    while let Some(capture) = playback.get_next_capture()? {
+        let depth = capture.get_depth_image();
+        let ir = capture.get_ir_image();
+        let color = capture.get_color_image();
+
+        let timestamp_usec = [&depth, &ir, &color].iter()
+            .filter_map(|image| image.as_ref().map(Image::get_device_timestamp_usec))
+            .min();
+        match (timestamp_usec, to_usec) {
+            (Some(timestamp_usec), Some(to_usec)) if timestamp_usec.saturating_sub(start_usec) > to_usec => break,
+            _ => {},
+        }
+
+        let path = |name: &str| output.join(format!("frame_{:06}_{}", frame, name));
+        if let Some(depth) = &depth {
+            write_pgm16(depth, &path("depth.pgm"))?;
+        }
+        if let Some(ir) = &ir {
+            write_pgm16(ir, &path("ir.pgm"))?;
+        }
+        if let Some(color) = &color {
+            write_png(color, &path("color.png"))?;
+        }
+
+        let timestamp = |image: &Option<Image>| {
+            image.as_ref().map(|image| image.get_device_timestamp_usec().to_string()).unwrap_or_default()
+        };
+        writeln!(timestamps, "{},{},{},{}", frame, timestamp(&depth), timestamp(&ir), timestamp(&color))?;
+        frame += 1;
+    }
+    timestamps.flush()?;
+
+    println!("{}: {} frames", output.display(), frame);
+    Ok(())
+}
+
+/// Check the `--from`/`--to` range, in seconds from the start of the recording.
+fn check_range(from_secs: f64, to_secs: Option<f64>) -> Result<(), Box<dyn Error>> {
+    for (key, secs) in [("--from", Some(from_secs)), ("--to", to_secs)].iter() {
+        if let Some(secs) = secs {
+            if !secs.is_finite() || *secs < 0.0 {
+                return Err(format!("invalid {}: {} must be a non-negative number of seconds", key, secs).into());
+            }
+        }
+    }
+    match to_secs {
+        Some(to_secs) if to_secs < from_secs =>
+            Err(format!("invalid --to: {} is before --from {}", to_secs, from_secs).into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_range_must_be_ordered_and_non_negative() {
+        assert!(check_range(0.0, None).is_ok());
+        assert!(check_range(1.5, Some(1.5)).is_ok());
+        assert!(check_range(1.0, Some(2.0)).is_ok());
+
+        assert!(check_range(2.0, Some(1.0)).is_err());
+        assert!(check_range(-1.0, None).is_err());
+        assert!(check_range(0.0, Some(-1.0)).is_err());
+        assert!(check_range(f64::NAN, None).is_err());
+        assert_eq!(check_range(2.0, Some(1.0)).unwrap_err().to_string(), "invalid --to: 1 is before --from 2");
+    }
+}
@@ -0,0 +1,64 @@
+//! `kinect snapshot`: saving the images of one capture.
+//!
+//! Depth and IR images are written as 16-bit PGM (depth in millimeters), BGRA color as PNG and
+//! MJPG color as the JPEG the camera produced, all readable without any SDK.
+
+use crate::config::{ConfigurationArgs, DeviceArgs};
+use crate::image_files::{write_pgm16, write_png};
+use kinect::ImageFormat;
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn snapshot(device_args: &DeviceArgs,
+                configuration: &ConfigurationArgs,
+                output: &Path,
+                warmup: u32,
+                timeout_ms: i32) -> Result<(), Box<dyn Error>>
+{
+    let configuration = configuration.to_configuration()?;
+    let device = device_args.open()?;
+    let serial_number = device.get_serial_number()?;
+
+    device.start_cameras(&configuration)?;
+    let capture = (0..warmup).try_for_each(|_| device.get_capture(timeout_ms).map(drop))
+        .and_then(|_| device.get_capture(timeout_ms));
+    device.stop_cameras();
+    let capture = capture?;
+
+    fs::create_dir_all(output)?;
+    let path = |name: &str| -> PathBuf { output.join(format!("{}_{}", serial_number, name)) };
+
+    let mut saved = Vec::new();
+    if let Some(depth) = capture.get_depth_image() {
+        let file = path("depth.pgm");
+        write_pgm16(&depth, &file)?;
+        saved.push(file);
+    }
+    if let Some(ir) = capture.get_ir_image() {
+        let file = path("ir.pgm");
+        write_pgm16(&ir, &file)?;
+        saved.push(file);
+    }
+    if let Some(color) = capture.get_color_image() {
+        match color.get_format() {
+            ImageFormat::ColorBgra32 => {
+                let file = path("color.png");
+                write_png(&color, &file)?;
+                saved.push(file);
+            },
+            ImageFormat::ColorMjpg => {
+                let file = path("color.jpg");
+                fs::write(&file, color.get_data())?;
+                saved.push(file);
+            },
+            format => eprintln!("skipping color image in unsupported format {:?}", format),
+        }
+    }
+
+    for file in saved {
+        println!("{}", file.display());
+    }
+    Ok(())
+}
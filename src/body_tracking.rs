@@ -0,0 +1,350 @@
+//! Body tracking with the Azure Kinect Body Tracking SDK (libk4abt).
+//!
+//! The SDK isn't covered by `k4a-sys`, so the small part of its API used here is declared in
+//! `ffi` below, following `k4abt.h` and `k4abttypes.h` from SDK 1.1.
+
+use crate::error::{BodyTrackerCreateError, BodyTrackerQueueError};
+use crate::Calibration;
+use crate::Capture;
+use crate::Image;
+use crate::motion_export::SkeletonSample;
+use crate::{Body, Joint, JointConfidenceLevel, Skeleton, JOINT_COUNT};
+
+use k4a_sys_temp as k4a_sys;
+use std::ffi::CString;
+use std::ptr::null_mut;
+
+/// The value of body index map pixels that aren't part of any body.
+pub const BODY_INDEX_MAP_BACKGROUND: u8 = 255;
+
+impl From<&ffi::k4abt_skeleton_t> for Skeleton {
+    fn from(skeleton: &ffi::k4abt_skeleton_t) -> Self {
+        let mut joints = [Joint {
+            position: [0.0; 3],
+            orientation: [1.0, 0.0, 0.0, 0.0],
+            confidence_level: JointConfidenceLevel::None,
+        }; JOINT_COUNT];
+
+        for (joint, raw) in joints.iter_mut().zip(skeleton.joints.iter()) {
+            joint.position = raw.position;
+            joint.orientation = raw.orientation;
+            joint.confidence_level = JointConfidenceLevel::from(raw.confidence_level as u32);
+        }
+
+        Self { joints }
+    }
+}
+
+/// Mounting orientation of the sensor, which helps the tracker find bodies.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SensorOrientation {
+    Default,
+    Clockwise90,
+    CounterClockwise90,
+    Flip180,
+}
+
+/// Where the tracker runs its neural network.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ProcessingMode {
+    /// The SDK's default GPU backend for the platform.
+    Gpu,
+    Cpu,
+    GpuCuda,
+    GpuTensorRt,
+    GpuDirectMl,
+}
+
+/// Settings for creating a `BodyTracker`.
+#[derive(Debug, Clone)]
+pub struct BodyTrackerConfiguration {
+    pub sensor_orientation: SensorOrientation,
+    pub processing_mode: ProcessingMode,
+    /// The GPU to use for GPU processing modes.
+    pub gpu_device_id: i32,
+    /// A model file to use instead of the SDK's default model (eg. the lite model).
+    pub model_path: Option<String>,
+}
+
+impl Default for BodyTrackerConfiguration {
+    /// Mirrors `K4ABT_TRACKER_CONFIG_DEFAULT`.
+    fn default() -> Self {
+        Self {
+            sensor_orientation: SensorOrientation::Default,
+            processing_mode: ProcessingMode::Gpu,
+            gpu_device_id: 0,
+            model_path: None,
+        }
+    }
+}
+
+/// A body tracker, fed captures from a device with the depth camera running.
+///
+/// Captures are processed asynchronously: each capture enqueued with `enqueue_capture` yields one
+/// body frame from `pop_result`, in order.
+pub struct BodyTracker {
+    tracker: ffi::k4abt_tracker_t,
+}
+
+// The tracker's queue functions may be called from different threads.
+unsafe impl Send for BodyTracker {}
+unsafe impl Sync for BodyTracker {}
+
+impl BodyTracker {
+    /// Create a tracker for a device's calibration.
+    pub fn create(calibration: &Calibration,
+                  configuration: &BodyTrackerConfiguration) -> Result<Self, BodyTrackerCreateError>
+    {
+        let model_path = match &configuration.model_path {
+            Some(path) => Some(CString::new(path.as_str()).map_err(|_| BodyTrackerCreateError::InvalidModelPathError)?),
+            None => None,
+        };
+
+        let raw_configuration = ffi::k4abt_tracker_configuration_t {
+            sensor_orientation: match configuration.sensor_orientation {
+                SensorOrientation::Default => 0,
+                SensorOrientation::Clockwise90 => 1,
+                SensorOrientation::CounterClockwise90 => 2,
+                SensorOrientation::Flip180 => 3,
+            },
+            processing_mode: match configuration.processing_mode {
+                ProcessingMode::Gpu => 0,
+                ProcessingMode::Cpu => 1,
+                ProcessingMode::GpuCuda => 2,
+                ProcessingMode::GpuTensorRt => 3,
+                ProcessingMode::GpuDirectMl => 4,
+            },
+            gpu_device_id: configuration.gpu_device_id,
+            model_path: model_path.as_ref().map(|path| path.as_ptr()).unwrap_or(std::ptr::null()),
+        };
+
+        let mut tracker = null_mut();
+
+        let result = unsafe {
+            ffi::k4abt_tracker_create(&calibration.0, raw_configuration, &mut tracker)
+        };
+
+        match result {
+            k4a_sys::k4a_result_t_K4A_RESULT_SUCCEEDED => Ok(Self { tracker }),
+            k4a_sys::k4a_result_t_K4A_RESULT_FAILED => Err(BodyTrackerCreateError::FailedError),
+            // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
+            // Linux uses u32 and Windows uses i32.
+            // This should be fixed in the `k4a-sys` build script.
+            _ => Err(BodyTrackerCreateError::UnexpectedError(result as i32)),
+        }
+    }
+
+    /// Set how much joint positions are smoothed across frames, from 0.0 (none) to 1.0 (full).
+    pub fn set_temporal_smoothing(&self, smoothing_factor: f32) {
+        unsafe {
+            ffi::k4abt_tracker_set_temporal_smoothing(self.tracker, smoothing_factor)
+        }
+    }
+
+    /// Add a capture to the tracker's input queue, waiting up to `timeout_ms` for room (-1 waits
+    /// forever, 0 doesn't wait). The capture must have a depth image.
+    pub fn enqueue_capture(&self, capture: &Capture, timeout_ms: i32) -> Result<(), BodyTrackerQueueError> {
+        let result = unsafe {
+            ffi::k4abt_tracker_enqueue_capture(self.tracker, capture.get_handle(), timeout_ms)
+        };
+        BodyTrackerQueueError::check(result, timeout_ms)
+    }
+
+    /// Take the next body frame from the tracker's output queue, waiting up to `timeout_ms` for
+    /// one (-1 waits forever, 0 doesn't wait).
+    pub fn pop_result(&self, timeout_ms: i32) -> Result<BodyFrame, BodyTrackerQueueError> {
+        let mut frame = null_mut();
+        let result = unsafe {
+            ffi::k4abt_tracker_pop_result(self.tracker, &mut frame, timeout_ms)
+        };
+        BodyTrackerQueueError::check(result, timeout_ms).map(|_| BodyFrame(frame))
+    }
+
+    /// Stop accepting captures and wake any threads blocked in `enqueue_capture` or `pop_result`.
+    /// Frames already queued can still be popped.
+    pub fn shutdown(&self) {
+        unsafe {
+            ffi::k4abt_tracker_shutdown(self.tracker)
+        }
+    }
+}
+
+impl Drop for BodyTracker {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::k4abt_tracker_destroy(self.tracker);
+        }
+        self.tracker = null_mut();
+    }
+}
+
+/// The bodies found in one capture.
+#[derive(Debug)]
+pub struct BodyFrame(pub ffi::k4abt_frame_t);
+
+// These are ref-counted handles and are safe to Send.
+unsafe impl Send for BodyFrame {}
+
+impl BodyFrame {
+    /// The number of bodies in the frame.
+    pub fn get_num_bodies(&self) -> usize {
+        unsafe {
+            ffi::k4abt_frame_get_num_bodies(self.0) as usize
+        }
+    }
+
+    /// The body at `index` (from 0 to `get_num_bodies() - 1`).
+    pub fn get_body(&self, index: usize) -> Option<Body> {
+        let mut skeleton = std::mem::MaybeUninit::<ffi::k4abt_skeleton_t>::uninit();
+
+        let result = unsafe {
+            ffi::k4abt_frame_get_body_skeleton(self.0, index as u32, skeleton.as_mut_ptr())
+        };
+
+        if result != k4a_sys::k4a_result_t_K4A_RESULT_SUCCEEDED {
+            return None;
+        }
+
+        let skeleton = unsafe { skeleton.assume_init() };
+        let id = unsafe { ffi::k4abt_frame_get_body_id(self.0, index as u32) };
+
+        Some(Body { id, skeleton: Skeleton::from(&skeleton) })
+    }
+
+    /// Every body in the frame.
+    pub fn get_bodies(&self) -> Vec<Body> {
+        (0..self.get_num_bodies()).filter_map(|index| self.get_body(index)).collect()
+    }
+
+    /// Every body in the frame, stamped with the frame's device timestamp for `motion_export`.
+    pub fn get_skeleton_samples(&self) -> Vec<SkeletonSample> {
+        let device_timestamp_usec = self.get_device_timestamp_usec();
+        self.get_bodies().into_iter().map(|body| SkeletonSample { device_timestamp_usec, body }).collect()
+    }
+
+    /// The device timestamp of the capture the bodies were found in.
+    pub fn get_device_timestamp_usec(&self) -> u64 {
+        unsafe {
+            ffi::k4abt_frame_get_device_timestamp_usec(self.0)
+        }
+    }
+
+    /// The system timestamp of the capture the bodies were found in.
+    pub fn get_system_timestamp_nsec(&self) -> u64 {
+        unsafe {
+            ffi::k4abt_frame_get_system_timestamp_nsec(self.0)
+        }
+    }
+
+    /// A `Custom8` image the size of the depth image, where each pixel is the index (in this
+    /// frame) of the body it belongs to, or `BODY_INDEX_MAP_BACKGROUND`.
+    pub fn get_body_index_map(&self) -> Option<Image> {
+        let image = unsafe {
+            ffi::k4abt_frame_get_body_index_map(self.0)
+        };
+        if image.is_null() {
+            return None;
+        }
+        Some(Image(image))
+    }
+
+    /// The capture the bodies were found in.
+    pub fn get_capture(&self) -> Option<Capture> {
+        let capture = unsafe {
+            ffi::k4abt_frame_get_capture(self.0)
+        };
+        if capture.is_null() {
+            return None;
+        }
+        Some(Capture(capture))
+    }
+}
+
+// Remove a libk4abt frame refcount on every drop.
+impl Drop for BodyFrame {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::k4abt_frame_release(self.0);
+        }
+        self.0 = null_mut();
+    }
+}
+
+// Handles are refcounted by libk4abt.
+impl Clone for BodyFrame {
+    fn clone(&self) -> Self {
+        unsafe {
+            ffi::k4abt_frame_reference(self.0);
+        }
+        Self(self.0)
+    }
+}
+
+#[allow(non_camel_case_types)]
+pub mod ffi {
+    use k4a_sys_temp as k4a_sys;
+    use std::os::raw::c_char;
+
+    #[repr(C)]
+    pub struct _k4abt_tracker_t {
+        _rsvd: usize,
+    }
+    pub type k4abt_tracker_t = *mut _k4abt_tracker_t;
+
+    #[repr(C)]
+    pub struct _k4abt_frame_t {
+        _rsvd: usize,
+    }
+    pub type k4abt_frame_t = *mut _k4abt_frame_t;
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct k4abt_tracker_configuration_t {
+        pub sensor_orientation: i32,
+        pub processing_mode: i32,
+        pub gpu_device_id: i32,
+        pub model_path: *const c_char,
+    }
+
+    /// `k4a_float3_t` and `k4a_quaternion_t` are unions over float arrays, laid out as these.
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct k4abt_joint_t {
+        pub position: [f32; 3],
+        pub orientation: [f32; 4],
+        pub confidence_level: i32,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct k4abt_skeleton_t {
+        pub joints: [k4abt_joint_t; crate::JOINT_COUNT],
+    }
+
+    #[link(name = "k4abt")]
+    extern "C" {
+        pub fn k4abt_tracker_create(sensor_calibration: *const k4a_sys::k4a_calibration_t,
+                                    config: k4abt_tracker_configuration_t,
+                                    tracker_handle: *mut k4abt_tracker_t) -> k4a_sys::k4a_result_t;
+        pub fn k4abt_tracker_destroy(tracker_handle: k4abt_tracker_t);
+        pub fn k4abt_tracker_set_temporal_smoothing(tracker_handle: k4abt_tracker_t, smoothing_factor: f32);
+        pub fn k4abt_tracker_enqueue_capture(tracker_handle: k4abt_tracker_t,
+                                             sensor_capture_handle: k4a_sys::k4a_capture_t,
+                                             timeout_in_ms: i32) -> k4a_sys::k4a_wait_result_t;
+        pub fn k4abt_tracker_pop_result(tracker_handle: k4abt_tracker_t,
+                                        body_frame_handle: *mut k4abt_frame_t,
+                                        timeout_in_ms: i32) -> k4a_sys::k4a_wait_result_t;
+        pub fn k4abt_tracker_shutdown(tracker_handle: k4abt_tracker_t);
+        pub fn k4abt_frame_release(body_frame_handle: k4abt_frame_t);
+        pub fn k4abt_frame_reference(body_frame_handle: k4abt_frame_t);
+        pub fn k4abt_frame_get_num_bodies(body_frame_handle: k4abt_frame_t) -> u32;
+        pub fn k4abt_frame_get_body_skeleton(body_frame_handle: k4abt_frame_t,
+                                             index: u32,
+                                             skeleton: *mut k4abt_skeleton_t) -> k4a_sys::k4a_result_t;
+        pub fn k4abt_frame_get_body_id(body_frame_handle: k4abt_frame_t, index: u32) -> u32;
+        pub fn k4abt_frame_get_device_timestamp_usec(body_frame_handle: k4abt_frame_t) -> u64;
+        pub fn k4abt_frame_get_system_timestamp_nsec(body_frame_handle: k4abt_frame_t) -> u64;
+        pub fn k4abt_frame_get_body_index_map(body_frame_handle: k4abt_frame_t) -> k4a_sys::k4a_image_t;
+        pub fn k4abt_frame_get_capture(body_frame_handle: k4abt_frame_t) -> k4a_sys::k4a_capture_t;
+    }
+}
@@ -1,36 +1,198 @@
 #![allow(unused)]
 
+use crate::error::CalibrationConversionError;
+use crate::Image;
+
 use k4a_sys_temp as k4a_sys;
+use std::os::raw::c_int;
 
 #[derive(Clone)]
 pub struct Calibration(pub k4a_sys::k4a_calibration_t);
 
-impl Calibration {
-    pub fn default() -> Self {
+/// The sensors a `Calibration` describes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CalibrationType {
+    /// Depth camera (which also captures the IR images).
+    Depth,
+    /// Color camera.
+    Color,
+    /// Gyroscope sensor.
+    Gyro,
+    /// Accelerometer sensor.
+    Accel,
+}
+
+impl CalibrationType {
+    pub fn to_k4a(&self) -> k4a_sys::k4a_calibration_type_t {
+        match self {
+            CalibrationType::Depth => k4a_sys::k4a_calibration_type_t_K4A_CALIBRATION_TYPE_DEPTH,
+            CalibrationType::Color => k4a_sys::k4a_calibration_type_t_K4A_CALIBRATION_TYPE_COLOR,
+            CalibrationType::Gyro => k4a_sys::k4a_calibration_type_t_K4A_CALIBRATION_TYPE_GYRO,
+            CalibrationType::Accel => k4a_sys::k4a_calibration_type_t_K4A_CALIBRATION_TYPE_ACCEL,
+        }
+    }
+}
+
+/// A rigid transformation between two sensor coordinate systems.
+///
+/// A point is transformed from the source to the target coordinate system as
+/// `target = rotation * source + translation`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Extrinsics {
+    /// 3x3 rotation matrix stored in row major order.
+    pub rotation: [f32; 9],
+    /// Translation vector, x,y,z (in millimeters).
+    pub translation: [f32; 3],
+}
+
+impl From<k4a_sys::k4a_calibration_extrinsics_t> for Extrinsics {
+    fn from(extrinsics: k4a_sys::k4a_calibration_extrinsics_t) -> Self {
+        Self {
+            rotation: extrinsics.rotation,
+            translation: extrinsics.translation,
+        }
+    }
+}
+
+impl From<Extrinsics> for k4a_sys::k4a_calibration_extrinsics_t {
+    fn from(extrinsics: Extrinsics) -> Self {
+        Self {
+            rotation: extrinsics.rotation,
+            translation: extrinsics.translation,
+        }
+    }
+}
+
+/// A camera's intrinsics: its pinhole parameters (in pixels) and lens distortion coefficients.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Intrinsics {
+    pub fx: f32,
+    pub fy: f32,
+    pub cx: f32,
+    pub cy: f32,
+    /// Radial distortion coefficients.
+    pub k1: f32,
+    pub k2: f32,
+    pub k3: f32,
+    pub k4: f32,
+    pub k5: f32,
+    pub k6: f32,
+    /// Tangential distortion coefficients.
+    pub p1: f32,
+    pub p2: f32,
+    /// Center of distortion, for the Brown-Conrady model.
+    pub codx: f32,
+    pub cody: f32,
+}
+
+impl Intrinsics {
+    /// The camera matrix `[fx 0 cx; 0 fy cy; 0 0 1]` in row major order.
+    pub fn camera_matrix(&self) -> [f32; 9] {
+        [self.fx, 0.0, self.cx,
+         0.0, self.fy, self.cy,
+         0.0, 0.0, 1.0]
+    }
+
+    /// The distortion coefficients in the order OpenCV's rational model takes them:
+    /// k1, k2, p1, p2, k3, k4, k5, k6.
+    pub fn distortion_coefficients(&self) -> [f32; 8] {
+        [self.k1, self.k2, self.p1, self.p2, self.k3, self.k4, self.k5, self.k6]
+    }
+}
+
+impl From<k4a_sys::k4a_calibration_intrinsics_t> for Intrinsics {
+    fn from(intrinsics: k4a_sys::k4a_calibration_intrinsics_t) -> Self {
+        // NB: This is a union field, so we have to use unsafe access
+        let p = unsafe { intrinsics.parameters.param };
+        Self {
+            fx: p.fx,
+            fy: p.fy,
+            cx: p.cx,
+            cy: p.cy,
+            k1: p.k1,
+            k2: p.k2,
+            k3: p.k3,
+            k4: p.k4,
+            k5: p.k5,
+            k6: p.k6,
+            p1: p.p1,
+            p2: p.p2,
+            codx: p.codx,
+            cody: p.cody,
+        }
+    }
+}
+
+/// A 2D point type the calibration conversions take and return: `[f32; 2]`, or with the
+/// `nalgebra` and `glam` features, `Point2<f32>`, `Vector2<f32>` and `Vec2`.
+pub trait CalibrationPoint2: Copy {
+    /// The 3D point type of the same kind.
+    type Point3: CalibrationPoint3;
+
+    fn to_array(self) -> [f32; 2];
+    fn from_array(array: [f32; 2]) -> Self;
+}
+
+/// A 3D point type the calibration conversions take and return: `[f32; 3]`, or with the
+/// `nalgebra` and `glam` features, `Point3<f32>`, `Vector3<f32>` and `Vec3`.
+pub trait CalibrationPoint3: Copy {
+    /// The 2D point type of the same kind.
+    type Point2: CalibrationPoint2;
+
+    fn to_array(self) -> [f32; 3];
+    fn from_array(array: [f32; 3]) -> Self;
+}
+
+impl CalibrationPoint2 for [f32; 2] {
+    type Point3 = [f32; 3];
+
+    fn to_array(self) -> [f32; 2] {
+        self
+    }
+
+    fn from_array(array: [f32; 2]) -> Self {
+        array
+    }
+}
+
+impl CalibrationPoint3 for [f32; 3] {
+    type Point2 = [f32; 2];
+
+    fn to_array(self) -> [f32; 3] {
+        self
+    }
+
+    fn from_array(array: [f32; 3]) -> Self {
+        array
+    }
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
         let extrinsics = k4a_sys::_k4a_calibration_extrinsics_t {
             rotation: [0.0f32, 0.0f32, 0.0f32, 0.0f32, 0.0f32, 0.0f32, 0.0f32, 0.0f32, 0.0f32],
             translation: [0.0f32, 0.0f32, 0.0f32],
         };
 
         let extrinsics_4 = [
-            extrinsics.clone(),
-            extrinsics.clone(),
-            extrinsics.clone(),
-            extrinsics.clone(),
+            extrinsics,
+            extrinsics,
+            extrinsics,
+            extrinsics,
         ];
 
         let extrinsics_4_4 = [
-            extrinsics_4.clone(),
-            extrinsics_4.clone(),
-            extrinsics_4.clone(),
-            extrinsics_4.clone(),
+            extrinsics_4,
+            extrinsics_4,
+            extrinsics_4,
+            extrinsics_4,
         ];
 
         let camera_calibration = k4a_sys::_k4a_calibration_camera_t {
             resolution_width: 0,
             resolution_height: 0,
             metric_radius: 0.0f32,
-            extrinsics: extrinsics.clone(),
+            extrinsics,
             intrinsics: k4a_sys::_k4a_calibration_intrinsics_t {
                 type_: 0,
                 parameter_count: 0,
@@ -57,14 +219,16 @@ impl Calibration {
         };
 
         Self(k4a_sys::k4a_calibration_t {
-            color_camera_calibration: camera_calibration.clone(),
-            depth_camera_calibration: camera_calibration.clone(),
+            color_camera_calibration: camera_calibration,
+            depth_camera_calibration: camera_calibration,
             color_resolution: 0,
             depth_mode: 0,
             extrinsics: extrinsics_4_4,
         })
     }
+}
 
+impl Calibration {
     /// Return the Calibration's color camera resolution width.
     pub fn color_camera_resolution_width(&self) -> i32 {
         self.0.color_camera_calibration.resolution_width
@@ -84,6 +248,155 @@ impl Calibration {
         self.0.depth_camera_calibration.resolution_height
     }
 
+    /// Return the intrinsic and extrinsic calibration of the color or depth camera.
+    ///
+    /// Returns None for the IMU sensors, which have no camera calibration.
+    pub fn get_camera_calibration(&self, camera: CalibrationType) -> Option<&k4a_sys::k4a_calibration_camera_t> {
+        match camera {
+            CalibrationType::Depth => Some(&self.0.depth_camera_calibration),
+            CalibrationType::Color => Some(&self.0.color_camera_calibration),
+            _ => None,
+        }
+    }
+
+    /// Return the intrinsics of the color or depth camera.
+    ///
+    /// Returns None for the IMU sensors, which have no camera calibration.
+    pub fn get_intrinsics(&self, camera: CalibrationType) -> Option<Intrinsics> {
+        self.get_camera_calibration(camera)
+            .map(|camera_calibration| camera_calibration.intrinsics.into())
+    }
+
+    /// Transform a 3D point (in millimeters) from the `source` to the `target` sensor coordinate
+    /// system.
+    pub fn convert_3d_to_3d<P: CalibrationPoint3>(&self,
+                                                  point_mm: P,
+                                                  source: CalibrationType,
+                                                  target: CalibrationType)
+                                                  -> Result<P, CalibrationConversionError>
+    {
+        let source_point = k4a_sys::k4a_float3_t { v: point_mm.to_array() };
+        let mut target_point = k4a_sys::k4a_float3_t { v: [0.0; 3] };
+
+        let result = unsafe {
+            k4a_sys::k4a_calibration_3d_to_3d(&self.0, &source_point, source.to_k4a(), target.to_k4a(),
+                                              &mut target_point)
+        };
+        CalibrationConversionError::check(result)?;
+
+        Ok(P::from_array(unsafe { target_point.v }))
+    }
+
+    /// Unproject a pixel of the `source` camera, at a depth in millimeters, to a 3D point in the
+    /// `target` sensor coordinate system.
+    ///
+    /// Returns None if the pixel is outside the camera's valid field of view.
+    pub fn convert_2d_to_3d<P: CalibrationPoint2>(&self,
+                                                  point: P,
+                                                  depth_mm: f32,
+                                                  source: CalibrationType,
+                                                  target: CalibrationType)
+                                                  -> Result<Option<P::Point3>, CalibrationConversionError>
+    {
+        let source_point = k4a_sys::k4a_float2_t { v: point.to_array() };
+        let mut target_point = k4a_sys::k4a_float3_t { v: [0.0; 3] };
+        let mut valid: c_int = 0;
+
+        let result = unsafe {
+            k4a_sys::k4a_calibration_2d_to_3d(&self.0, &source_point, depth_mm, source.to_k4a(), target.to_k4a(),
+                                              &mut target_point, &mut valid)
+        };
+        CalibrationConversionError::check(result)?;
+
+        if valid == 0 {
+            return Ok(None);
+        }
+        Ok(Some(P::Point3::from_array(unsafe { target_point.v })))
+    }
+
+    /// Project a 3D point (in millimeters) in the `source` sensor coordinate system to a pixel of
+    /// the `target` camera.
+    ///
+    /// Returns None if the point is outside the camera's valid field of view.
+    pub fn convert_3d_to_2d<P: CalibrationPoint3>(&self,
+                                                  point_mm: P,
+                                                  source: CalibrationType,
+                                                  target: CalibrationType)
+                                                  -> Result<Option<P::Point2>, CalibrationConversionError>
+    {
+        let source_point = k4a_sys::k4a_float3_t { v: point_mm.to_array() };
+        let mut target_point = k4a_sys::k4a_float2_t { v: [0.0; 2] };
+        let mut valid: c_int = 0;
+
+        let result = unsafe {
+            k4a_sys::k4a_calibration_3d_to_2d(&self.0, &source_point, source.to_k4a(), target.to_k4a(),
+                                              &mut target_point, &mut valid)
+        };
+        CalibrationConversionError::check(result)?;
+
+        if valid == 0 {
+            return Ok(None);
+        }
+        Ok(Some(P::Point2::from_array(unsafe { target_point.v })))
+    }
+
+    /// Map a pixel of the `source` camera, at a depth in millimeters, to a pixel of the `target`
+    /// camera.
+    ///
+    /// Returns None if either pixel is outside its camera's valid field of view.
+    pub fn convert_2d_to_2d<P: CalibrationPoint2>(&self,
+                                                  point: P,
+                                                  depth_mm: f32,
+                                                  source: CalibrationType,
+                                                  target: CalibrationType)
+                                                  -> Result<Option<P>, CalibrationConversionError>
+    {
+        let source_point = k4a_sys::k4a_float2_t { v: point.to_array() };
+        let mut target_point = k4a_sys::k4a_float2_t { v: [0.0; 2] };
+        let mut valid: c_int = 0;
+
+        let result = unsafe {
+            k4a_sys::k4a_calibration_2d_to_2d(&self.0, &source_point, depth_mm, source.to_k4a(), target.to_k4a(),
+                                              &mut target_point, &mut valid)
+        };
+        CalibrationConversionError::check(result)?;
+
+        if valid == 0 {
+            return Ok(None);
+        }
+        Ok(Some(P::from_array(unsafe { target_point.v })))
+    }
+
+    /// Map a color camera pixel to the depth camera pixel that sees the same point, searching
+    /// along the pixel's epipolar line in `depth_image` (a `Depth16` image).
+    ///
+    /// Returns None if no depth pixel matches.
+    pub fn convert_color_2d_to_depth_2d<P: CalibrationPoint2>(&self,
+                                                              point: P,
+                                                              depth_image: &Image)
+                                                              -> Result<Option<P>, CalibrationConversionError>
+    {
+        let source_point = k4a_sys::k4a_float2_t { v: point.to_array() };
+        let mut target_point = k4a_sys::k4a_float2_t { v: [0.0; 2] };
+        let mut valid: c_int = 0;
+
+        let result = unsafe {
+            k4a_sys::k4a_calibration_color_2d_to_depth_2d(&self.0, &source_point, depth_image.get_handle(),
+                                                          &mut target_point, &mut valid)
+        };
+        CalibrationConversionError::check(result)?;
+
+        if valid == 0 {
+            return Ok(None);
+        }
+        Ok(Some(P::from_array(unsafe { target_point.v })))
+    }
+
+    /// Return the transformation from the `source` to the `target` sensor coordinate system.
+    pub fn get_extrinsics(&self, source: CalibrationType, target: CalibrationType) -> Extrinsics {
+        self.0.extrinsics[source.to_k4a() as usize][target.to_k4a() as usize].into()
+    }
+
     // TODO: Make this the `Debug` trait output instead.
     pub fn debug_print(&self) {
         println!("===== CALIBRATION =====");
@@ -142,3 +455,47 @@ impl Calibration {
         println!("==========");
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A rotation taking x to y, y to z and z to x, which isn't its own transpose, and a translation.
+    pub(crate) fn extrinsics() -> Extrinsics {
+        Extrinsics {
+            rotation: [0.0, 0.0, 1.0,
+                       1.0, 0.0, 0.0,
+                       0.0, 1.0, 0.0],
+            translation: [10.0, -20.0, 30.0],
+        }
+    }
+
+    /// `target = rotation * source + translation`, worked out by hand.
+    pub(crate) fn transform(extrinsics: &Extrinsics, [x, y, z]: [f32; 3]) -> [f32; 3] {
+        let r = &extrinsics.rotation;
+        let t = &extrinsics.translation;
+        [r[0] * x + r[1] * y + r[2] * z + t[0],
+         r[3] * x + r[4] * y + r[5] * z + t[1],
+         r[6] * x + r[7] * y + r[8] * z + t[2]]
+    }
+
+    pub(crate) fn intrinsics() -> Intrinsics {
+        Intrinsics {
+            fx: 500.0, fy: 510.0, cx: 320.0, cy: 240.0,
+            k1: 0.1, k2: -0.2, k3: 0.05, k4: 0.0, k5: 0.0, k6: 0.0,
+            p1: 0.001, p2: -0.002,
+            codx: 0.0, cody: 0.0,
+        }
+    }
+
+    pub(crate) fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+        for (a, e) in actual.iter().zip(&expected) {
+            assert!((a - e).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn transform_by_hand_permutes_then_translates() {
+        assert_eq!(transform(&extrinsics(), [1.0, 2.0, 3.0]), [13.0, -19.0, 32.0]);
+    }
+}
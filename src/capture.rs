@@ -30,9 +30,7 @@ impl Clone for Capture {
         unsafe {
             k4a_sys::k4a_capture_reference(handle);
         }
-        Self {
-            0: handle,
-        }
+        Self(handle)
     }
 
     fn clone_from(&mut self, source: &Self) {
@@ -82,6 +80,17 @@ impl Capture {
         Some(Image(image))
     }
 
+    /// Get the temperature of the device when the capture was taken, in degrees Celsius (if known).
+    pub fn get_temperature_c(&self) -> Option<f32> {
+        let temperature_c = unsafe {
+            k4a_sys::k4a_capture_get_temperature_c(self.0)
+        };
+        if temperature_c.is_nan() {
+            return None;
+        }
+        Some(temperature_c)
+    }
+
     /// Returns the underlying opaque handle *without* an additional refcount.
     /// Do not deallocate it.
     pub fn get_handle(&self) -> k4a_sys::k4a_capture_t {
@@ -0,0 +1,378 @@
+use crate::Capture;
+use crate::SyncedRig;
+
+use std::collections::VecDeque;
+
+/// Default number of items a stream may queue while waiting for the other streams.
+const DEFAULT_MAX_PENDING: usize = 30;
+
+/// Something with a device timestamp, such as a `Capture`.
+///
+/// This lets `CaptureMatcher` group anything by device timestamp, including synthetic captures.
+pub trait DeviceTimestamped {
+    /// The device timestamp in microseconds, if known.
+    fn get_device_timestamp_usec(&self) -> Option<u64>;
+}
+
+/// Captures are timestamped by their color image, falling back to their depth and then IR images.
+impl DeviceTimestamped for Capture {
+    fn get_device_timestamp_usec(&self) -> Option<u64> {
+        self.get_color_image()
+            .or_else(|| self.get_depth_image())
+            .or_else(|| self.get_ir_image())
+            .map(|image| image.get_device_timestamp_usec())
+    }
+}
+
+/// One item from every stream, with device timestamps within tolerance of each other.
+#[derive(Debug)]
+pub struct Frameset<T> {
+    /// The offset-adjusted device timestamp of the item from the first stream.
+    pub timestamp_usec: i64,
+    /// One item per stream, in stream order.
+    pub items: Vec<T>,
+}
+
+/// Why an item was not grouped into a frameset.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UnmatchedReason {
+    /// The item has no device timestamp.
+    MissingTimestamp,
+    /// The item arrived after a frameset at or past its timestamp was already emitted, or
+    /// arrived out of order within its stream.
+    Late,
+    /// Every other stream moved past the item's timestamp without a counterpart for it.
+    NoCounterpart,
+    /// The item's stream queued more than the allowed number of items waiting for other streams.
+    Overflow,
+}
+
+/// An item that was not grouped into a frameset.
+#[derive(Debug)]
+pub struct Unmatched<T> {
+    /// The stream the item was pushed to.
+    pub stream: usize,
+    /// The offset-adjusted device timestamp of the item (if any).
+    pub timestamp_usec: Option<i64>,
+    pub reason: UnmatchedReason,
+    pub item: T,
+}
+
+/// The outcome of pushing an item to a `CaptureMatcher`.
+#[derive(Debug)]
+pub enum MatchOutput<T> {
+    /// Items from every stream were grouped.
+    Frameset(Frameset<T>),
+    /// An item could not be grouped.
+    Unmatched(Unmatched<T>),
+}
+
+/// Running totals of a `CaptureMatcher`'s output.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct CaptureMatcherStats {
+    pub framesets: u64,
+    pub missing_timestamp: u64,
+    pub late: u64,
+    pub no_counterpart: u64,
+    pub overflow: u64,
+}
+
+/// Groups items from several streams (eg. captures from several devices) into framesets by
+/// device timestamp.
+///
+/// Each stream has an expected offset that is subtracted from its device timestamps before they
+/// are compared, eg. the subordinate delay configured for a device. Items are grouped once every
+/// stream has an item within `tolerance_usec` of the others. Items pushed to a stream must be in
+/// timestamp order, which is how devices deliver them.
+pub struct CaptureMatcher<T = Capture> {
+    queues: Vec<VecDeque<(i64, T)>>,
+    offsets_usec: Vec<i64>,
+    tolerance_usec: i64,
+    max_pending: usize,
+    last_frameset_usec: Option<i64>,
+    stats: CaptureMatcherStats,
+}
+
+impl<T: DeviceTimestamped> CaptureMatcher<T> {
+    /// Create a matcher with one stream per offset.
+    pub fn new(offsets_usec: Vec<i64>, tolerance_usec: i64) -> Self {
+        Self {
+            queues: offsets_usec.iter().map(|_| VecDeque::new()).collect(),
+            offsets_usec,
+            tolerance_usec,
+            max_pending: DEFAULT_MAX_PENDING,
+            last_frameset_usec: None,
+            stats: CaptureMatcherStats::default(),
+        }
+    }
+
+    /// Set the number of items a stream may queue before its oldest items are dropped.
+    pub fn set_max_pending(&mut self, max_pending: usize) {
+        self.max_pending = max_pending.max(1);
+    }
+
+    /// The number of streams.
+    pub fn stream_count(&self) -> usize {
+        self.queues.len()
+    }
+
+    /// Running totals of framesets and unmatched items.
+    pub fn get_stats(&self) -> CaptureMatcherStats {
+        self.stats
+    }
+
+    /// Push an item to a stream, returning any framesets completed and items dropped as a result.
+    ///
+    /// Panics if `stream` is out of range.
+    pub fn push(&mut self, stream: usize, item: T) -> Vec<MatchOutput<T>> {
+        let mut output = Vec::new();
+
+        let timestamp_usec = match item.get_device_timestamp_usec() {
+            Some(timestamp_usec) => timestamp_usec as i64 - self.offsets_usec[stream],
+            None => {
+                self.emit_unmatched(&mut output, stream, None, UnmatchedReason::MissingTimestamp, item);
+                return output;
+            },
+        };
+
+        let behind_last_frameset = self.last_frameset_usec
+            .map(|last| timestamp_usec <= last + self.tolerance_usec)
+            .unwrap_or(false);
+        let out_of_order = self.queues[stream].back()
+            .map(|(last, _)| timestamp_usec <= *last)
+            .unwrap_or(false);
+
+        if behind_last_frameset || out_of_order {
+            self.emit_unmatched(&mut output, stream, Some(timestamp_usec), UnmatchedReason::Late, item);
+            return output;
+        }
+
+        self.queues[stream].push_back((timestamp_usec, item));
+
+        if self.queues[stream].len() > self.max_pending {
+            if let Some((timestamp_usec, item)) = self.queues[stream].pop_front() {
+                self.emit_unmatched(&mut output, stream, Some(timestamp_usec), UnmatchedReason::Overflow, item);
+            }
+        }
+
+        self.match_queued(&mut output);
+        output
+    }
+
+    /// Drop every queued item, returning them as unmatched.
+    pub fn flush(&mut self) -> Vec<MatchOutput<T>> {
+        let mut output = Vec::new();
+
+        for stream in 0..self.queues.len() {
+            while let Some((timestamp_usec, item)) = self.queues[stream].pop_front() {
+                self.emit_unmatched(&mut output, stream, Some(timestamp_usec), UnmatchedReason::NoCounterpart, item);
+            }
+        }
+
+        output
+    }
+
+    fn match_queued(&mut self, output: &mut Vec<MatchOutput<T>>) {
+        loop {
+            let heads = self.queues.iter()
+                .map(|queue| queue.front().map(|(timestamp_usec, _)| *timestamp_usec))
+                .collect::<Option<Vec<i64>>>();
+
+            let heads = match heads {
+                Some(ref heads) if !heads.is_empty() => heads,
+                _ => return, // Some stream has nothing queued yet.
+            };
+
+            let newest = heads.iter().copied().max().unwrap_or(0);
+            let mut dropped = false;
+
+            // Streams only move forward, so a head too far behind the newest head can never match.
+            for (stream, head) in heads.iter().enumerate() {
+                if *head < newest - self.tolerance_usec {
+                    if let Some((timestamp_usec, item)) = self.queues[stream].pop_front() {
+                        self.emit_unmatched(output, stream, Some(timestamp_usec), UnmatchedReason::NoCounterpart, item);
+                    }
+                    dropped = true;
+                }
+            }
+
+            if dropped {
+                continue;
+            }
+
+            let timestamp_usec = heads[0];
+            let items = self.queues.iter_mut()
+                .filter_map(|queue| queue.pop_front().map(|(_, item)| item))
+                .collect();
+
+            self.last_frameset_usec = Some(newest);
+            self.stats.framesets += 1;
+            output.push(MatchOutput::Frameset(Frameset { timestamp_usec, items }));
+        }
+    }
+
+    fn emit_unmatched(&mut self,
+                      output: &mut Vec<MatchOutput<T>>,
+                      stream: usize,
+                      timestamp_usec: Option<i64>,
+                      reason: UnmatchedReason,
+                      item: T)
+    {
+        match reason {
+            UnmatchedReason::MissingTimestamp => self.stats.missing_timestamp += 1,
+            UnmatchedReason::Late => self.stats.late += 1,
+            UnmatchedReason::NoCounterpart => self.stats.no_counterpart += 1,
+            UnmatchedReason::Overflow => self.stats.overflow += 1,
+        }
+        output.push(MatchOutput::Unmatched(Unmatched { stream, timestamp_usec, reason, item }));
+    }
+}
+
+impl CaptureMatcher<Capture> {
+    /// Create a matcher for a started rig, with the master as stream 0 followed by the subordinates
+    /// in the order of `SyncedRig::subordinates`. Offsets are the configured subordinate delays.
+    ///
+    /// NB: Captures are compared by color timestamp when present. Depth-only captures are offset by
+    /// the per-device depth delay, so those need a matcher built with `CaptureMatcher::new`.
+    pub fn for_rig(rig: &SyncedRig) -> Self {
+        let offsets_usec = std::iter::once(rig.master())
+            .chain(rig.subordinates().iter())
+            .map(|rig_device| {
+                rig_device.configuration.as_ref()
+                    .map(|configuration| configuration.0.subordinate_delay_off_master_usec as i64)
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        Self::new(offsets_usec, rig.alignment_tolerance_usec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A synthetic capture: an id to follow it through the matcher, and its device timestamp.
+    #[derive(Debug, PartialEq)]
+    struct Fake(u32, Option<u64>);
+
+    impl DeviceTimestamped for Fake {
+        fn get_device_timestamp_usec(&self) -> Option<u64> {
+            self.1
+        }
+    }
+
+    fn framesets(output: &[MatchOutput<Fake>]) -> Vec<(i64, Vec<u32>)> {
+        output.iter()
+            .filter_map(|output| match output {
+                MatchOutput::Frameset(frameset) => {
+                    Some((frameset.timestamp_usec, frameset.items.iter().map(|item| item.0).collect()))
+                },
+                MatchOutput::Unmatched(_) => None,
+            })
+            .collect()
+    }
+
+    fn unmatched(output: &[MatchOutput<Fake>]) -> Vec<(usize, Option<i64>, UnmatchedReason, u32)> {
+        output.iter()
+            .filter_map(|output| match output {
+                MatchOutput::Unmatched(unmatched) => {
+                    Some((unmatched.stream, unmatched.timestamp_usec, unmatched.reason, unmatched.item.0))
+                },
+                MatchOutput::Frameset(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_within_tolerance_after_offsets() {
+        let mut matcher = CaptureMatcher::new(vec![0, 160], 100);
+
+        assert!(matcher.push(0, Fake(1, Some(10_000))).is_empty());
+        let output = matcher.push(1, Fake(2, Some(10_240)));
+        assert_eq!(framesets(&output), vec![(10_000, vec![1, 2])]);
+        assert!(unmatched(&output).is_empty());
+
+        // Stream 1 is offset to 10_240, 93us from stream 0.
+        assert!(matcher.push(1, Fake(3, Some(10_400))).is_empty());
+        let output = matcher.push(0, Fake(4, Some(10_333)));
+        assert_eq!(framesets(&output), vec![(10_333, vec![4, 3])]);
+
+        assert_eq!(matcher.get_stats().framesets, 2);
+    }
+
+    #[test]
+    fn drops_heads_without_a_counterpart() {
+        let mut matcher = CaptureMatcher::new(vec![0, 0], 100);
+
+        assert!(matcher.push(0, Fake(1, Some(10_000))).is_empty());
+        assert!(matcher.push(0, Fake(2, Some(43_333))).is_empty());
+
+        // Stream 1 starts past the first item of stream 0, which can no longer be matched.
+        let output = matcher.push(1, Fake(3, Some(43_300)));
+        assert_eq!(unmatched(&output), vec![(0, Some(10_000), UnmatchedReason::NoCounterpart, 1)]);
+        assert_eq!(framesets(&output), vec![(43_333, vec![2, 3])]);
+        assert_eq!(matcher.get_stats().no_counterpart, 1);
+    }
+
+    #[test]
+    fn reports_late_and_out_of_order_items() {
+        let mut matcher = CaptureMatcher::new(vec![0, 0], 100);
+
+        matcher.push(0, Fake(1, Some(10_000)));
+        assert_eq!(framesets(&matcher.push(1, Fake(2, Some(10_000)))).len(), 1);
+
+        // At or before the last frameset (within tolerance) is late.
+        let output = matcher.push(1, Fake(3, Some(10_050)));
+        assert_eq!(unmatched(&output), vec![(1, Some(10_050), UnmatchedReason::Late, 3)]);
+
+        // Going backwards within a stream is late too.
+        assert!(matcher.push(0, Fake(4, Some(20_000))).is_empty());
+        let output = matcher.push(0, Fake(5, Some(19_000)));
+        assert_eq!(unmatched(&output), vec![(0, Some(19_000), UnmatchedReason::Late, 5)]);
+
+        assert_eq!(matcher.get_stats().late, 2);
+    }
+
+    #[test]
+    fn reports_missing_timestamps() {
+        let mut matcher = CaptureMatcher::new(vec![0, 0], 100);
+
+        let output = matcher.push(1, Fake(1, None));
+        assert_eq!(unmatched(&output), vec![(1, None, UnmatchedReason::MissingTimestamp, 1)]);
+        assert_eq!(matcher.get_stats().missing_timestamp, 1);
+    }
+
+    #[test]
+    fn drops_the_oldest_item_on_overflow() {
+        let mut matcher = CaptureMatcher::new(vec![0, 0], 100);
+        matcher.set_max_pending(2);
+
+        assert!(matcher.push(0, Fake(1, Some(1_000))).is_empty());
+        assert!(matcher.push(0, Fake(2, Some(2_000))).is_empty());
+        let output = matcher.push(0, Fake(3, Some(3_000)));
+        assert_eq!(unmatched(&output), vec![(0, Some(1_000), UnmatchedReason::Overflow, 1)]);
+        assert_eq!(matcher.get_stats().overflow, 1);
+
+        let output = matcher.push(1, Fake(4, Some(2_000)));
+        assert_eq!(framesets(&output), vec![(2_000, vec![2, 4])]);
+    }
+
+    #[test]
+    fn flush_returns_leftovers() {
+        let mut matcher = CaptureMatcher::new(vec![0, 0, 0], 100);
+
+        matcher.push(0, Fake(1, Some(1_000)));
+        matcher.push(0, Fake(2, Some(2_000)));
+        matcher.push(2, Fake(3, Some(1_000)));
+
+        let output = matcher.flush();
+        assert!(framesets(&output).is_empty());
+        assert_eq!(unmatched(&output), vec![
+            (0, Some(1_000), UnmatchedReason::NoCounterpart, 1),
+            (0, Some(2_000), UnmatchedReason::NoCounterpart, 2),
+            (2, Some(1_000), UnmatchedReason::NoCounterpart, 3),
+        ]);
+        assert!(matcher.flush().is_empty());
+    }
+}
@@ -0,0 +1,531 @@
+use crate::Image;
+use crate::ImageFormat;
+use crate::error::ExtrinsicCalibrationError;
+
+use std::collections::{HashMap, VecDeque};
+
+/// Smoothing scales (in pixels) tried in turn when looking for the target.
+const DETECTION_SIGMAS: [f32; 3] = [1.5, 3.0, 5.0];
+
+/// Corner responses below this fraction of the strongest response are ignored.
+const RESPONSE_THRESHOLD: f32 = 0.05;
+
+/// A grid neighbor must be within this fraction of the grid spacing of its predicted position.
+const NEIGHBOR_TOLERANCE: f32 = 0.35;
+
+/// A planar checkerboard calibration target.
+///
+/// The target is described by its inner corners (where four squares meet), so a board of 10x7
+/// squares has 9x6 inner corners. Corners are numbered row by row, with the target's own coordinate
+/// system at the first corner: x along the columns, y along the rows, and z into the board.
+///
+/// NB: A checkerboard looks the same when rotated by 180 degrees (or 90 degrees if square), so the
+/// first corner is taken to be the one nearest the top left of the image. Every camera must see the
+/// target roughly upright for the corners to be numbered consistently between cameras.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Checkerboard {
+    /// Number of inner corners along each row.
+    pub columns: usize,
+    /// Number of inner corners along each column.
+    pub rows: usize,
+    /// Side length of each square, in millimeters.
+    pub square_size_mm: f32,
+}
+
+impl Checkerboard {
+    pub fn new(columns: usize, rows: usize, square_size_mm: f32) -> Self {
+        Self { columns, rows, square_size_mm }
+    }
+
+    /// The number of inner corners.
+    pub fn corner_count(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    /// The position of each inner corner in the target coordinate system, in millimeters.
+    pub fn get_corner_positions(&self) -> Vec<[f32; 3]> {
+        (0..self.rows)
+            .flat_map(|row| (0..self.columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                [column as f32 * self.square_size_mm, row as f32 * self.square_size_mm, 0.0]
+            })
+            .collect()
+    }
+
+    /// Detect the inner corners of the target in a color, IR or custom grayscale image.
+    ///
+    /// Supported formats are `ColorBgra32`, `Ir16`, `Custom8` and `Custom16`. Returns the corner
+    /// positions in pixels, with subpixel precision, in the order of `get_corner_positions`.
+    pub fn detect(&self, image: &Image) -> Result<Vec<[f32; 2]>, ExtrinsicCalibrationError> {
+        let gray = GrayImage::from_image(image)?;
+        self.detect_gray(&gray).ok_or(ExtrinsicCalibrationError::TargetNotFoundError)
+    }
+
+    pub(crate) fn detect_gray(&self, gray: &GrayImage) -> Option<Vec<[f32; 2]>> {
+        if self.columns < 2 || self.rows < 2 {
+            return None;
+        }
+
+        DETECTION_SIGMAS.iter().find_map(|sigma| {
+            let smoothed = gray.gaussian_blur(*sigma);
+            let candidates = find_saddle_points(&smoothed, *sigma);
+            let grid = grow_grid(&candidates)?;
+            let corners = self.order_grid(&grid, &candidates)?;
+            let spacing = mean_spacing(&corners, self.columns);
+            Some(corners.into_iter().map(|corner| smoothed.refine_corner(corner, spacing)).collect())
+        })
+    }
+
+    /// Number the grid found by `grow_grid` in target order, or None if it isn't the target.
+    fn order_grid(&self, grid: &HashMap<(i32, i32), usize>, candidates: &[Candidate]) -> Option<Vec<[f32; 2]>> {
+        if grid.len() != self.corner_count() {
+            return None;
+        }
+
+        // Make the grid indices right handed in the image (x right, y down).
+        let mut handedness = 0.0;
+        for (&(i, j), &index) in grid.iter() {
+            if let (Some(&right), Some(&down)) = (grid.get(&(i + 1, j)), grid.get(&(i, j + 1))) {
+                let a = sub2(candidates[right].position, candidates[index].position);
+                let b = sub2(candidates[down].position, candidates[index].position);
+                handedness += a[0] * b[1] - a[1] * b[0];
+            }
+        }
+        let flip = if handedness < 0.0 { -1 } else { 1 };
+
+        let mut best: Option<(f32, Vec<[f32; 2]>)> = None;
+
+        // Try each of the four rotations of the grid indices.
+        for rotation in 0..4 {
+            let rotated: Vec<((i32, i32), usize)> = grid.iter()
+                .map(|(&(i, j), &index)| {
+                    let j = j * flip;
+                    let ij = match rotation {
+                        0 => (i, j),
+                        1 => (-j, i),
+                        2 => (-i, -j),
+                        _ => (j, -i),
+                    };
+                    (ij, index)
+                })
+                .collect();
+
+            let min_i = rotated.iter().map(|((i, _), _)| *i).min()?;
+            let max_i = rotated.iter().map(|((i, _), _)| *i).max()?;
+            let min_j = rotated.iter().map(|((_, j), _)| *j).min()?;
+            let max_j = rotated.iter().map(|((_, j), _)| *j).max()?;
+
+            if (max_i - min_i + 1) as usize != self.columns || (max_j - min_j + 1) as usize != self.rows {
+                continue;
+            }
+
+            let mut corners = vec![[0.0f32; 2]; self.corner_count()];
+            for ((i, j), index) in rotated {
+                let column = (i - min_i) as usize;
+                let row = (j - min_j) as usize;
+                corners[row * self.columns + column] = candidates[index].position;
+            }
+
+            let score = corners[0][0] + corners[0][1];
+            if best.as_ref().map(|(best_score, _)| score < *best_score).unwrap_or(true) {
+                best = Some((score, corners));
+            }
+        }
+
+        best.map(|(_, corners)| corners)
+    }
+}
+
+/// A single channel floating point image used for detection.
+pub(crate) struct GrayImage {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<f32>,
+}
+
+impl GrayImage {
+    pub fn new(width: usize, height: usize, data: Vec<f32>) -> Self {
+        Self { width, height, data }
+    }
+
+    pub fn from_image(image: &Image) -> Result<Self, ExtrinsicCalibrationError> {
+        let width = image.get_width_pixels();
+        let height = image.get_height_pixels();
+        let stride = image.get_stride_bytes();
+        let buffer = image.get_data();
+        let mut data = Vec::with_capacity(width * height);
+
+        let format = image.get_format();
+        for y in 0..height {
+            let row = &buffer[y * stride..];
+            for x in 0..width {
+                let value = match format {
+                    ImageFormat::ColorBgra32 => {
+                        let pixel = &row[x * 4..x * 4 + 4];
+                        0.114 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.299 * pixel[2] as f32
+                    },
+                    ImageFormat::Ir16 | ImageFormat::Custom16 => {
+                        u16::from_le_bytes([row[x * 2], row[x * 2 + 1]]) as f32
+                    },
+                    ImageFormat::Custom8 => row[x] as f32,
+                    _ => return Err(ExtrinsicCalibrationError::UnsupportedImageFormatError(format)),
+                };
+                data.push(value);
+            }
+        }
+
+        Ok(Self::new(width, height, data))
+    }
+
+    fn get(&self, x: isize, y: isize) -> f32 {
+        let x = x.max(0).min(self.width as isize - 1) as usize;
+        let y = y.max(0).min(self.height as isize - 1) as usize;
+        self.data[y * self.width + x]
+    }
+
+    /// Separable gaussian blur with edge clamping.
+    fn gaussian_blur(&self, sigma: f32) -> GrayImage {
+        let radius = (sigma * 3.0).ceil() as isize;
+        let kernel: Vec<f32> = (-radius..=radius)
+            .map(|k| (-(k * k) as f32 / (2.0 * sigma * sigma)).exp())
+            .collect();
+        let total: f32 = kernel.iter().sum();
+
+        let mut horizontal = vec![0.0; self.data.len()];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let sum: f32 = (-radius..=radius)
+                    .map(|k| kernel[(k + radius) as usize] * self.get(x as isize + k, y as isize))
+                    .sum();
+                horizontal[y * self.width + x] = sum / total;
+            }
+        }
+
+        let horizontal = GrayImage::new(self.width, self.height, horizontal);
+        let mut blurred = vec![0.0; self.data.len()];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let sum: f32 = (-radius..=radius)
+                    .map(|k| kernel[(k + radius) as usize] * horizontal.get(x as isize, y as isize + k))
+                    .sum();
+                blurred[y * self.width + x] = sum / total;
+            }
+        }
+
+        GrayImage::new(self.width, self.height, blurred)
+    }
+
+    /// Bilinearly interpolated sample.
+    fn sample(&self, x: f32, y: f32) -> f32 {
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let fx = x - x0;
+        let fy = y - y0;
+        let (x0, y0) = (x0 as isize, y0 as isize);
+        let top = self.get(x0, y0) * (1.0 - fx) + self.get(x0 + 1, y0) * fx;
+        let bottom = self.get(x0, y0 + 1) * (1.0 - fx) + self.get(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    /// Refine a corner to subpixel precision: every image gradient near a saddle point is
+    /// orthogonal to the vector from the saddle point, so solve for the point that best satisfies
+    /// that in the least squares sense.
+    fn refine_corner(&self, corner: [f32; 2], spacing: f32) -> [f32; 2] {
+        let half_window = (spacing * 0.25).clamp(3.0, 10.0);
+        let steps = half_window.ceil() as i32;
+        let mut position = corner;
+
+        for _ in 0..20 {
+            let mut g = [0.0f64; 3]; // gxx, gxy, gyy
+            let mut b = [0.0f64; 2];
+
+            for dy in -steps..=steps {
+                for dx in -steps..=steps {
+                    let x = position[0] + dx as f32;
+                    let y = position[1] + dy as f32;
+                    let gx = (self.sample(x + 1.0, y) - self.sample(x - 1.0, y)) as f64 * 0.5;
+                    let gy = (self.sample(x, y + 1.0) - self.sample(x, y - 1.0)) as f64 * 0.5;
+                    let distance2 = (dx * dx + dy * dy) as f64;
+                    let weight = (-distance2 / (half_window as f64 * half_window as f64)).exp();
+
+                    let (gxx, gxy, gyy) = (gx * gx * weight, gx * gy * weight, gy * gy * weight);
+                    g[0] += gxx;
+                    g[1] += gxy;
+                    g[2] += gyy;
+                    b[0] += gxx * x as f64 + gxy * y as f64;
+                    b[1] += gxy * x as f64 + gyy * y as f64;
+                }
+            }
+
+            let det = g[0] * g[2] - g[1] * g[1];
+            if det.abs() < 1e-12 {
+                break;
+            }
+            let next = [
+                ((g[2] * b[0] - g[1] * b[1]) / det) as f32,
+                ((g[0] * b[1] - g[1] * b[0]) / det) as f32,
+            ];
+            let shift = sub2(next, position);
+            // Don't let the refinement wander off to a neighboring corner.
+            if shift[0].abs() > half_window || shift[1].abs() > half_window {
+                break;
+            }
+            position = next;
+            if shift[0] * shift[0] + shift[1] * shift[1] < 1e-4 {
+                break;
+            }
+        }
+
+        position
+    }
+}
+
+/// A candidate inner corner.
+struct Candidate {
+    position: [f32; 2],
+}
+
+/// Find saddle points (where the determinant of the Hessian is strongly negative) that look like
+/// checkerboard corners, with alternating dark and light quadrants around them.
+fn find_saddle_points(image: &GrayImage, sigma: f32) -> Vec<Candidate> {
+    let (width, height) = (image.width as isize, image.height as isize);
+    let mut response = vec![0.0f32; image.data.len()];
+
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let center = image.get(x, y);
+            let ixx = image.get(x + 1, y) - 2.0 * center + image.get(x - 1, y);
+            let iyy = image.get(x, y + 1) - 2.0 * center + image.get(x, y - 1);
+            let ixy = (image.get(x + 1, y + 1) - image.get(x + 1, y - 1)
+                - image.get(x - 1, y + 1) + image.get(x - 1, y - 1)) * 0.25;
+            response[(y * width + x) as usize] = (ixy * ixy - ixx * iyy).max(0.0);
+        }
+    }
+
+    let strongest = response.iter().cloned().fold(0.0f32, f32::max);
+    if strongest <= 0.0 {
+        return Vec::new();
+    }
+    let threshold = strongest * RESPONSE_THRESHOLD;
+    let radius = (sigma * 2.0).ceil() as isize + 1;
+    let ring_radius = sigma * 2.5 + 2.0;
+    let margin = ring_radius.ceil() as isize + 1;
+
+    let mut candidates = Vec::new();
+    for y in margin..height - margin {
+        for x in margin..width - margin {
+            let value = response[(y * width + x) as usize];
+            if value < threshold {
+                continue;
+            }
+
+            let is_maximum = (-radius..=radius).all(|dy| {
+                (-radius..=radius).all(|dx| {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if (dx == 0 && dy == 0) || nx < 0 || ny < 0 || nx >= width || ny >= height {
+                        return true;
+                    }
+                    let other = response[(ny * width + nx) as usize];
+                    // Break ties by position so plateaus yield a single maximum.
+                    other < value || (other == value && (dy, dx) > (0, 0))
+                })
+            });
+
+            if is_maximum && has_alternating_quadrants(image, x as f32, y as f32, ring_radius) {
+                candidates.push(Candidate { position: [x as f32, y as f32] });
+            }
+        }
+    }
+
+    candidates
+}
+
+/// Sample a ring around a point, which should cross between dark and light exactly four times.
+fn has_alternating_quadrants(image: &GrayImage, x: f32, y: f32, radius: f32) -> bool {
+    const SAMPLES: usize = 32;
+
+    let ring: Vec<f32> = (0..SAMPLES)
+        .map(|k| {
+            let angle = k as f32 * std::f32::consts::PI * 2.0 / SAMPLES as f32;
+            image.sample(x + radius * angle.cos(), y + radius * angle.sin())
+        })
+        .collect();
+
+    let min = ring.iter().cloned().fold(f32::MAX, f32::min);
+    let max = ring.iter().cloned().fold(f32::MIN, f32::max);
+    if max - min <= 0.0 {
+        return false;
+    }
+    let middle = (min + max) * 0.5;
+
+    let crossings = (0..SAMPLES)
+        .filter(|k| (ring[*k] > middle) != (ring[(k + 1) % SAMPLES] > middle))
+        .count();
+
+    crossings == 4
+}
+
+/// Grow a grid of neighboring candidates outwards from the candidate nearest their centroid.
+/// Returns a map from grid indices to candidate indices.
+fn grow_grid(candidates: &[Candidate]) -> Option<HashMap<(i32, i32), usize>> {
+    if candidates.len() < 4 {
+        return None;
+    }
+
+    let count = candidates.len() as f32;
+    let centroid = candidates.iter()
+        .fold([0.0f32; 2], |sum, candidate| add2(sum, candidate.position));
+    let centroid = [centroid[0] / count, centroid[1] / count];
+
+    let seed = nearest(candidates, centroid, f32::MAX, &[])?;
+    let seed_position = candidates[seed].position;
+
+    // The grid axes at the seed are its nearest neighbor and the most orthogonal of the next few.
+    let mut neighbors: Vec<usize> = (0..candidates.len()).filter(|i| *i != seed).collect();
+    neighbors.sort_by(|a, b| {
+        distance2(candidates[*a].position, seed_position)
+            .partial_cmp(&distance2(candidates[*b].position, seed_position))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let u = sub2(candidates[neighbors[0]].position, seed_position);
+    let v = neighbors.iter().take(4).skip(1)
+        .map(|i| sub2(candidates[*i].position, seed_position))
+        .max_by(|a, b| {
+            let cross_a = (u[0] * a[1] - u[1] * a[0]).abs() / length2(*a);
+            let cross_b = (u[0] * b[1] - u[1] * b[0]).abs() / length2(*b);
+            cross_a.partial_cmp(&cross_b).unwrap_or(std::cmp::Ordering::Equal)
+        })?;
+
+    let (u_length, v_length) = (length2(u), length2(v));
+    if u_length <= 0.0 || v_length / u_length > 2.0 || u_length / v_length > 2.0 {
+        return None;
+    }
+
+    let mut grid = HashMap::new();
+    let mut used = vec![false; candidates.len()];
+    let mut queue = VecDeque::new();
+
+    grid.insert((0, 0), seed);
+    used[seed] = true;
+    queue.push_back(((0, 0), u, v));
+
+    while let Some(((i, j), u, v)) = queue.pop_front() {
+        let position = candidates[grid[&(i, j)]].position;
+
+        for (di, dj) in [(1, 0), (-1, 0), (0, 1), (0, -1)].iter() {
+            let key = (i + di, j + dj);
+            if grid.contains_key(&key) {
+                continue;
+            }
+
+            let step = if *di != 0 { scale2(u, *di as f32) } else { scale2(v, *dj as f32) };
+            let predicted = add2(position, step);
+            let tolerance = length2(step) * NEIGHBOR_TOLERANCE;
+
+            let found = match nearest(candidates, predicted, tolerance, &used) {
+                Some(found) => found,
+                None => continue,
+            };
+
+            // Follow the local grid spacing, so perspective foreshortening is tracked.
+            let actual = sub2(candidates[found].position, position);
+            let (next_u, next_v) = if *di != 0 {
+                (scale2(actual, *di as f32), v)
+            } else {
+                (u, scale2(actual, *dj as f32))
+            };
+
+            grid.insert(key, found);
+            used[found] = true;
+            queue.push_back((key, next_u, next_v));
+        }
+    }
+
+    Some(grid)
+}
+
+fn nearest(candidates: &[Candidate], point: [f32; 2], max_distance: f32, used: &[bool]) -> Option<usize> {
+    candidates.iter()
+        .enumerate()
+        .filter(|(i, _)| !used.get(*i).cloned().unwrap_or(false))
+        .map(|(i, candidate)| (i, distance2(candidate.position, point)))
+        .filter(|(_, distance)| *distance <= max_distance)
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(i, _)| i)
+}
+
+fn mean_spacing(corners: &[[f32; 2]], columns: usize) -> f32 {
+    let distances: Vec<f32> = corners.windows(2)
+        .enumerate()
+        .filter(|(i, _)| (i + 1) % columns != 0)
+        .map(|(_, pair)| distance2(pair[0], pair[1]))
+        .collect();
+    distances.iter().sum::<f32>() / distances.len().max(1) as f32
+}
+
+fn add2(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn sub2(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn scale2(a: [f32; 2], s: f32) -> [f32; 2] {
+    [a[0] * s, a[1] * s]
+}
+
+fn length2(a: [f32; 2]) -> f32 {
+    (a[0] * a[0] + a[1] * a[1]).sqrt()
+}
+
+fn distance2(a: [f32; 2], b: [f32; 2]) -> f32 {
+    length2(sub2(a, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extrinsic_calibration::tests::{pinhole_camera, pose, project_corners, render_target};
+
+    #[test]
+    fn corner_positions_are_row_by_row() {
+        let target = Checkerboard::new(3, 2, 25.0);
+        assert_eq!(target.corner_count(), 6);
+        assert_eq!(target.get_corner_positions(), vec![
+            [0.0, 0.0, 0.0], [25.0, 0.0, 0.0], [50.0, 0.0, 0.0],
+            [0.0, 25.0, 0.0], [25.0, 25.0, 0.0], [50.0, 25.0, 0.0],
+        ]);
+    }
+
+    #[test]
+    fn detect_gray_finds_every_corner_to_subpixel_accuracy() {
+        let camera = pinhole_camera();
+        let target = Checkerboard::new(7, 5, 30.0);
+
+        for target_to_camera in [
+            pose([0.0, 0.0, 0.0], [-90.0, -60.0, 600.0]),
+            pose([0.3, -0.25, 0.1], [-110.0, -40.0, 700.0]),
+        ].iter() {
+            let image = render_target(&camera, &target, target_to_camera, 640, 480);
+            let corners = target.detect_gray(&image).expect("the target is found");
+            let expected = project_corners(&camera, &target, target_to_camera);
+
+            assert_eq!(corners.len(), target.corner_count());
+            for (corner, expected) in corners.iter().zip(expected.iter()) {
+                let error = ((corner[0] as f64 - expected[0]).powi(2) + (corner[1] as f64 - expected[1]).powi(2)).sqrt();
+                assert!(error < 0.1, "corner {:?} is {} px from {:?}", corner, error, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn detect_gray_rejects_other_boards_and_blank_images() {
+        let camera = pinhole_camera();
+        let target_to_camera = pose([0.0, 0.0, 0.0], [-90.0, -60.0, 600.0]);
+        let image = render_target(&camera, &Checkerboard::new(7, 5, 30.0), &target_to_camera, 640, 480);
+
+        assert!(Checkerboard::new(6, 5, 30.0).detect_gray(&image).is_none());
+        assert!(Checkerboard::new(7, 5, 30.0).detect_gray(&GrayImage::new(64, 48, vec![128.0; 64 * 48])).is_none());
+    }
+}
@@ -0,0 +1,357 @@
+use crate::Capture;
+use crate::Image;
+use crate::ImuSample;
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Default number of most recent samples the fit is made from.
+const DEFAULT_MAX_SAMPLES: usize = 120;
+
+/// A device timestamp this far behind the previous sample means the device clock was reset.
+const RESET_THRESHOLD_USEC: u64 = 1_000_000;
+
+/// The current time of the clock libk4a stamps image system timestamps with, in nanoseconds.
+///
+/// This is `CLOCK_MONOTONIC` on Linux and `QueryPerformanceCounter` on Windows.
+#[cfg(target_os = "linux")]
+pub fn host_clock_nsec() -> u64 {
+    use std::os::raw::{c_int, c_long};
+
+    #[repr(C)]
+    struct Timespec {
+        tv_sec: c_long,
+        tv_nsec: c_long,
+    }
+
+    const CLOCK_MONOTONIC: c_int = 1;
+
+    extern "C" {
+        fn clock_gettime(clock_id: c_int, tp: *mut Timespec) -> c_int;
+    }
+
+    let mut time = Timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe {
+        clock_gettime(CLOCK_MONOTONIC, &mut time);
+    }
+    time.tv_sec as u64 * 1_000_000_000 + time.tv_nsec as u64
+}
+
+/// The current time of the clock libk4a stamps image system timestamps with, in nanoseconds.
+///
+/// This is `CLOCK_MONOTONIC` on Linux and `QueryPerformanceCounter` on Windows.
+#[cfg(windows)]
+pub fn host_clock_nsec() -> u64 {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn QueryPerformanceCounter(count: *mut i64) -> i32;
+        fn QueryPerformanceFrequency(frequency: *mut i64) -> i32;
+    }
+
+    let mut count = 0;
+    let mut frequency = 1;
+    unsafe {
+        QueryPerformanceCounter(&mut count);
+        QueryPerformanceFrequency(&mut frequency);
+    }
+    (count as u128 * 1_000_000_000 / frequency as u128) as u64
+}
+
+/// One moment on the host clock, as a host clock timestamp, an `Instant` and a `SystemTime`, so
+/// host clock timestamps can be converted to the others.
+#[derive(Debug, Copy, Clone)]
+pub struct HostClockReference {
+    pub host_timestamp_nsec: u64,
+    pub instant: Instant,
+    pub system_time: SystemTime,
+}
+
+impl HostClockReference {
+    #[cfg(any(target_os = "linux", windows))]
+    pub fn now() -> Self {
+        Self {
+            host_timestamp_nsec: host_clock_nsec(),
+            instant: Instant::now(),
+            system_time: SystemTime::now(),
+        }
+    }
+
+    /// The `Instant` of a host clock timestamp.
+    pub fn to_instant(&self, host_timestamp_nsec: u64) -> Instant {
+        offset(self.instant, self.host_timestamp_nsec, host_timestamp_nsec)
+    }
+
+    /// The `SystemTime` of a host clock timestamp.
+    pub fn to_system_time(&self, host_timestamp_nsec: u64) -> SystemTime {
+        offset(self.system_time, self.host_timestamp_nsec, host_timestamp_nsec)
+    }
+}
+
+fn offset<T>(time: T, from_nsec: u64, to_nsec: u64) -> T
+    where T: std::ops::Add<Duration, Output = T> + std::ops::Sub<Duration, Output = T>
+{
+    if to_nsec >= from_nsec {
+        time + Duration::from_nanos(to_nsec - from_nsec)
+    } else {
+        time - Duration::from_nanos(from_nsec - to_nsec)
+    }
+}
+
+/// A fitted mapping from device timestamps to host clock timestamps.
+#[derive(Debug, Copy, Clone)]
+pub struct ClockMapping {
+    /// A device timestamp, which `host_at_origin_nsec` is the host time of.
+    pub device_origin_usec: u64,
+    pub host_at_origin_nsec: f64,
+    /// Host time elapsed per unit of device time.
+    pub rate: f64,
+    pub reference: HostClockReference,
+}
+
+impl ClockMapping {
+    /// How much faster the host clock runs than the device clock, in parts per million.
+    pub fn drift_ppm(&self) -> f64 {
+        (self.rate - 1.0) * 1e6
+    }
+
+    /// The host clock timestamp of a device timestamp.
+    pub fn device_to_host_nsec(&self, device_timestamp_usec: u64) -> u64 {
+        let elapsed_usec = device_timestamp_usec as f64 - self.device_origin_usec as f64;
+        (self.host_at_origin_nsec + elapsed_usec * 1000.0 * self.rate).max(0.0).round() as u64
+    }
+
+    pub fn device_to_instant(&self, device_timestamp_usec: u64) -> Instant {
+        self.reference.to_instant(self.device_to_host_nsec(device_timestamp_usec))
+    }
+
+    pub fn device_to_system_time(&self, device_timestamp_usec: u64) -> SystemTime {
+        self.reference.to_system_time(self.device_to_host_nsec(device_timestamp_usec))
+    }
+}
+
+/// Estimates how device timestamps map to host time, from pairs of device and host timestamps of
+/// the same events.
+///
+/// Images carry both: their device timestamp and their system timestamp (when the host received
+/// them). The mapping is a line fitted to the most recent `max_samples` pairs with the Theil-Sen
+/// estimator, which ignores the occasional pair delayed on its way to the host. Since system
+/// timestamps are taken on arrival, mapped times include the typical transfer latency.
+///
+/// Device timestamps restart from zero when a device's cameras are restarted (eg. by
+/// `ResilientDevice` after a reconnect). A sample more than a second behind the previous one is
+/// taken as such a reset and starts the fit over; call `reset` to start over explicitly.
+pub struct ClockSync {
+    samples: VecDeque<(u64, u64)>,
+    /// Number of most recent samples the fit is made from.
+    pub max_samples: usize,
+    reference: HostClockReference,
+}
+
+impl ClockSync {
+    #[cfg(any(target_os = "linux", windows))]
+    pub fn new() -> Self {
+        Self::with_reference(HostClockReference::now())
+    }
+
+    /// Create an estimator whose mappings convert host timestamps with `reference`.
+    pub fn with_reference(reference: HostClockReference) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            max_samples: DEFAULT_MAX_SAMPLES,
+            reference,
+        }
+    }
+
+    /// Add a device timestamp and the host clock timestamp of the same moment.
+    pub fn add_sample(&mut self, device_timestamp_usec: u64, host_timestamp_nsec: u64) {
+        let device_clock_reset = self.samples.back()
+            .map(|(last_usec, _)| device_timestamp_usec + RESET_THRESHOLD_USEC < *last_usec)
+            .unwrap_or(false);
+        if device_clock_reset {
+            self.reset();
+        }
+
+        self.samples.push_back((device_timestamp_usec, host_timestamp_nsec));
+        while self.samples.len() > self.max_samples.max(2) {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Drop every sample, eg. after the device's cameras were restarted.
+    pub fn reset(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Add the timestamps of an image.
+    pub fn add_image(&mut self, image: &Image) {
+        self.add_sample(image.get_device_timestamp_usec(), image.get_system_timestamp_nsec());
+    }
+
+    /// Add the timestamps of the first image of a capture (color, then depth, then IR).
+    pub fn add_capture(&mut self, capture: &Capture) {
+        let image = capture.get_color_image()
+            .or_else(|| capture.get_depth_image())
+            .or_else(|| capture.get_ir_image());
+        if let Some(image) = image {
+            self.add_image(&image);
+        }
+    }
+
+    /// Add an IMU sample read at `host_timestamp_nsec` (eg. `host_clock_nsec()` right after
+    /// `Device::get_imu_sample`). Samples waiting in the IMU queue make for late host timestamps,
+    /// so prefer images.
+    pub fn add_imu_sample(&mut self, sample: &ImuSample, host_timestamp_nsec: u64) {
+        self.add_sample(sample.acc_timestamp_usec, host_timestamp_nsec);
+    }
+
+    /// The number of samples the fit is made from.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Fit the mapping, if there are samples at two or more device times.
+    pub fn estimate(&self) -> Option<ClockMapping> {
+        let (device_origin_usec, host_origin_nsec) = *self.samples.front()?;
+
+        // Relative to the first sample, so f64 keeps sub-microsecond precision.
+        let points: Vec<(f64, f64)> = self.samples.iter()
+            .map(|(device_usec, host_nsec)| {
+                (*device_usec as f64 - device_origin_usec as f64,
+                 *host_nsec as f64 - host_origin_nsec as f64)
+            })
+            .collect();
+
+        let mut slopes = Vec::with_capacity(points.len() * (points.len() - 1) / 2);
+        for (i, (x1, y1)) in points.iter().enumerate() {
+            for (x2, y2) in points[i + 1..].iter() {
+                if x1 != x2 {
+                    slopes.push((y2 - y1) / (x2 - x1));
+                }
+            }
+        }
+        // Host nanoseconds per device microsecond.
+        let slope = median(&mut slopes)?;
+
+        let mut intercepts: Vec<f64> = points.iter().map(|(x, y)| y - slope * x).collect();
+        let intercept = median(&mut intercepts)?;
+
+        Some(ClockMapping {
+            device_origin_usec,
+            host_at_origin_nsec: host_origin_nsec as f64 + intercept,
+            rate: slope / 1000.0,
+            reference: self.reference,
+        })
+    }
+}
+
+#[cfg(any(target_os = "linux", windows))]
+impl Default for ClockSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let middle = values.len() / 2;
+    if values.len() > middle * 2 {
+        Some(values[middle])
+    } else {
+        Some((values[middle - 1] + values[middle]) / 2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OFFSET_NSEC: f64 = 5_000_000_000.0;
+    const DRIFT_PPM: f64 = 50.0;
+
+    fn reference() -> HostClockReference {
+        HostClockReference {
+            host_timestamp_nsec: 0,
+            instant: Instant::now(),
+            system_time: SystemTime::now(),
+        }
+    }
+
+    fn host_nsec(device_usec: u64) -> f64 {
+        OFFSET_NSEC + device_usec as f64 * 1000.0 * (1.0 + DRIFT_PPM * 1e-6)
+    }
+
+    /// 30 fps samples, with up to 500ns of jitter and every seventh one delayed by 3ms.
+    fn add_samples(clock_sync: &mut ClockSync, first_usec: u64, count: u64) {
+        for i in 0..count {
+            let device_usec = first_usec + i * 33_333;
+            let jitter_nsec = ((i * 37) % 11) as f64 * 100.0 - 500.0;
+            let delay_nsec = if i % 7 == 3 { 3_000_000.0 } else { 0.0 };
+            clock_sync.add_sample(device_usec, (host_nsec(device_usec) + jitter_nsec + delay_nsec) as u64);
+        }
+    }
+
+    #[test]
+    fn fits_offset_and_drift_despite_delayed_samples() {
+        let mut clock_sync = ClockSync::with_reference(reference());
+        add_samples(&mut clock_sync, 1_000_000, 100);
+
+        let mapping = clock_sync.estimate().unwrap();
+        assert!((mapping.drift_ppm() - DRIFT_PPM).abs() < 1.0, "drift {}", mapping.drift_ppm());
+        for device_usec in [1_000_000, 2_500_000, 4_300_000, 10_000_000].iter() {
+            let error_nsec = mapping.device_to_host_nsec(*device_usec) as f64 - host_nsec(*device_usec);
+            assert!(error_nsec.abs() < 2_000.0, "{}us is off by {}ns", device_usec, error_nsec);
+        }
+    }
+
+    #[test]
+    fn keeps_only_the_most_recent_samples() {
+        let mut clock_sync = ClockSync::with_reference(reference());
+        clock_sync.max_samples = 10;
+        add_samples(&mut clock_sync, 0, 25);
+        assert_eq!(clock_sync.len(), 10);
+        assert_eq!(clock_sync.estimate().unwrap().device_origin_usec, 15 * 33_333);
+    }
+
+    #[test]
+    fn needs_two_device_times() {
+        let mut clock_sync = ClockSync::with_reference(reference());
+        assert!(clock_sync.is_empty());
+        assert!(clock_sync.estimate().is_none());
+
+        clock_sync.add_sample(1_000, 2_000_000);
+        assert!(clock_sync.estimate().is_none());
+        clock_sync.add_sample(1_000, 2_000_500);
+        assert!(clock_sync.estimate().is_none());
+
+        clock_sync.add_sample(2_000, 3_000_000);
+        assert!((clock_sync.estimate().unwrap().rate - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn starts_over_when_the_device_clock_resets() {
+        let mut clock_sync = ClockSync::with_reference(reference());
+        add_samples(&mut clock_sync, 600_000_000, 50);
+
+        // After a restart the device clock counts from zero again, against the same host clock.
+        clock_sync.add_sample(200_000, 9_000_000_000);
+        assert_eq!(clock_sync.len(), 1);
+        clock_sync.add_sample(233_333, 9_033_333_000);
+        let mapping = clock_sync.estimate().unwrap();
+        assert_eq!(mapping.device_to_host_nsec(200_000), 9_000_000_000);
+        assert!(mapping.drift_ppm().abs() < 1.0);
+
+        // Small steps back, eg. from interleaved IMU samples, don't.
+        clock_sync.add_sample(230_000, 9_030_000_000);
+        assert_eq!(clock_sync.len(), 3);
+
+        clock_sync.reset();
+        assert!(clock_sync.is_empty());
+    }
+}
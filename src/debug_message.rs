@@ -0,0 +1,196 @@
+//! Receiving libk4a's debug messages.
+//!
+//! libk4a logs to stdout by default. Installing a handler delivers messages to the handler as
+//! well; to silence stdout, also set the environment variable `K4A_ENABLE_LOG_TO_STDOUT=0` before
+//! the first libk4a call.
+
+use crate::error::SetDebugMessageHandlerError;
+use k4a_sys_temp as k4a_sys;
+
+#[cfg(feature = "log")]
+use std::convert::TryFrom;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::RwLock;
+
+/// The severity of a libk4a debug message, from most to least severe.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Critical,
+    Error,
+    Warning,
+    Info,
+    Trace,
+}
+
+impl LogLevel {
+    fn from_raw(level: k4a_sys::k4a_log_level_t) -> Self {
+        match level {
+            k4a_sys::k4a_log_level_t_K4A_LOG_LEVEL_CRITICAL => LogLevel::Critical,
+            k4a_sys::k4a_log_level_t_K4A_LOG_LEVEL_ERROR => LogLevel::Error,
+            k4a_sys::k4a_log_level_t_K4A_LOG_LEVEL_WARNING => LogLevel::Warning,
+            k4a_sys::k4a_log_level_t_K4A_LOG_LEVEL_INFO => LogLevel::Info,
+            _ => LogLevel::Trace,
+        }
+    }
+
+    fn to_raw(self) -> k4a_sys::k4a_log_level_t {
+        match self {
+            LogLevel::Critical => k4a_sys::k4a_log_level_t_K4A_LOG_LEVEL_CRITICAL,
+            LogLevel::Error => k4a_sys::k4a_log_level_t_K4A_LOG_LEVEL_ERROR,
+            LogLevel::Warning => k4a_sys::k4a_log_level_t_K4A_LOG_LEVEL_WARNING,
+            LogLevel::Info => k4a_sys::k4a_log_level_t_K4A_LOG_LEVEL_INFO,
+            LogLevel::Trace => k4a_sys::k4a_log_level_t_K4A_LOG_LEVEL_TRACE,
+        }
+    }
+}
+
+/// A debug message from libk4a, with the libk4a source location that logged it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DebugMessage<'a> {
+    pub level: LogLevel,
+    pub file: &'a str,
+    pub line: i32,
+    pub message: &'a str,
+}
+
+type Handler = Box<dyn Fn(&DebugMessage<'_>) + Send + Sync>;
+
+/// The installed handler. libk4a only gets a pointer to `handle_message`, which looks it up here,
+/// so replacing the handler never leaves libk4a with a dangling context pointer.
+static HANDLER: RwLock<Option<Handler>> = RwLock::new(None);
+
+/// Deliver libk4a debug messages at `min_level` or more severe to `handler`, replacing any
+/// handler installed before.
+///
+/// libk4a may call the handler from any of its threads, concurrently. The handler must not set
+/// or clear the debug message handler itself. Panics in the handler are caught and discarded.
+pub fn set_debug_message_handler<F>(min_level: LogLevel, handler: F) -> Result<(), SetDebugMessageHandlerError>
+    where F: Fn(&DebugMessage<'_>) + Send + Sync + 'static
+{
+    let previous = swap_handler(Some(Box::new(handler)));
+
+    // NB: libk4a may be delivering a message on another thread, which takes the read lock, so the
+    // lock must not be held across this call.
+    let result = unsafe {
+        k4a_sys::k4a_set_debug_message_handler(Some(handle_message), std::ptr::null_mut(), min_level.to_raw())
+    };
+
+    if let Err(error) = check(result) {
+        swap_handler(previous);
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Remove the debug message handler, restoring libk4a's default logging.
+pub fn clear_debug_message_handler() -> Result<(), SetDebugMessageHandlerError> {
+    let previous = swap_handler(None);
+
+    let result = unsafe {
+        k4a_sys::k4a_set_debug_message_handler(None, std::ptr::null_mut(),
+                                               k4a_sys::k4a_log_level_t_K4A_LOG_LEVEL_OFF)
+    };
+
+    if let Err(error) = check(result) {
+        swap_handler(previous);
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Install `handler`, returning the handler it replaces. The lock is only held for the swap.
+fn swap_handler(handler: Option<Handler>) -> Option<Handler> {
+    let mut installed = HANDLER.write().unwrap_or_else(|error| error.into_inner());
+    std::mem::replace(&mut *installed, handler)
+}
+
+fn check(result: k4a_sys::k4a_result_t) -> Result<(), SetDebugMessageHandlerError> {
+    match result {
+        k4a_sys::k4a_result_t_K4A_RESULT_SUCCEEDED => Ok(()),
+        k4a_sys::k4a_result_t_K4A_RESULT_FAILED => Err(SetDebugMessageHandlerError::FailedError),
+        _ => {
+            // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
+            // Linux uses u32 and Windows uses i32.
+            // This should be fixed in the `k4a-sys` build script.
+            Err(SetDebugMessageHandlerError::UnexpectedError(result as i32))
+        },
+    }
+}
+
+/// Forward libk4a debug messages to the `log` crate, with target `k4a`.
+///
+/// Critical messages are logged as errors. The libk4a file and line are set on each record, and
+/// are also attached as `file` and `line` key-values.
+#[cfg(feature = "log")]
+pub fn install_log_handler(min_level: LogLevel) -> Result<(), SetDebugMessageHandlerError> {
+    set_debug_message_handler(min_level, |message| {
+        let level = match message.level {
+            LogLevel::Critical | LogLevel::Error => log::Level::Error,
+            LogLevel::Warning => log::Level::Warn,
+            LogLevel::Info => log::Level::Info,
+            LogLevel::Trace => log::Level::Trace,
+        };
+
+        let metadata = log::Metadata::builder().level(level).target("k4a").build();
+        if level > log::max_level() || !log::logger().enabled(&metadata) {
+            return;
+        }
+
+        let fields = [
+            ("file", log::kv::Value::from(message.file)),
+            ("line", log::kv::Value::from(message.line)),
+        ];
+
+        log::logger().log(&log::Record::builder()
+            .metadata(metadata)
+            .file(Some(message.file))
+            .line(u32::try_from(message.line).ok())
+            .key_values(&fields)
+            .args(format_args!("{}", message.message))
+            .build());
+    })
+}
+
+/// Forward libk4a debug messages to `tracing` as events with target `k4a`.
+///
+/// Critical messages are recorded as errors. The libk4a file and line are recorded as the `file`
+/// and `line` fields.
+#[cfg(feature = "tracing")]
+pub fn install_tracing_handler(min_level: LogLevel) -> Result<(), SetDebugMessageHandlerError> {
+    set_debug_message_handler(min_level, |message| {
+        let DebugMessage { level, file, line, message } = *message;
+        match level {
+            LogLevel::Critical | LogLevel::Error => tracing::error!(target: "k4a", file, line, "{}", message),
+            LogLevel::Warning => tracing::warn!(target: "k4a", file, line, "{}", message),
+            LogLevel::Info => tracing::info!(target: "k4a", file, line, "{}", message),
+            LogLevel::Trace => tracing::trace!(target: "k4a", file, line, "{}", message),
+        }
+    })
+}
+
+unsafe extern "C" fn handle_message(_context: *mut c_void,
+                                    level: k4a_sys::k4a_log_level_t,
+                                    file: *const c_char,
+                                    line: c_int,
+                                    message: *const c_char)
+{
+    let file = if file.is_null() { Default::default() } else { CStr::from_ptr(file).to_string_lossy() };
+    let message = if message.is_null() { Default::default() } else { CStr::from_ptr(message).to_string_lossy() };
+
+    let message = DebugMessage {
+        level: LogLevel::from_raw(level),
+        file: &file,
+        line,
+        message: &message,
+    };
+
+    // Unwinding into libk4a is undefined behavior.
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+        let installed = HANDLER.read().unwrap_or_else(|error| error.into_inner());
+        if let Some(handler) = installed.as_ref() {
+            handler(&message);
+        }
+    }));
+}
@@ -0,0 +1,443 @@
+//! Filters for `Depth16` images: edge-aware smoothing, temporal smoothing, hole filling and
+//! flying pixel removal.
+//!
+//! Zero is "no depth" in `Depth16` images. Filters never blend zeros into valid depths, and every
+//! filter returns a new `Depth16` image of the same size.
+
+use crate::error::DepthFilterError;
+use crate::Image;
+use crate::ImageFormat;
+
+/// Smooth depth while keeping edges, with a bilateral filter.
+///
+/// Neighbors are weighted by their distance in pixels (Gaussian with `spatial_sigma_px`) and by
+/// their difference in depth (Gaussian with `range_sigma_mm`), so depths across an edge barely
+/// contribute. Neighbors over three range sigmas away in depth are ignored.
+///
+/// Both sigmas must be positive.
+pub fn bilateral_filter(image: &Image,
+                        spatial_sigma_px: f32,
+                        range_sigma_mm: f32) -> Result<Image, DepthFilterError>
+{
+    for (name, value) in [("spatial_sigma_px", spatial_sigma_px), ("range_sigma_mm", range_sigma_mm)].iter() {
+        if value.is_nan() || *value <= 0.0 {
+            return Err(DepthFilterError::InvalidParameterError { name, value: *value });
+        }
+    }
+
+    let depth = DepthBuffer::from_image(image)?;
+    let radius = (2.0 * spatial_sigma_px).ceil().max(1.0) as isize;
+
+    let spatial_weights: Vec<f32> = (-radius..=radius)
+        .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+        .map(|(dx, dy)| (-((dx * dx + dy * dy) as f32) / (2.0 * spatial_sigma_px * spatial_sigma_px)).exp())
+        .collect();
+    let max_difference = 3.0 * range_sigma_mm;
+
+    let mut output = vec![0; depth.data.len()];
+
+    for y in 0..depth.height as isize {
+        for x in 0..depth.width as isize {
+            let center = depth.get(x, y);
+            if center == 0 {
+                continue;
+            }
+
+            let mut weight_sum = 0.0;
+            let mut depth_sum = 0.0;
+
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let neighbor = depth.get(x + dx, y + dy);
+                    let difference = neighbor as f32 - center as f32;
+                    if neighbor == 0 || difference.abs() > max_difference {
+                        continue;
+                    }
+                    let spatial = spatial_weights[((dy + radius) * (2 * radius + 1) + dx + radius) as usize];
+                    let weight = spatial * (-(difference * difference) / (2.0 * range_sigma_mm * range_sigma_mm)).exp();
+                    weight_sum += weight;
+                    depth_sum += weight * neighbor as f32;
+                }
+            }
+
+            output[y as usize * depth.width + x as usize] = (depth_sum / weight_sum).round() as u16;
+        }
+    }
+
+    depth.with_data(output).into_image()
+}
+
+/// How `fill_holes` chooses a depth for missing pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HoleFilling {
+    /// Use the nearest valid depth to the left on the same row.
+    FromLeft,
+    /// Use the nearest (smallest) depth among the 8 neighbors, favoring the foreground.
+    NearestNeighbor,
+    /// Use the farthest (largest) depth among the 8 neighbors, favoring the background. This is
+    /// the safer choice at object edges, where holes are usually shadows of the foreground.
+    FarthestNeighbor,
+}
+
+/// Fill missing depth in holes up to `max_radius_px` wide.
+///
+/// Neighbor strategies grow inward from the edges of a hole by one pixel per pass, for
+/// `max_radius_px` passes. `FromLeft` fills runs of up to `max_radius_px` missing pixels that
+/// have a valid pixel to their left.
+pub fn fill_holes(image: &Image, strategy: HoleFilling, max_radius_px: usize) -> Result<Image, DepthFilterError> {
+    let mut depth = DepthBuffer::from_image(image)?;
+
+    match strategy {
+        HoleFilling::FromLeft => {
+            for y in 0..depth.height {
+                let row = &mut depth.data[y * depth.width..(y + 1) * depth.width];
+                let mut x = 0;
+                while x < row.len() {
+                    if row[x] != 0 || x == 0 || row[x - 1] == 0 {
+                        x += 1;
+                        continue;
+                    }
+                    let run_end = row[x..].iter().position(|d| *d != 0).map(|length| x + length).unwrap_or(row.len());
+                    if run_end - x <= max_radius_px {
+                        let fill = row[x - 1];
+                        row[x..run_end].iter_mut().for_each(|d| *d = fill);
+                    }
+                    x = run_end;
+                }
+            }
+        },
+        HoleFilling::NearestNeighbor | HoleFilling::FarthestNeighbor => {
+            for _ in 0..max_radius_px {
+                let mut filled = depth.data.clone();
+                let mut changed = false;
+
+                for y in 0..depth.height as isize {
+                    for x in 0..depth.width as isize {
+                        if depth.get(x, y) != 0 {
+                            continue;
+                        }
+                        let neighbors = NEIGHBORS.iter()
+                            .map(|(dx, dy)| depth.get(x + dx, y + dy))
+                            .filter(|d| *d != 0);
+                        let fill = match strategy {
+                            HoleFilling::NearestNeighbor => neighbors.min(),
+                            _ => neighbors.max(),
+                        };
+                        if let Some(fill) = fill {
+                            filled[y as usize * depth.width + x as usize] = fill;
+                            changed = true;
+                        }
+                    }
+                }
+
+                depth.data = filled;
+                if !changed {
+                    break;
+                }
+            }
+        },
+    }
+
+    depth.into_image()
+}
+
+/// Remove flying pixels: depths interpolated between a foreground and background at object
+/// edges, which float in space between the two.
+///
+/// A pixel is removed if fewer than `min_neighbors` of its 8 neighbors have a depth within
+/// `max_jump_mm` of it.
+pub fn remove_flying_pixels(image: &Image, max_jump_mm: u16, min_neighbors: usize) -> Result<Image, DepthFilterError> {
+    let depth = DepthBuffer::from_image(image)?;
+    let mut output = depth.data.clone();
+
+    for y in 0..depth.height as isize {
+        for x in 0..depth.width as isize {
+            let center = depth.get(x, y);
+            if center == 0 {
+                continue;
+            }
+            let supporting = NEIGHBORS.iter()
+                .map(|(dx, dy)| depth.get(x + dx, y + dy))
+                .filter(|d| *d != 0 && (*d as i32 - center as i32).abs() <= max_jump_mm as i32)
+                .count();
+            if supporting < min_neighbors {
+                output[y as usize * depth.width + x as usize] = 0;
+            }
+        }
+    }
+
+    depth.with_data(output).into_image()
+}
+
+/// When `TemporalFilter` fills missing depth with the depth from earlier frames.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Persistence {
+    /// Missing depth stays missing.
+    Disabled,
+    /// Fill if the pixel had depth in at least `valid` of the last `frames` frames (at most 8).
+    ValidInLast { valid: u8, frames: u8 },
+    /// Fill whenever the pixel has ever had depth.
+    Always,
+}
+
+/// Exponential smoothing of depth across consecutive frames, for static parts of a scene.
+///
+/// Each pixel is blended with its smoothed history as `alpha * depth + (1 - alpha) * history`.
+/// Changes larger than `delta_mm` are treated as motion, which restarts the history from the new
+/// depth instead of smearing it. Feeding a frame of a different size resets the filter.
+#[derive(Debug, Clone)]
+pub struct TemporalFilter {
+    pub alpha: f32,
+    pub delta_mm: f32,
+    pub persistence: Persistence,
+    width: usize,
+    height: usize,
+    /// Smoothed depth per pixel, zero where there's no history.
+    history: Vec<f32>,
+    /// Bits set for the frames a pixel had depth in, most recent frame in the lowest bit.
+    validity: Vec<u8>,
+}
+
+impl TemporalFilter {
+    pub fn new(alpha: f32, delta_mm: f32, persistence: Persistence) -> Self {
+        Self {
+            alpha,
+            delta_mm,
+            persistence,
+            width: 0,
+            height: 0,
+            history: Vec::new(),
+            validity: Vec::new(),
+        }
+    }
+
+    /// Forget the history, eg. after the camera moves.
+    pub fn reset(&mut self) {
+        self.history.clear();
+        self.validity.clear();
+    }
+
+    /// Filter the next frame.
+    pub fn filter(&mut self, image: &Image) -> Result<Image, DepthFilterError> {
+        let depth = DepthBuffer::from_image(image)?;
+
+        if depth.width != self.width || depth.height != self.height || self.history.len() != depth.data.len() {
+            self.width = depth.width;
+            self.height = depth.height;
+            self.history = vec![0.0; depth.data.len()];
+            self.validity = vec![0; depth.data.len()];
+        }
+
+        let mut output = vec![0; depth.data.len()];
+
+        for (i, value) in depth.data.iter().enumerate() {
+            let current = *value as f32;
+            let previous = self.history[i];
+            let validity = self.validity[i] << 1 | (*value != 0) as u8;
+            self.validity[i] = validity;
+
+            if *value == 0 {
+                if previous > 0.0 && self.persists(validity) {
+                    output[i] = previous.round() as u16;
+                }
+                continue;
+            }
+
+            let smoothed = if previous > 0.0 && (current - previous).abs() <= self.delta_mm {
+                self.alpha * current + (1.0 - self.alpha) * previous
+            } else {
+                current
+            };
+
+            self.history[i] = smoothed;
+            output[i] = smoothed.round() as u16;
+        }
+
+        depth.with_data(output).into_image()
+    }
+
+    fn persists(&self, validity: u8) -> bool {
+        match self.persistence {
+            Persistence::Disabled => false,
+            Persistence::Always => true,
+            Persistence::ValidInLast { valid, frames } => {
+                let frames = frames.clamp(1, 8);
+                let mask = (0xffu16 >> (8 - frames)) as u8;
+                (validity & mask).count_ones() >= valid as u32
+            },
+        }
+    }
+}
+
+const NEIGHBORS: [(isize, isize); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+/// A `Depth16` image copied out into row-major millimeters.
+struct DepthBuffer {
+    width: usize,
+    height: usize,
+    data: Vec<u16>,
+}
+
+impl DepthBuffer {
+    fn from_image(image: &Image) -> Result<Self, DepthFilterError> {
+        match image.get_format() {
+            ImageFormat::Depth16 => {},
+            format => return Err(DepthFilterError::UnsupportedImageFormatError(format)),
+        }
+
+        Ok(Self {
+            width: image.get_width_pixels(),
+            height: image.get_height_pixels(),
+            data: image.read_u16_pixels(),
+        })
+    }
+
+    fn with_data(self, data: Vec<u16>) -> Self {
+        Self { data, ..self }
+    }
+
+    /// The depth at (x, y), or zero outside the image.
+    fn get(&self, x: isize, y: isize) -> u16 {
+        if x < 0 || y < 0 || x >= self.width as isize || y >= self.height as isize {
+            return 0;
+        }
+        self.data[y as usize * self.width + x as usize]
+    }
+
+    fn into_image(self) -> Result<Image, DepthFilterError> {
+        let mut image = Image::create(ImageFormat::Depth16, self.width as u32, self.height as u32, 0)
+            .map_err(DepthFilterError::CreateImageError)?;
+        let stride = image.get_stride_bytes();
+        let buffer = image.get_data_mut();
+
+        for (y, row) in self.data.chunks_exact(self.width.max(1)).enumerate() {
+            for (x, value) in row.iter().enumerate() {
+                buffer[y * stride + x * 2..y * stride + x * 2 + 2].copy_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        Ok(image.into_image())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn depth_image(width: usize, data: Vec<u16>) -> Image {
+        DepthBuffer { width, height: data.len() / width, data }.into_image().unwrap()
+    }
+
+    fn pixels(image: &Image) -> Vec<u16> {
+        image.read_u16_pixels()
+    }
+
+    #[test]
+    fn bilateral_filter_smooths_without_crossing_edges() {
+        // Near and far halves with 8mm of checkered noise, and a hole.
+        let mut data: Vec<u16> = (0..64)
+            .map(|i| if i % 8 < 4 { 1000 } else { 2000 } + 8 * ((i % 8 + i / 8) % 2) as u16)
+            .collect();
+        data[30] = 0;
+        let filtered = pixels(&bilateral_filter(&depth_image(8, data.clone()), 1.0, 10.0).unwrap());
+
+        let mut near = Vec::new();
+        let mut far = Vec::new();
+        for (input, output) in data.iter().zip(filtered.iter()) {
+            match *input {
+                0 => assert_eq!(*output, 0),
+                d if d < 1500 => near.push(*output),
+                _ => far.push(*output),
+            }
+        }
+
+        // Each side keeps to its own depths, with much less noise.
+        let spread = |depths: &[u16]| depths.iter().max().unwrap() - depths.iter().min().unwrap();
+        assert!(near.iter().all(|d| (1000..=1008).contains(d)), "{:?}", near);
+        assert!(far.iter().all(|d| (2000..=2008).contains(d)), "{:?}", far);
+        assert!(spread(&near) <= 4 && spread(&far) <= 4, "{:?} {:?}", near, far);
+    }
+
+    #[test]
+    fn bilateral_filter_rejects_bad_sigmas() {
+        let image = depth_image(2, vec![1000; 4]);
+        for (spatial, range) in [(0.0, 10.0), (1.0, -1.0), (f32::NAN, 10.0), (1.0, f32::NAN)].iter() {
+            match bilateral_filter(&image, *spatial, *range) {
+                Err(DepthFilterError::InvalidParameterError { .. }) => {},
+                other => panic!("sigmas {} and {} gave {:?}", spatial, range, other.map(|_| ())),
+            }
+        }
+
+        let ir = Image::create(ImageFormat::Ir16, 2, 2, 0).unwrap().into_image();
+        assert!(matches!(bilateral_filter(&ir, 1.0, 10.0), Err(DepthFilterError::UnsupportedImageFormatError(_))));
+    }
+
+    #[test]
+    fn fill_holes_from_left_fills_short_runs() {
+        let image = depth_image(10, vec![0, 1000, 0, 0, 1200, 0, 0, 0, 1300, 0]);
+        let filled = pixels(&fill_holes(&image, HoleFilling::FromLeft, 2).unwrap());
+        assert_eq!(filled, vec![0, 1000, 1000, 1000, 1200, 0, 0, 0, 1300, 1300]);
+    }
+
+    #[test]
+    fn fill_holes_from_neighbors_grows_inward() {
+        let image = depth_image(5, vec![1000, 0, 0, 0, 2000]);
+
+        let nearest = |radius| pixels(&fill_holes(&image, HoleFilling::NearestNeighbor, radius).unwrap());
+        assert_eq!(nearest(1), vec![1000, 1000, 0, 2000, 2000]);
+        assert_eq!(nearest(2), vec![1000, 1000, 1000, 2000, 2000]);
+
+        let farthest = pixels(&fill_holes(&image, HoleFilling::FarthestNeighbor, 5).unwrap());
+        assert_eq!(farthest, vec![1000, 1000, 2000, 2000, 2000]);
+
+        // Diagonal neighbors count too.
+        let image = depth_image(3, vec![1500, 0, 0, 0, 0, 0, 0, 0, 900]);
+        let filled = pixels(&fill_holes(&image, HoleFilling::NearestNeighbor, 1).unwrap());
+        assert_eq!(filled, vec![1500, 1500, 0, 1500, 900, 900, 0, 900, 900]);
+    }
+
+    #[test]
+    fn remove_flying_pixels_drops_unsupported_depths() {
+        let mut data = vec![1000; 25];
+        data[12] = 1500; // Floats between the plane and something far behind it.
+        data[0] = 1040;
+        let image = depth_image(5, data);
+
+        let output = pixels(&remove_flying_pixels(&image, 50, 2).unwrap());
+        assert_eq!(output[12], 0);
+        assert_eq!(output[0], 1040);
+        assert_eq!(output.iter().filter(|d| **d == 0).count(), 1);
+
+        // Corners only have three neighbors.
+        let output = pixels(&remove_flying_pixels(&depth_image(5, vec![1000; 25]), 50, 4).unwrap());
+        assert_eq!(output.iter().filter(|d| **d == 0).count(), 4);
+    }
+
+    #[test]
+    fn temporal_filter_smooths_and_restarts_on_motion() {
+        let mut filter = TemporalFilter::new(0.5, 50.0, Persistence::Disabled);
+        let mut next = |depth| pixels(&filter.filter(&depth_image(1, vec![depth])).unwrap())[0];
+
+        assert_eq!(next(1000), 1000);
+        assert_eq!(next(1020), 1010);
+        assert_eq!(next(1030), 1020);
+        assert_eq!(next(1500), 1500);
+        assert_eq!(next(0), 0);
+    }
+
+    #[test]
+    fn temporal_filter_persistence() {
+        let outputs = |persistence| {
+            let mut filter = TemporalFilter::new(0.5, 50.0, persistence);
+            [1000, 1000, 0, 0, 0, 1000, 0].iter()
+                .map(|depth| pixels(&filter.filter(&depth_image(1, vec![*depth])).unwrap())[0])
+                .collect::<Vec<u16>>()
+        };
+
+        assert_eq!(outputs(Persistence::Disabled), vec![1000, 1000, 0, 0, 0, 1000, 0]);
+        assert_eq!(outputs(Persistence::Always), vec![1000; 7]);
+        // Two of the last three frames had depth only for the first frame of each gap.
+        assert_eq!(outputs(Persistence::ValidInLast { valid: 2, frames: 3 }), vec![1000, 1000, 1000, 0, 0, 1000, 0]);
+        assert_eq!(outputs(Persistence::ValidInLast { valid: 1, frames: 3 }), vec![1000, 1000, 1000, 1000, 0, 1000, 1000]);
+    }
+}
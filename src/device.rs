@@ -3,13 +3,16 @@
 use crate::Calibration;
 use crate::Capture;
 use crate::DeviceConfiguration;
-use crate::KinectError;
+use crate::DeviceInfo;
+use crate::Error;
+use crate::HardwareVersion;
+use crate::ImuSample;
 use crate::SynchronizationJackStatus;
 
 use k4a_sys_temp as k4a_sys;
 use std::mem::MaybeUninit;
 use std::{ptr, fmt};
-use crate::error::{DeviceOpenError, DeviceStartCamerasError, DeviceGetCalibrationError, DeviceGetCaptureError, DeviceGetSerialNumberError};
+use crate::error::{ResultCode, DeviceOpenError, DeviceOpenBySerialError, DeviceStartCamerasError, DeviceStartImuError, DeviceGetCalibrationError, DeviceGetCaptureError, DeviceGetImuSampleError, DeviceGetSerialNumberError, DeviceGetSyncJackStatusError, DeviceGetVersionError};
 
 /// A Kinect Device Handle
 #[derive(Debug)]
@@ -43,7 +46,8 @@ impl Device {
         let result = unsafe {
             k4a_sys::k4a_device_open(device_index, &mut device_pointer)
         };
-        if result != k4a_sys::k4a_buffer_result_t_K4A_BUFFER_RESULT_SUCCEEDED {
+        // NB: `k4a_device_open` returns a `k4a_result_t`, not a `k4a_buffer_result_t`.
+        if ResultCode::from_result(result).is_some() {
             // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
             // Linux uses u32 and Windows uses i32.
             // This should be fixed in the `k4a-sys` build script.
@@ -54,6 +58,61 @@ impl Device {
         })
     }
 
+    /// List the installed devices along with their serial numbers, firmware versions, and
+    /// synchronization jack statuses.
+    ///
+    /// Each device is briefly opened in order to interrogate it. Devices that cannot be opened or
+    /// queried (eg. because another process is already using them) are left out of the list; use
+    /// `Device::probe` on each index to find out why.
+    pub fn list() -> Vec<DeviceInfo> {
+        (0..Self::get_installed_count())
+            .filter_map(|device_index| Self::probe(device_index).ok())
+            .collect()
+    }
+
+    /// Briefly open the device with the given index to interrogate it for the details reported by
+    /// `Device::list`.
+    pub fn probe(device_index: u32) -> Result<DeviceInfo, Error> {
+        let device = Self::open(device_index)?;
+
+        Ok(DeviceInfo {
+            index: device_index,
+            serial_number: device.get_serial_number()?,
+            firmware: device.get_version()?,
+            synchronization_jack_status: device.get_synchronization_jack_status()?,
+        })
+    }
+
+    /// Open the device with the given serial number.
+    ///
+    /// Unlike device indices, serial numbers are stable across reboots and replugs. Devices that
+    /// fail to open (eg. because another process has them open) are skipped, and reported in the
+    /// `NotFoundError` if no other device matches.
+    pub fn open_by_serial(serial_number: &str) -> Result<Self, DeviceOpenBySerialError> {
+        let installed_count = Self::get_installed_count();
+        let mut unopenable = Vec::new();
+
+        for device_index in 0..installed_count {
+            let device = match Self::open(device_index) {
+                Ok(device) => device,
+                Err(error) => {
+                    unopenable.push((device_index, error));
+                    continue;
+                },
+            };
+            match device.get_serial_number() {
+                Ok(ref found) if found == serial_number => return Ok(device),
+                _ => continue,
+            }
+        }
+
+        Err(DeviceOpenBySerialError::NotFoundError {
+            serial_number: serial_number.to_string(),
+            installed_count,
+            unopenable,
+        })
+    }
+
     /// Fetch the device serial number.
     pub fn get_serial_number(&self) -> Result<String, DeviceGetSerialNumberError> {
         // First we interrogate the serial number size.
@@ -63,20 +122,23 @@ impl Device {
             k4a_sys::k4a_device_get_serialnum(self.device_pointer, ptr::null_mut(), &mut serial_number_length)
         };
 
-        if result != k4a_sys::k4a_buffer_result_t_K4A_BUFFER_RESULT_TOO_SMALL {
-            return Err(DeviceGetSerialNumberError::CouldNotRequestError);
+        match ResultCode::from_buffer_result(result) {
+            Some(ResultCode::BufferTooSmall) => {},
+            Some(code) => return Err(DeviceGetSerialNumberError::CouldNotRequestError(code)),
+            // NB: Without a buffer the call can't succeed; libk4a should ask for one.
+            None => return Err(DeviceGetSerialNumberError::CouldNotRequestError(ResultCode::Unexpected(result as i32))),
         }
 
         // Now we request to fill a serial number buffer.
         let mut serial_number = vec![0i8; serial_number_length];
-        let serial_number_ptr = (&mut serial_number).as_mut_ptr();
+        let serial_number_ptr = serial_number.as_mut_ptr();
 
         let result = unsafe {
             k4a_sys::k4a_device_get_serialnum(self.device_pointer, serial_number_ptr, &mut serial_number_length)
         };
 
-        if result != k4a_sys::k4a_buffer_result_t_K4A_BUFFER_RESULT_SUCCEEDED {
-            return Err(DeviceGetSerialNumberError::CouldNotReadError);
+        if let Some(code) = ResultCode::from_buffer_result(result) {
+            return Err(DeviceGetSerialNumberError::CouldNotReadError(code));
         }
 
         // NB: Library shouldn't be returning i8's
@@ -87,9 +149,35 @@ impl Device {
             .map_err(|_| DeviceGetSerialNumberError::CouldNotFormatError)
     }
 
+    /// Get the firmware versions of the device components.
+    pub fn get_version(&self) -> Result<HardwareVersion, DeviceGetVersionError> {
+        let mut version_buffer: MaybeUninit<k4a_sys::k4a_hardware_version_t> = MaybeUninit::uninit();
+
+        let version = unsafe {
+            let result = k4a_sys::k4a_device_get_version(self.device_pointer, version_buffer.as_mut_ptr());
+
+            match result {
+                k4a_sys::k4a_result_t_K4A_RESULT_SUCCEEDED => { /* ok, continue */ },
+                k4a_sys::k4a_result_t_K4A_RESULT_FAILED => {
+                    return Err(DeviceGetVersionError::FailedError);
+                },
+                _ => {
+                    // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
+                    // Linux uses u32 and Windows uses i32.
+                    // This should be fixed in the `k4a-sys` build script.
+                    return Err(DeviceGetVersionError::UnexpectedError(result as i32));
+                },
+            }
+
+            version_buffer.assume_init()
+        };
+
+        Ok(version.into())
+    }
+
     /// Get the device synchronization jack statuses.
     /// Each device has an 'in' jack and an 'out' jack.
-    pub fn get_synchronization_jack_status(&self) -> Result<SynchronizationJackStatus, KinectError> {
+    pub fn get_synchronization_jack_status(&self) -> Result<SynchronizationJackStatus, DeviceGetSyncJackStatusError> {
         let mut sync_in_jack_connected = false;
         let mut sync_out_jack_connected = false;
 
@@ -98,11 +186,17 @@ impl Device {
                                               &mut sync_in_jack_connected, &mut sync_out_jack_connected)
         };
 
-        if result != k4a_sys::k4a_result_t_K4A_RESULT_SUCCEEDED {
-            // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
-            // Linux uses u32 and Windows uses i32.
-            // This should be fixed in the `k4a-sys` build script.
-            return Err(KinectError::UnableToGetSyncJackStatus { error_code: result as i32 });
+        match result {
+            k4a_sys::k4a_result_t_K4A_RESULT_SUCCEEDED => {},
+            k4a_sys::k4a_result_t_K4A_RESULT_FAILED => {
+                return Err(DeviceGetSyncJackStatusError::FailedError);
+            },
+            _ => {
+                // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
+                // Linux uses u32 and Windows uses i32.
+                // This should be fixed in the `k4a-sys` build script.
+                return Err(DeviceGetSyncJackStatusError::UnexpectedError(result as i32));
+            },
         }
 
         Ok(SynchronizationJackStatus {
@@ -120,14 +214,14 @@ impl Device {
             k4a_sys::k4a_device_start_cameras(self.device_pointer, &device_config.0)
         };
 
-        if result != k4a_sys::k4a_buffer_result_t_K4A_BUFFER_RESULT_SUCCEEDED {
+        if ResultCode::from_result(result).is_some() {
             // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
             // Linux uses u32 and Windows uses i32.
             // This should be fixed in the `k4a-sys` build script.
             return Err(DeviceStartCamerasError { error_code: result as i32 });
         }
 
-        return Ok(())
+        Ok(())
     }
 
     // TODO: More sensible defaults, or get rid of this entirely.
@@ -191,6 +285,66 @@ impl Device {
         Ok(())
     }
 
+    /// Start the IMU. The cameras must be started first.
+    pub fn start_imu(&self) -> Result<(), DeviceStartImuError> {
+        let result = unsafe {
+            k4a_sys::k4a_device_start_imu(self.device_pointer)
+        };
+
+        if ResultCode::from_result(result).is_some() {
+            // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
+            // Linux uses u32 and Windows uses i32.
+            // This should be fixed in the `k4a-sys` build script.
+            return Err(DeviceStartImuError { error_code: result as i32 });
+        }
+
+        Ok(())
+    }
+
+    /// Stops the IMU.
+    ///
+    /// Like `stop_cameras`, this may be called while another thread is blocking in
+    /// `get_imu_sample`, which will then return a failure.
+    pub fn stop_imu(&self) {
+        unsafe {
+            k4a_sys::k4a_device_stop_imu(self.device_pointer)
+        }
+    }
+
+    /// Get the next IMU sample.
+    pub fn get_imu_sample(&self, timeout_ms: i32) -> Result<ImuSample, DeviceGetImuSampleError> {
+        let mut sample = MaybeUninit::<k4a_sys::k4a_imu_sample_t>::uninit();
+
+        let result = unsafe {
+            k4a_sys::k4a_device_get_imu_sample(self.device_pointer, sample.as_mut_ptr(), timeout_ms)
+        };
+
+        match result {
+            k4a_sys::k4a_wait_result_t_K4A_WAIT_RESULT_SUCCEEDED => { /* ok, continue */ },
+            k4a_sys::k4a_wait_result_t_K4A_WAIT_RESULT_TIMEOUT => {
+                return Err(DeviceGetImuSampleError::TimeoutError { timeout_millis: timeout_ms });
+            },
+            k4a_sys::k4a_wait_result_t_K4A_WAIT_RESULT_FAILED => {
+                return Err(DeviceGetImuSampleError::FailedError);
+            }
+            _ => {
+                // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
+                // Linux uses u32 and Windows uses i32.
+                // This should be fixed in the `k4a-sys` build script.
+                return Err(DeviceGetImuSampleError::UnexpectedError(result as i32));
+            }
+        }
+
+        let sample = unsafe { sample.assume_init() };
+        Ok(ImuSample {
+            temperature_c: sample.temperature,
+            acc_sample: unsafe { sample.acc_sample.v },
+            acc_timestamp_usec: sample.acc_timestamp_usec,
+            gyro_sample: unsafe { sample.gyro_sample.v },
+            gyro_timestamp_usec: sample.gyro_timestamp_usec,
+        })
+    }
+
     /// Get the camera calibration for the entire Azure Kinect device.
     ///
     /// The calibration represents the data needed to transform between the camera views and may be
@@ -1,6 +1,7 @@
 use k4a_sys_temp as k4a_sys;
 
 /// Copied from k4a-sys
+#[derive(Clone)]
 pub struct DeviceConfiguration (pub k4a_sys::k4a_device_configuration_t);
 
 /// Copied from k4a-sys
@@ -40,3 +41,9 @@ impl DeviceConfiguration {
     }
 }
 
+impl Default for DeviceConfiguration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
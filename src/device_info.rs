@@ -0,0 +1,17 @@
+use crate::HardwareVersion;
+use crate::SynchronizationJackStatus;
+
+/// Describes an installed device, as reported by `Device::list`.
+#[derive(Debug, Clone)]
+pub struct DeviceInfo {
+    /// The index used to open the device.
+    /// NB: Indices are assigned by libk4a and can change across reboots or replugs. Prefer the
+    /// serial number when a specific device is needed.
+    pub index: u32,
+    /// The device serial number.
+    pub serial_number: String,
+    /// The firmware versions reported by the device.
+    pub firmware: HardwareVersion,
+    /// The device synchronization jack statuses.
+    pub synchronization_jack_status: SynchronizationJackStatus,
+}
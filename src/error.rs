@@ -1,7 +1,361 @@
 //! Crate error types
 
+use crate::CalibrationType;
+use crate::ImageFormat;
+
+use k4a_sys_temp as k4a_sys;
 use std::fmt;
-use std::error::Error;
+use std::error::Error as StdError;
+use std::io;
+
+/// Any error from this crate.
+///
+/// Every error type converts into this with `From`, so functions returning different error types
+/// can be combined with `?`.
+#[derive(Debug)]
+pub enum Error {
+    /// Creating an image with `Image::create`.
+    CreateImageError(CreateImageError),
+    /// Converting points between cameras with `Calibration`.
+    CalibrationConversionError(CalibrationConversionError),
+    /// Detecting a calibration target or solving for camera extrinsics.
+    ExtrinsicCalibrationError(ExtrinsicCalibrationError),
+    /// Getting a device's calibration with `Device::get_calibration`.
+    DeviceGetCalibrationError(DeviceGetCalibrationError),
+    /// Getting a capture with `Device::get_capture`.
+    DeviceGetCaptureError(DeviceGetCaptureError),
+    /// Getting firmware versions with `Device::get_version`.
+    DeviceGetVersionError(DeviceGetVersionError),
+    /// Getting a serial number with `Device::get_serial_number`.
+    DeviceGetSerialNumberError(DeviceGetSerialNumberError),
+    /// Getting synchronization jack statuses with `Device::get_synchronization_jack_status`.
+    DeviceGetSyncJackStatusError(DeviceGetSyncJackStatusError),
+    /// Opening a device with `Device::open`.
+    DeviceOpenError(DeviceOpenError),
+    /// Opening a device with `Device::open_by_serial`.
+    DeviceOpenBySerialError(DeviceOpenBySerialError),
+    /// Starting the cameras with `Device::start_cameras`.
+    DeviceStartCamerasError(DeviceStartCamerasError),
+    /// Starting the IMU with `Device::start_imu`.
+    DeviceStartImuError(DeviceStartImuError),
+    /// Getting an IMU sample with `Device::get_imu_sample`.
+    DeviceGetImuSampleError(DeviceGetImuSampleError),
+    /// Opening, starting or capturing from a `SyncedRig`.
+    SyncedRigError(SyncedRigError),
+    /// Capturing from or reconnecting a `ResilientDevice`.
+    ResilientDeviceError(ResilientDeviceError),
+    /// Creating a `StreamStats`.
+    StreamStatsError(StreamStatsError),
+    /// Transforming images between cameras with `Transformation`.
+    TransformationError(TransformationError),
+    /// Reading or writing point cloud files.
+    PointCloudIoError(PointCloudIoError),
+    /// Filtering or downsampling point clouds.
+    PointCloudProcessingError(PointCloudProcessingError),
+    /// Filtering depth images with `depth_filter`.
+    DepthFilterError(DepthFilterError),
+    /// Rendering images with `visualization`.
+    VisualizationError(VisualizationError),
+    /// Creating a `BodyTracker`.
+    #[cfg(feature = "body-tracking")]
+    BodyTrackerCreateError(BodyTrackerCreateError),
+    /// Queueing captures on or popping frames from a `BodyTracker`.
+    #[cfg(feature = "body-tracking")]
+    BodyTrackerQueueError(BodyTrackerQueueError),
+    /// Exporting skeletons with `motion_export`.
+    MotionExportError(MotionExportError),
+    /// Installing a debug message handler with `set_debug_message_handler`.
+    SetDebugMessageHandlerError(SetDebugMessageHandlerError),
+    /// Installing an allocator with `set_allocator`.
+    SetAllocatorError(SetAllocatorError),
+    /// Acquiring an image from an `ImagePool`.
+    ImagePoolError(ImagePoolError),
+    /// Converting images to and from other crates' image types.
+    ImageConversionError(ImageConversionError),
+    /// Recording with a `Recorder`.
+    #[cfg(feature = "record")]
+    RecordError(RecordError),
+    /// Reading a recording with a `Playback`.
+    #[cfg(feature = "record")]
+    PlaybackError(PlaybackError),
+}
+
+impl Error {
+    /// The libk4a result behind the error, if it came from a failed libk4a call.
+    pub fn result_code(&self) -> Option<ResultCode> {
+        match self {
+            Error::CreateImageError(error) =>
+                ResultCode::from_result(error.error_code as k4a_sys::k4a_result_t),
+            Error::CalibrationConversionError(CalibrationConversionError::FailedError) => Some(ResultCode::Failed),
+            Error::CalibrationConversionError(CalibrationConversionError::UnexpectedError(code)) =>
+                Some(ResultCode::Unexpected(*code)),
+            Error::DeviceGetCalibrationError(DeviceGetCalibrationError::FailedError) => Some(ResultCode::Failed),
+            Error::DeviceGetCalibrationError(DeviceGetCalibrationError::UnexpectedError(code)) =>
+                Some(ResultCode::Unexpected(*code)),
+            Error::DeviceGetCaptureError(DeviceGetCaptureError::TimeoutError { .. }) => Some(ResultCode::Timeout),
+            Error::DeviceGetCaptureError(DeviceGetCaptureError::FailedError) => Some(ResultCode::Failed),
+            Error::DeviceGetCaptureError(DeviceGetCaptureError::UnexpectedError(code)) =>
+                Some(ResultCode::Unexpected(*code)),
+            Error::DeviceGetVersionError(DeviceGetVersionError::FailedError) => Some(ResultCode::Failed),
+            Error::DeviceGetVersionError(DeviceGetVersionError::UnexpectedError(code)) =>
+                Some(ResultCode::Unexpected(*code)),
+            Error::DeviceGetSerialNumberError(DeviceGetSerialNumberError::CouldNotRequestError(code))
+            | Error::DeviceGetSerialNumberError(DeviceGetSerialNumberError::CouldNotReadError(code)) => Some(*code),
+            Error::DeviceGetSyncJackStatusError(DeviceGetSyncJackStatusError::FailedError) => Some(ResultCode::Failed),
+            Error::DeviceGetSyncJackStatusError(DeviceGetSyncJackStatusError::UnexpectedError(code)) =>
+                Some(ResultCode::Unexpected(*code)),
+            Error::DeviceOpenError(error) =>
+                ResultCode::from_result(error.error_code as k4a_sys::k4a_result_t),
+            Error::DeviceOpenBySerialError(DeviceOpenBySerialError::NotFoundError { unopenable, .. }) =>
+                unopenable.first().and_then(|(_, error)| Error::from(*error).result_code()),
+            Error::DeviceStartCamerasError(error) =>
+                ResultCode::from_result(error.error_code as k4a_sys::k4a_result_t),
+            Error::DeviceStartImuError(error) =>
+                ResultCode::from_result(error.error_code as k4a_sys::k4a_result_t),
+            Error::DeviceGetImuSampleError(DeviceGetImuSampleError::TimeoutError { .. }) => Some(ResultCode::Timeout),
+            Error::DeviceGetImuSampleError(DeviceGetImuSampleError::FailedError) => Some(ResultCode::Failed),
+            Error::DeviceGetImuSampleError(DeviceGetImuSampleError::UnexpectedError(code)) =>
+                Some(ResultCode::Unexpected(*code)),
+            Error::SyncedRigError(SyncedRigError::OpenError(error)) => Error::from(*error).result_code(),
+            Error::SyncedRigError(SyncedRigError::GetSerialNumberError(error)) => Error::from(*error).result_code(),
+            Error::SyncedRigError(SyncedRigError::GetSyncJackStatusError { error, .. }) =>
+                Error::from(*error).result_code(),
+            Error::SyncedRigError(SyncedRigError::StartCamerasError { error, .. }) =>
+                Error::from(*error).result_code(),
+            Error::SyncedRigError(SyncedRigError::GetCaptureError { error, .. }) => Error::from(*error).result_code(),
+            Error::ResilientDeviceError(ResilientDeviceError::TimeoutError { .. }) => Some(ResultCode::Timeout),
+            Error::ResilientDeviceError(ResilientDeviceError::ReconnectError { error, .. }) => error.result_code(),
+            Error::TransformationError(TransformationError::CreateImageError(error)) =>
+                Error::from(*error).result_code(),
+            Error::TransformationError(TransformationError::FailedError) => Some(ResultCode::Failed),
+            Error::TransformationError(TransformationError::UnexpectedError(code)) =>
+                Some(ResultCode::Unexpected(*code)),
+            Error::DepthFilterError(DepthFilterError::CreateImageError(error)) => Error::from(*error).result_code(),
+            Error::VisualizationError(VisualizationError::CreateImageError(error)) =>
+                Error::from(*error).result_code(),
+            #[cfg(feature = "body-tracking")]
+            Error::BodyTrackerCreateError(BodyTrackerCreateError::FailedError) => Some(ResultCode::Failed),
+            #[cfg(feature = "body-tracking")]
+            Error::BodyTrackerCreateError(BodyTrackerCreateError::UnexpectedError(code)) =>
+                Some(ResultCode::Unexpected(*code)),
+            #[cfg(feature = "body-tracking")]
+            Error::BodyTrackerQueueError(BodyTrackerQueueError::TimeoutError { .. }) => Some(ResultCode::Timeout),
+            #[cfg(feature = "body-tracking")]
+            Error::BodyTrackerQueueError(BodyTrackerQueueError::FailedError) => Some(ResultCode::Failed),
+            #[cfg(feature = "body-tracking")]
+            Error::BodyTrackerQueueError(BodyTrackerQueueError::UnexpectedError(code)) =>
+                Some(ResultCode::Unexpected(*code)),
+            Error::SetDebugMessageHandlerError(SetDebugMessageHandlerError::FailedError) => Some(ResultCode::Failed),
+            Error::SetDebugMessageHandlerError(SetDebugMessageHandlerError::UnexpectedError(code)) =>
+                Some(ResultCode::Unexpected(*code)),
+            Error::SetAllocatorError(SetAllocatorError::FailedError) => Some(ResultCode::Failed),
+            Error::SetAllocatorError(SetAllocatorError::UnexpectedError(code)) => Some(ResultCode::Unexpected(*code)),
+            Error::ImagePoolError(ImagePoolError::CreateImageError(error)) => Error::from(*error).result_code(),
+            Error::ImageConversionError(ImageConversionError::CreateImageError(error)) =>
+                Error::from(*error).result_code(),
+            #[cfg(feature = "record")]
+            Error::RecordError(RecordError::FailedError) => Some(ResultCode::Failed),
+            #[cfg(feature = "record")]
+            Error::RecordError(RecordError::UnexpectedError(code)) => Some(ResultCode::Unexpected(*code)),
+            #[cfg(feature = "record")]
+            Error::PlaybackError(PlaybackError::FailedError) => Some(ResultCode::Failed),
+            #[cfg(feature = "record")]
+            Error::PlaybackError(PlaybackError::UnexpectedError(code)) => Some(ResultCode::Unexpected(*code)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::CreateImageError(error) => error.fmt(f),
+            Error::CalibrationConversionError(error) => error.fmt(f),
+            Error::ExtrinsicCalibrationError(error) => error.fmt(f),
+            Error::DeviceGetCalibrationError(error) => error.fmt(f),
+            Error::DeviceGetCaptureError(error) => error.fmt(f),
+            Error::DeviceGetVersionError(error) => error.fmt(f),
+            Error::DeviceGetSerialNumberError(error) => error.fmt(f),
+            Error::DeviceGetSyncJackStatusError(error) => error.fmt(f),
+            Error::DeviceOpenError(error) => error.fmt(f),
+            Error::DeviceOpenBySerialError(error) => error.fmt(f),
+            Error::DeviceStartCamerasError(error) => error.fmt(f),
+            Error::DeviceStartImuError(error) => error.fmt(f),
+            Error::DeviceGetImuSampleError(error) => error.fmt(f),
+            Error::SyncedRigError(error) => error.fmt(f),
+            Error::ResilientDeviceError(error) => error.fmt(f),
+            Error::StreamStatsError(error) => error.fmt(f),
+            Error::TransformationError(error) => error.fmt(f),
+            Error::PointCloudIoError(error) => error.fmt(f),
+            Error::PointCloudProcessingError(error) => error.fmt(f),
+            Error::DepthFilterError(error) => error.fmt(f),
+            Error::VisualizationError(error) => error.fmt(f),
+            #[cfg(feature = "body-tracking")]
+            Error::BodyTrackerCreateError(error) => error.fmt(f),
+            #[cfg(feature = "body-tracking")]
+            Error::BodyTrackerQueueError(error) => error.fmt(f),
+            Error::MotionExportError(error) => error.fmt(f),
+            Error::SetDebugMessageHandlerError(error) => error.fmt(f),
+            Error::SetAllocatorError(error) => error.fmt(f),
+            Error::ImagePoolError(error) => error.fmt(f),
+            Error::ImageConversionError(error) => error.fmt(f),
+            #[cfg(feature = "record")]
+            Error::RecordError(error) => error.fmt(f),
+            #[cfg(feature = "record")]
+            Error::PlaybackError(error) => error.fmt(f),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::CreateImageError(error) => Some(error),
+            Error::CalibrationConversionError(error) => Some(error),
+            Error::ExtrinsicCalibrationError(error) => Some(error),
+            Error::DeviceGetCalibrationError(error) => Some(error),
+            Error::DeviceGetCaptureError(error) => Some(error),
+            Error::DeviceGetVersionError(error) => Some(error),
+            Error::DeviceGetSerialNumberError(error) => Some(error),
+            Error::DeviceGetSyncJackStatusError(error) => Some(error),
+            Error::DeviceOpenError(error) => Some(error),
+            Error::DeviceOpenBySerialError(error) => Some(error),
+            Error::DeviceStartCamerasError(error) => Some(error),
+            Error::DeviceStartImuError(error) => Some(error),
+            Error::DeviceGetImuSampleError(error) => Some(error),
+            Error::SyncedRigError(error) => Some(error),
+            Error::ResilientDeviceError(error) => Some(error),
+            Error::StreamStatsError(error) => Some(error),
+            Error::TransformationError(error) => Some(error),
+            Error::PointCloudIoError(error) => Some(error),
+            Error::PointCloudProcessingError(error) => Some(error),
+            Error::DepthFilterError(error) => Some(error),
+            Error::VisualizationError(error) => Some(error),
+            #[cfg(feature = "body-tracking")]
+            Error::BodyTrackerCreateError(error) => Some(error),
+            #[cfg(feature = "body-tracking")]
+            Error::BodyTrackerQueueError(error) => Some(error),
+            Error::MotionExportError(error) => Some(error),
+            Error::SetDebugMessageHandlerError(error) => Some(error),
+            Error::SetAllocatorError(error) => Some(error),
+            Error::ImagePoolError(error) => Some(error),
+            Error::ImageConversionError(error) => Some(error),
+            #[cfg(feature = "record")]
+            Error::RecordError(error) => Some(error),
+            #[cfg(feature = "record")]
+            Error::PlaybackError(error) => Some(error),
+        }
+    }
+}
+
+macro_rules! impl_from_error {
+    ($($(#[$attribute:meta])* $error:ident),* $(,)?) => {
+        $(
+            $(#[$attribute])*
+            impl From<$error> for Error {
+                fn from(error: $error) -> Self {
+                    Error::$error(error)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_error!(
+    CreateImageError,
+    CalibrationConversionError,
+    ExtrinsicCalibrationError,
+    DeviceGetCalibrationError,
+    DeviceGetCaptureError,
+    DeviceGetVersionError,
+    DeviceGetSerialNumberError,
+    DeviceGetSyncJackStatusError,
+    DeviceOpenError,
+    DeviceOpenBySerialError,
+    DeviceStartCamerasError,
+    DeviceStartImuError,
+    DeviceGetImuSampleError,
+    SyncedRigError,
+    ResilientDeviceError,
+    StreamStatsError,
+    TransformationError,
+    PointCloudIoError,
+    PointCloudProcessingError,
+    DepthFilterError,
+    VisualizationError,
+    #[cfg(feature = "body-tracking")]
+    BodyTrackerCreateError,
+    #[cfg(feature = "body-tracking")]
+    BodyTrackerQueueError,
+    MotionExportError,
+    SetDebugMessageHandlerError,
+    SetAllocatorError,
+    ImagePoolError,
+    ImageConversionError,
+    #[cfg(feature = "record")]
+    RecordError,
+    #[cfg(feature = "record")]
+    PlaybackError,
+);
+
+/// How a libk4a call failed, decoded the same way from each of libk4a's result types:
+/// `k4a_result_t`, `k4a_wait_result_t` and `k4a_buffer_result_t`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ResultCode {
+    /// The call failed (`K4A_RESULT_FAILED`, `K4A_WAIT_RESULT_FAILED` or `K4A_BUFFER_RESULT_FAILED`).
+    Failed,
+    /// The call timed out (`K4A_WAIT_RESULT_TIMEOUT`).
+    Timeout,
+    /// The buffer passed was too small (`K4A_BUFFER_RESULT_TOO_SMALL`).
+    BufferTooSmall,
+    /// A value libk4a doesn't document.
+    Unexpected(i32),
+}
+
+// NB: Linux and Windows platforms differ in integer types used here, so we cast unexpected codes.
+// Linux uses u32 and Windows uses i32.
+// This should be fixed in the `k4a-sys` build script.
+impl ResultCode {
+    /// Decode a `k4a_result_t`, or None if it's success.
+    pub fn from_result(result: k4a_sys::k4a_result_t) -> Option<Self> {
+        match result {
+            k4a_sys::k4a_result_t_K4A_RESULT_SUCCEEDED => None,
+            k4a_sys::k4a_result_t_K4A_RESULT_FAILED => Some(ResultCode::Failed),
+            _ => Some(ResultCode::Unexpected(result as i32)),
+        }
+    }
+
+    /// Decode a `k4a_wait_result_t`, or None if it's success.
+    pub fn from_wait_result(result: k4a_sys::k4a_wait_result_t) -> Option<Self> {
+        match result {
+            k4a_sys::k4a_wait_result_t_K4A_WAIT_RESULT_SUCCEEDED => None,
+            k4a_sys::k4a_wait_result_t_K4A_WAIT_RESULT_FAILED => Some(ResultCode::Failed),
+            k4a_sys::k4a_wait_result_t_K4A_WAIT_RESULT_TIMEOUT => Some(ResultCode::Timeout),
+            _ => Some(ResultCode::Unexpected(result as i32)),
+        }
+    }
+
+    /// Decode a `k4a_buffer_result_t`, or None if it's success.
+    pub fn from_buffer_result(result: k4a_sys::k4a_buffer_result_t) -> Option<Self> {
+        match result {
+            k4a_sys::k4a_buffer_result_t_K4A_BUFFER_RESULT_SUCCEEDED => None,
+            k4a_sys::k4a_buffer_result_t_K4A_BUFFER_RESULT_FAILED => Some(ResultCode::Failed),
+            k4a_sys::k4a_buffer_result_t_K4A_BUFFER_RESULT_TOO_SMALL => Some(ResultCode::BufferTooSmall),
+            _ => Some(ResultCode::Unexpected(result as i32)),
+        }
+    }
+}
+
+impl fmt::Display for ResultCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResultCode::Failed => write!(f, "failed"),
+            ResultCode::Timeout => write!(f, "timed out"),
+            ResultCode::BufferTooSmall => write!(f, "buffer too small"),
+            ResultCode::Unexpected(code) => write!(f, "unexpected result (code: {})", code),
+        }
+    }
+}
 
 /// Represents errors creating images with `k4a_image_create`.
 #[derive(Copy, Clone, Debug)]
@@ -16,8 +370,51 @@ impl fmt::Display for CreateImageError {
     }
 }
 
-impl Error for CreateImageError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
+impl StdError for CreateImageError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+}
+
+/// Represents errors detecting calibration targets and estimating extrinsics between cameras.
+#[derive(Copy, Clone, Debug)]
+pub enum ExtrinsicCalibrationError {
+    /// Targets can't be detected in images of this format.
+    UnsupportedImageFormatError(ImageFormat),
+    /// Only the color and depth cameras have intrinsics to calibrate with.
+    UnsupportedCameraError(CalibrationType),
+    /// The target was not found in the image.
+    TargetNotFoundError,
+    /// The target corners don't determine a pose (eg. they're collinear or outside the lens model).
+    DegenerateTargetError,
+    /// A view had a different number of images than there are cameras.
+    ImageCountError { expected: usize, actual: usize },
+    /// A camera never saw the target at the same time as the reference camera.
+    NoSharedViewsError { camera_index: usize },
+}
+
+impl fmt::Display for ExtrinsicCalibrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtrinsicCalibrationError::UnsupportedImageFormatError(format) =>
+                write!(f, "ExtrinsicCalibrationError::UnsupportedImageFormatError ({:?})", format),
+            ExtrinsicCalibrationError::UnsupportedCameraError(camera) =>
+                write!(f, "ExtrinsicCalibrationError::UnsupportedCameraError ({:?})", camera),
+            ExtrinsicCalibrationError::TargetNotFoundError =>
+                write!(f, "ExtrinsicCalibrationError::TargetNotFoundError"),
+            ExtrinsicCalibrationError::DegenerateTargetError =>
+                write!(f, "ExtrinsicCalibrationError::DegenerateTargetError"),
+            ExtrinsicCalibrationError::ImageCountError { expected, actual } =>
+                write!(f, "ExtrinsicCalibrationError::ImageCountError (expected {} images, got {})",
+                       expected, actual),
+            ExtrinsicCalibrationError::NoSharedViewsError { camera_index } =>
+                write!(f, "ExtrinsicCalibrationError::NoSharedViewsError (camera {})", camera_index),
+        }
+    }
+}
+
+impl StdError for ExtrinsicCalibrationError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
         None
     }
 }
@@ -42,8 +439,47 @@ impl fmt::Display for DeviceGetCalibrationError {
     }
 }
 
-impl Error for DeviceGetCalibrationError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
+impl StdError for DeviceGetCalibrationError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+}
+
+/// Represents errors converting points with the `k4a_calibration_*_to_*` functions.
+#[derive(Copy, Clone, Debug)]
+pub enum CalibrationConversionError {
+    /// The conversion failed (eg. the calibration is invalid, or a sensor has no camera).
+    FailedError,
+    /// Unexpected error code returned by libk4a.
+    UnexpectedError(i32),
+}
+
+impl CalibrationConversionError {
+    pub(crate) fn check(result: k4a_sys::k4a_result_t) -> Result<(), CalibrationConversionError> {
+        match result {
+            k4a_sys::k4a_result_t_K4A_RESULT_SUCCEEDED => Ok(()),
+            k4a_sys::k4a_result_t_K4A_RESULT_FAILED => Err(CalibrationConversionError::FailedError),
+            // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
+            // Linux uses u32 and Windows uses i32.
+            // This should be fixed in the `k4a-sys` build script.
+            _ => Err(CalibrationConversionError::UnexpectedError(result as i32)),
+        }
+    }
+}
+
+impl fmt::Display for CalibrationConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalibrationConversionError::FailedError =>
+                write!(f, "CalibrationConversionError::FailedError"),
+            CalibrationConversionError::UnexpectedError(code) =>
+                write!(f, "CalibrationConversionError::UnexpectedError (code: {})", code),
+        }
+    }
+}
+
+impl StdError for CalibrationConversionError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
         None
     }
 }
@@ -74,8 +510,34 @@ impl fmt::Display for DeviceGetCaptureError {
     }
 }
 
-impl Error for DeviceGetCaptureError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
+impl StdError for DeviceGetCaptureError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+}
+
+/// Represents errors getting device firmware versions with `k4a_device_get_version`.
+#[derive(Copy, Clone, Debug)]
+pub enum DeviceGetVersionError {
+    /// Failed to get the device firmware version.
+    FailedError,
+    /// Unexpected error code returned by libk4a.
+    UnexpectedError(i32),
+}
+
+impl fmt::Display for DeviceGetVersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceGetVersionError::FailedError =>
+                write!(f, "DeviceGetVersionError::FailedError"),
+            DeviceGetVersionError::UnexpectedError(code) =>
+                write!(f, "DeviceGetVersionError::UnexpectedError (code: {})", code),
+        }
+    }
+}
+
+impl StdError for DeviceGetVersionError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
         None
     }
 }
@@ -83,10 +545,10 @@ impl Error for DeviceGetCaptureError {
 /// Represents errors getting device serial numbers with `k4a_device_get_serialnum`.
 #[derive(Copy, Clone, Debug)]
 pub enum DeviceGetSerialNumberError {
-    /// Buffering error during initial request (1st API call).
-    CouldNotRequestError,
-    /// Buffering error when reading serial number (2nd API call).
-    CouldNotReadError,
+    /// Buffering error during initial request (1st API call), with libk4a's result.
+    CouldNotRequestError(ResultCode),
+    /// Buffering error when reading serial number (2nd API call), with libk4a's result.
+    CouldNotReadError(ResultCode),
     /// Could not format as a UTF-8 string.
     CouldNotFormatError,
 }
@@ -94,18 +556,44 @@ pub enum DeviceGetSerialNumberError {
 impl fmt::Display for DeviceGetSerialNumberError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            DeviceGetSerialNumberError::CouldNotRequestError =>
-                write!(f, "DeviceGetSerialNumberError::CouldNotRequestError"),
-            DeviceGetSerialNumberError::CouldNotReadError =>
-                write!(f, "DeviceGetSerialNumberError::CouldNotReadError"),
+            DeviceGetSerialNumberError::CouldNotRequestError(code) =>
+                write!(f, "DeviceGetSerialNumberError::CouldNotRequestError ({})", code),
+            DeviceGetSerialNumberError::CouldNotReadError(code) =>
+                write!(f, "DeviceGetSerialNumberError::CouldNotReadError ({})", code),
             DeviceGetSerialNumberError::CouldNotFormatError =>
                 write!(f, "DeviceGetSerialNumberError::CouldNotFormatError"),
         }
     }
 }
 
-impl Error for DeviceGetSerialNumberError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
+impl StdError for DeviceGetSerialNumberError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+}
+
+/// Represents errors getting synchronization jack statuses with `k4a_device_get_sync_jack`.
+#[derive(Copy, Clone, Debug)]
+pub enum DeviceGetSyncJackStatusError {
+    /// Failed to get the synchronization jack status.
+    FailedError,
+    /// Unexpected error code returned by libk4a.
+    UnexpectedError(i32),
+}
+
+impl fmt::Display for DeviceGetSyncJackStatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceGetSyncJackStatusError::FailedError =>
+                write!(f, "DeviceGetSyncJackStatusError::FailedError"),
+            DeviceGetSyncJackStatusError::UnexpectedError(code) =>
+                write!(f, "DeviceGetSyncJackStatusError::UnexpectedError (code: {})", code),
+        }
+    }
+}
+
+impl StdError for DeviceGetSyncJackStatusError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
         None
     }
 }
@@ -122,12 +610,56 @@ impl fmt::Display for DeviceOpenError {
     }
 }
 
-impl Error for DeviceOpenError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
+impl StdError for DeviceOpenError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
         None
     }
 }
 
+/// Represents errors opening devices by serial number with `Device::open_by_serial`.
+#[derive(Clone, Debug)]
+pub enum DeviceOpenBySerialError {
+    /// None of the installed devices that could be opened has the requested serial number.
+    ///
+    /// If some devices couldn't be opened (eg. they're in use by another process), the requested
+    /// device may be one of them.
+    NotFoundError {
+        /// The serial number that was requested.
+        serial_number: String,
+        /// The number of installed devices that were searched.
+        installed_count: u32,
+        /// The indices of the devices that couldn't be opened, and why.
+        unopenable: Vec<(u32, DeviceOpenError)>,
+    },
+}
+
+impl fmt::Display for DeviceOpenBySerialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceOpenBySerialError::NotFoundError { serial_number, installed_count, unopenable } if unopenable.is_empty() =>
+                write!(f, "DeviceOpenBySerialError::NotFoundError (serial number {} not found among {} installed devices)",
+                       serial_number, installed_count),
+            DeviceOpenBySerialError::NotFoundError { serial_number, installed_count, unopenable } => {
+                let unopenable = unopenable.iter()
+                    .map(|(index, error)| format!("device {}: {}", index, error))
+                    .collect::<Vec<_>>();
+                write!(f, "DeviceOpenBySerialError::NotFoundError (serial number {} not found among {} installed devices, \
+                           could not open {})",
+                       serial_number, installed_count, unopenable.join(", "))
+            },
+        }
+    }
+}
+
+impl StdError for DeviceOpenBySerialError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            DeviceOpenBySerialError::NotFoundError { unopenable, .. } =>
+                unopenable.first().map(|(_, error)| error as &(dyn StdError + 'static)),
+        }
+    }
+}
+
 /// Represents errors opening devices with `k4a_device_start_cameras`.
 #[derive(Copy, Clone, Debug)]
 pub struct DeviceStartCamerasError {
@@ -141,8 +673,698 @@ impl fmt::Display for DeviceStartCamerasError {
     }
 }
 
-impl Error for DeviceStartCamerasError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
+impl StdError for DeviceStartCamerasError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+}
+
+/// Represents errors starting the IMU with `k4a_device_start_imu`.
+#[derive(Copy, Clone, Debug)]
+pub struct DeviceStartImuError {
+    /// The error code returned by libk4a.
+    pub error_code: i32,
+}
+
+impl fmt::Display for DeviceStartImuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DeviceStartImuError (code: {})", self.error_code)
+    }
+}
+
+impl StdError for DeviceStartImuError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+}
+
+/// Represents errors getting IMU samples with `k4a_device_get_imu_sample`.
+#[derive(Copy, Clone, Debug)]
+pub enum DeviceGetImuSampleError {
+    /// No sample arrived before the timeout elapsed.
+    /// Error contains the original value of our timeout threshold (not the time elapsed).
+    TimeoutError { timeout_millis: i32 },
+    /// There was a failure in getting the sample
+    FailedError,
+    /// Unexpected error code returned by libk4a
+    UnexpectedError(i32),
+}
+
+impl fmt::Display for DeviceGetImuSampleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceGetImuSampleError::TimeoutError { timeout_millis } =>
+                write!(f, "DeviceGetImuSampleError::TimeoutError (timeout of {} millis elapsed)", timeout_millis),
+            DeviceGetImuSampleError::FailedError =>
+                write!(f, "DeviceGetImuSampleError::FailedError"),
+            DeviceGetImuSampleError::UnexpectedError(code) =>
+                write!(f, "DeviceGetImuSampleError::UnexpectedError (code: {})", code),
+        }
+    }
+}
+
+impl StdError for DeviceGetImuSampleError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+}
+
+/// Represents errors opening, starting, and capturing from a `SyncedRig`.
+#[derive(Clone, Debug)]
+pub enum SyncedRigError {
+    /// No devices were given, or none are installed.
+    NoDevicesError,
+    /// A device could not be opened by index.
+    OpenError(DeviceOpenError),
+    /// A device could not be opened by serial number.
+    OpenBySerialError(DeviceOpenBySerialError),
+    /// The serial number of a device could not be read.
+    GetSerialNumberError(DeviceGetSerialNumberError),
+    /// The synchronization jack status of a device could not be read.
+    GetSyncJackStatusError { serial_number: String, error: DeviceGetSyncJackStatusError },
+    /// A device has neither its 'in' nor its 'out' synchronization jack connected.
+    UnconnectedDeviceError { serial_number: String },
+    /// No device has only its 'out' synchronization jack connected.
+    NoMasterError,
+    /// More than one device has only its 'out' synchronization jack connected.
+    MultipleMastersError { serial_numbers: Vec<String> },
+    /// The master must run its color camera to drive the synchronization signal.
+    MasterColorCameraDisabledError,
+    /// The cameras of a device failed to start.
+    StartCamerasError { serial_number: String, error: DeviceStartCamerasError },
+    /// A capture could not be read from a device.
+    GetCaptureError { serial_number: String, error: DeviceGetCaptureError },
+    /// A capture had no images to read a timestamp from.
+    MissingTimestampError { serial_number: String },
+    /// Captures from every device could not be aligned within the allowed number of reads.
+    AlignmentError { reads: u32 },
+}
+
+impl fmt::Display for SyncedRigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncedRigError::NoDevicesError =>
+                write!(f, "SyncedRigError::NoDevicesError"),
+            SyncedRigError::OpenError(error) =>
+                write!(f, "SyncedRigError::OpenError ({})", error),
+            SyncedRigError::OpenBySerialError(error) =>
+                write!(f, "SyncedRigError::OpenBySerialError ({})", error),
+            SyncedRigError::GetSerialNumberError(error) =>
+                write!(f, "SyncedRigError::GetSerialNumberError ({})", error),
+            SyncedRigError::GetSyncJackStatusError { serial_number, error } =>
+                write!(f, "SyncedRigError::GetSyncJackStatusError (device {}: {})", serial_number, error),
+            SyncedRigError::UnconnectedDeviceError { serial_number } =>
+                write!(f, "SyncedRigError::UnconnectedDeviceError (device {} has no sync cables)", serial_number),
+            SyncedRigError::NoMasterError =>
+                write!(f, "SyncedRigError::NoMasterError"),
+            SyncedRigError::MultipleMastersError { serial_numbers } =>
+                write!(f, "SyncedRigError::MultipleMastersError (devices {})", serial_numbers.join(", ")),
+            SyncedRigError::MasterColorCameraDisabledError =>
+                write!(f, "SyncedRigError::MasterColorCameraDisabledError"),
+            SyncedRigError::StartCamerasError { serial_number, error } =>
+                write!(f, "SyncedRigError::StartCamerasError (device {}: {})", serial_number, error),
+            SyncedRigError::GetCaptureError { serial_number, error } =>
+                write!(f, "SyncedRigError::GetCaptureError (device {}: {})", serial_number, error),
+            SyncedRigError::MissingTimestampError { serial_number } =>
+                write!(f, "SyncedRigError::MissingTimestampError (device {})", serial_number),
+            SyncedRigError::AlignmentError { reads } =>
+                write!(f, "SyncedRigError::AlignmentError (gave up after {} reads)", reads),
+        }
+    }
+}
+
+impl StdError for SyncedRigError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            SyncedRigError::OpenError(error) => Some(error),
+            SyncedRigError::OpenBySerialError(error) => Some(error),
+            SyncedRigError::GetSerialNumberError(error) => Some(error),
+            SyncedRigError::GetSyncJackStatusError { error, .. } => Some(error),
+            SyncedRigError::StartCamerasError { error, .. } => Some(error),
+            SyncedRigError::GetCaptureError { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Represents errors getting captures from a `ResilientDevice`.
+#[derive(Debug)]
+pub enum ResilientDeviceError {
+    /// No capture arrived before the timeout elapsed.
+    TimeoutError { timeout_millis: i32 },
+    /// The device is lost, and its next reconnect attempt wasn't due before the timeout elapsed.
+    DisconnectedError { attempts: u32 },
+    /// Reopening or restarting the lost device failed.
+    ReconnectError { attempts: u32, error: Box<Error> },
+    /// The device is lost, and the backoff's maximum number of reconnect attempts failed.
+    GaveUpError { attempts: u32 },
+}
+
+impl fmt::Display for ResilientDeviceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResilientDeviceError::TimeoutError { timeout_millis } =>
+                write!(f, "ResilientDeviceError::TimeoutError (timeout of {} millis elapsed)", timeout_millis),
+            ResilientDeviceError::DisconnectedError { attempts } =>
+                write!(f, "ResilientDeviceError::DisconnectedError (after {} reconnect attempts)", attempts),
+            ResilientDeviceError::ReconnectError { attempts, error } =>
+                write!(f, "ResilientDeviceError::ReconnectError (attempt {}: {})", attempts, error),
+            ResilientDeviceError::GaveUpError { attempts } =>
+                write!(f, "ResilientDeviceError::GaveUpError (after {} reconnect attempts)", attempts),
+        }
+    }
+}
+
+impl StdError for ResilientDeviceError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            ResilientDeviceError::ReconnectError { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Represents errors creating a `StreamStats`.
+#[derive(Copy, Clone, Debug)]
+pub enum StreamStatsError {
+    /// A parameter must be positive.
+    InvalidParameterError { name: &'static str, value: f64 },
+}
+
+impl fmt::Display for StreamStatsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamStatsError::InvalidParameterError { name, value } =>
+                write!(f, "StreamStatsError::InvalidParameterError ({} must be positive, got {})", name, value),
+        }
+    }
+}
+
+impl StdError for StreamStatsError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+}
+
+/// Represents errors from the `k4a_transformation_*` functions.
+#[derive(Copy, Clone, Debug)]
+pub enum TransformationError {
+    /// The output image could not be created.
+    CreateImageError(CreateImageError),
+    /// The transformation failed (eg. the input images have the wrong format or size).
+    FailedError,
+    /// Unexpected error code returned by libk4a.
+    UnexpectedError(i32),
+}
+
+impl TransformationError {
+    pub(crate) fn check(result: k4a_sys::k4a_result_t) -> Result<(), TransformationError> {
+        match result {
+            k4a_sys::k4a_result_t_K4A_RESULT_SUCCEEDED => Ok(()),
+            k4a_sys::k4a_result_t_K4A_RESULT_FAILED => Err(TransformationError::FailedError),
+            // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
+            // Linux uses u32 and Windows uses i32.
+            // This should be fixed in the `k4a-sys` build script.
+            _ => Err(TransformationError::UnexpectedError(result as i32)),
+        }
+    }
+}
+
+impl fmt::Display for TransformationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransformationError::CreateImageError(error) =>
+                write!(f, "TransformationError::CreateImageError ({})", error),
+            TransformationError::FailedError =>
+                write!(f, "TransformationError::FailedError"),
+            TransformationError::UnexpectedError(code) =>
+                write!(f, "TransformationError::UnexpectedError (code: {})", code),
+        }
+    }
+}
+
+impl StdError for TransformationError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            TransformationError::CreateImageError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Represents errors reading or writing point cloud files.
+#[derive(Debug)]
+pub enum PointCloudIoError {
+    /// The underlying reader or writer failed.
+    IoError(io::Error),
+    /// The file is malformed.
+    ParseError(String),
+    /// The file is valid but uses a feature that isn't supported (eg. big endian PLY data).
+    UnsupportedError(String),
+}
+
+impl From<io::Error> for PointCloudIoError {
+    fn from(error: io::Error) -> Self {
+        PointCloudIoError::IoError(error)
+    }
+}
+
+impl fmt::Display for PointCloudIoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointCloudIoError::IoError(error) =>
+                write!(f, "PointCloudIoError::IoError ({})", error),
+            PointCloudIoError::ParseError(message) =>
+                write!(f, "PointCloudIoError::ParseError ({})", message),
+            PointCloudIoError::UnsupportedError(message) =>
+                write!(f, "PointCloudIoError::UnsupportedError ({})", message),
+        }
+    }
+}
+
+impl StdError for PointCloudIoError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            PointCloudIoError::IoError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Represents errors filtering point clouds.
+#[derive(Copy, Clone, Debug)]
+pub enum PointCloudProcessingError {
+    /// A filter parameter must be positive.
+    InvalidParameterError { name: &'static str, value: f32 },
+}
+
+impl fmt::Display for PointCloudProcessingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointCloudProcessingError::InvalidParameterError { name, value } =>
+                write!(f, "PointCloudProcessingError::InvalidParameterError ({} must be positive, got {})", name, value),
+        }
+    }
+}
+
+impl StdError for PointCloudProcessingError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+}
+
+/// Represents errors filtering depth images.
+#[derive(Copy, Clone, Debug)]
+pub enum DepthFilterError {
+    /// Only `Depth16` images can be filtered.
+    UnsupportedImageFormatError(ImageFormat),
+    /// Failed to create the filtered image.
+    CreateImageError(CreateImageError),
+    /// A filter parameter must be positive.
+    InvalidParameterError { name: &'static str, value: f32 },
+}
+
+impl fmt::Display for DepthFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DepthFilterError::UnsupportedImageFormatError(format) =>
+                write!(f, "DepthFilterError::UnsupportedImageFormatError ({:?})", format),
+            DepthFilterError::CreateImageError(error) =>
+                write!(f, "DepthFilterError::CreateImageError ({})", error),
+            DepthFilterError::InvalidParameterError { name, value } =>
+                write!(f, "DepthFilterError::InvalidParameterError ({} must be positive, got {})", name, value),
+        }
+    }
+}
+
+impl StdError for DepthFilterError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            DepthFilterError::CreateImageError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Represents errors making viewable images.
+#[derive(Copy, Clone, Debug)]
+pub enum VisualizationError {
+    /// The image has the wrong format (eg. an IR image passed as depth).
+    UnsupportedImageFormatError(ImageFormat),
+    /// Failed to create the output image.
+    CreateImageError(CreateImageError),
+    /// The output image passed doesn't have the format and size of the output.
+    OutputImageMismatchError { format: ImageFormat, width: usize, height: usize },
+}
+
+impl fmt::Display for VisualizationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VisualizationError::UnsupportedImageFormatError(format) =>
+                write!(f, "VisualizationError::UnsupportedImageFormatError ({:?})", format),
+            VisualizationError::CreateImageError(error) =>
+                write!(f, "VisualizationError::CreateImageError ({})", error),
+            VisualizationError::OutputImageMismatchError { format, width, height } =>
+                write!(f, "VisualizationError::OutputImageMismatchError (expected {:?} {}x{})", format, width, height),
+        }
+    }
+}
+
+impl StdError for VisualizationError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            VisualizationError::CreateImageError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Represents errors creating body trackers with `k4abt_tracker_create`.
+#[cfg(feature = "body-tracking")]
+#[derive(Copy, Clone, Debug)]
+pub enum BodyTrackerCreateError {
+    /// The model path contains a nul byte.
+    InvalidModelPathError,
+    /// Failed to create the tracker (eg. the depth mode is off or the GPU is unavailable).
+    FailedError,
+    /// Unexpected error code returned by libk4abt.
+    UnexpectedError(i32),
+}
+
+#[cfg(feature = "body-tracking")]
+impl fmt::Display for BodyTrackerCreateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BodyTrackerCreateError::InvalidModelPathError =>
+                write!(f, "BodyTrackerCreateError::InvalidModelPathError"),
+            BodyTrackerCreateError::FailedError =>
+                write!(f, "BodyTrackerCreateError::FailedError"),
+            BodyTrackerCreateError::UnexpectedError(code) =>
+                write!(f, "BodyTrackerCreateError::UnexpectedError (code: {})", code),
+        }
+    }
+}
+
+#[cfg(feature = "body-tracking")]
+impl StdError for BodyTrackerCreateError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+}
+
+/// Represents errors with `k4abt_tracker_enqueue_capture` and `k4abt_tracker_pop_result`.
+#[cfg(feature = "body-tracking")]
+#[derive(Copy, Clone, Debug)]
+pub enum BodyTrackerQueueError {
+    /// The queue stayed full (or empty) and our timeout elapsed.
+    /// Error contains the original value of our timeout threshold (not the time elapsed).
+    TimeoutError { timeout_millis: i32 },
+    /// The tracker failed, or was shut down.
+    FailedError,
+    /// Unexpected error code returned by libk4abt.
+    UnexpectedError(i32),
+}
+
+#[cfg(feature = "body-tracking")]
+impl BodyTrackerQueueError {
+    pub(crate) fn check(result: k4a_sys::k4a_wait_result_t, timeout_ms: i32) -> Result<(), BodyTrackerQueueError> {
+        match result {
+            k4a_sys::k4a_wait_result_t_K4A_WAIT_RESULT_SUCCEEDED => Ok(()),
+            k4a_sys::k4a_wait_result_t_K4A_WAIT_RESULT_TIMEOUT =>
+                Err(BodyTrackerQueueError::TimeoutError { timeout_millis: timeout_ms }),
+            k4a_sys::k4a_wait_result_t_K4A_WAIT_RESULT_FAILED => Err(BodyTrackerQueueError::FailedError),
+            // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
+            // Linux uses u32 and Windows uses i32.
+            // This should be fixed in the `k4a-sys` build script.
+            _ => Err(BodyTrackerQueueError::UnexpectedError(result as i32)),
+        }
+    }
+}
+
+#[cfg(feature = "body-tracking")]
+impl fmt::Display for BodyTrackerQueueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BodyTrackerQueueError::TimeoutError { timeout_millis } =>
+                write!(f, "BodyTrackerQueueError::TimeoutError (timeout of {} millis elapsed)", timeout_millis),
+            BodyTrackerQueueError::FailedError =>
+                write!(f, "BodyTrackerQueueError::FailedError"),
+            BodyTrackerQueueError::UnexpectedError(code) =>
+                write!(f, "BodyTrackerQueueError::UnexpectedError (code: {})", code),
+        }
+    }
+}
+
+#[cfg(feature = "body-tracking")]
+impl StdError for BodyTrackerQueueError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+}
+
+/// Represents errors exporting skeleton motion.
+#[derive(Debug)]
+pub enum MotionExportError {
+    /// The underlying writer failed.
+    IoError(io::Error),
+    /// There are no samples to export.
+    NoFramesError,
+    /// The samples aren't all of the same body.
+    MixedBodiesError { expected: u32, found: u32 },
+}
+
+impl From<io::Error> for MotionExportError {
+    fn from(error: io::Error) -> Self {
+        MotionExportError::IoError(error)
+    }
+}
+
+impl fmt::Display for MotionExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MotionExportError::IoError(error) =>
+                write!(f, "MotionExportError::IoError ({})", error),
+            MotionExportError::NoFramesError =>
+                write!(f, "MotionExportError::NoFramesError"),
+            MotionExportError::MixedBodiesError { expected, found } =>
+                write!(f, "MotionExportError::MixedBodiesError (expected body {}, found body {})", expected, found),
+        }
+    }
+}
+
+impl StdError for MotionExportError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            MotionExportError::IoError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Represents errors setting the debug message handler with `k4a_set_debug_message_handler`.
+#[derive(Copy, Clone, Debug)]
+pub enum SetDebugMessageHandlerError {
+    /// Failed to set the debug message handler.
+    FailedError,
+    /// Unexpected error code returned by libk4a.
+    UnexpectedError(i32),
+}
+
+impl fmt::Display for SetDebugMessageHandlerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetDebugMessageHandlerError::FailedError =>
+                write!(f, "SetDebugMessageHandlerError::FailedError"),
+            SetDebugMessageHandlerError::UnexpectedError(code) =>
+                write!(f, "SetDebugMessageHandlerError::UnexpectedError (code: {})", code),
+        }
+    }
+}
+
+impl StdError for SetDebugMessageHandlerError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+}
+
+/// Represents errors setting the allocator with `k4a_set_allocator`.
+#[derive(Copy, Clone, Debug)]
+pub enum SetAllocatorError {
+    /// Failed to set the allocator.
+    FailedError,
+    /// Unexpected error code returned by libk4a.
+    UnexpectedError(i32),
+}
+
+impl fmt::Display for SetAllocatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetAllocatorError::FailedError =>
+                write!(f, "SetAllocatorError::FailedError"),
+            SetAllocatorError::UnexpectedError(code) =>
+                write!(f, "SetAllocatorError::UnexpectedError (code: {})", code),
+        }
+    }
+}
+
+impl StdError for SetAllocatorError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+}
+
+/// Represents errors acquiring images from an `ImagePool`.
+#[derive(Copy, Clone, Debug)]
+pub enum ImagePoolError {
+    /// The format has no fixed size (MJPG), or no fixed stride (`Custom`) and no stride was given.
+    UnsupportedImageFormatError(ImageFormat),
+    /// Failed to allocate the buffer or create the image.
+    CreateImageError(CreateImageError),
+}
+
+impl fmt::Display for ImagePoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImagePoolError::UnsupportedImageFormatError(format) =>
+                write!(f, "ImagePoolError::UnsupportedImageFormatError ({:?})", format),
+            ImagePoolError::CreateImageError(error) =>
+                write!(f, "ImagePoolError::CreateImageError ({})", error),
+        }
+    }
+}
+
+impl StdError for ImagePoolError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            ImagePoolError::CreateImageError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Represents errors converting images to and from other crates' image types.
+#[derive(Copy, Clone, Debug)]
+pub enum ImageConversionError {
+    /// The image's format has no counterpart in the target type.
+    UnsupportedImageFormatError(ImageFormat),
+    /// Failed to create the converted image.
+    CreateImageError(CreateImageError),
+    /// The image's buffer or stride isn't aligned for a view of its samples.
+    UnalignedDataError,
+}
+
+impl fmt::Display for ImageConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageConversionError::UnsupportedImageFormatError(format) =>
+                write!(f, "ImageConversionError::UnsupportedImageFormatError ({:?})", format),
+            ImageConversionError::CreateImageError(error) =>
+                write!(f, "ImageConversionError::CreateImageError ({})", error),
+            ImageConversionError::UnalignedDataError =>
+                write!(f, "ImageConversionError::UnalignedDataError"),
+        }
+    }
+}
+
+impl StdError for ImageConversionError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            ImageConversionError::CreateImageError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Represents errors with creating and writing recordings with `k4a_record_*`.
+#[cfg(feature = "record")]
+#[derive(Copy, Clone, Debug)]
+pub enum RecordError {
+    /// The path isn't valid UTF-8 or contains a nul byte.
+    InvalidPathError,
+    /// Failed to create or write the recording (eg. the file can't be created, or the header wasn't written first).
+    FailedError,
+    /// Unexpected error code returned by libk4arecord.
+    UnexpectedError(i32),
+}
+
+#[cfg(feature = "record")]
+impl RecordError {
+    pub(crate) fn check(result: k4a_sys::k4a_result_t) -> Result<(), RecordError> {
+        match result {
+            k4a_sys::k4a_result_t_K4A_RESULT_SUCCEEDED => Ok(()),
+            k4a_sys::k4a_result_t_K4A_RESULT_FAILED => Err(RecordError::FailedError),
+            // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
+            // Linux uses u32 and Windows uses i32.
+            // This should be fixed in the `k4a-sys` build script.
+            _ => Err(RecordError::UnexpectedError(result as i32)),
+        }
+    }
+}
+
+#[cfg(feature = "record")]
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::InvalidPathError =>
+                write!(f, "RecordError::InvalidPathError"),
+            RecordError::FailedError =>
+                write!(f, "RecordError::FailedError"),
+            RecordError::UnexpectedError(code) =>
+                write!(f, "RecordError::UnexpectedError (code: {})", code),
+        }
+    }
+}
+
+#[cfg(feature = "record")]
+impl StdError for RecordError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+}
+
+/// Represents errors with reading recordings with `k4a_playback_*`.
+#[cfg(feature = "record")]
+#[derive(Copy, Clone, Debug)]
+pub enum PlaybackError {
+    /// The path isn't valid UTF-8 or contains a nul byte.
+    InvalidPathError,
+    /// Failed to open or read the recording (eg. the file is missing or isn't a recording).
+    FailedError,
+    /// Unexpected error code returned by libk4arecord.
+    UnexpectedError(i32),
+}
+
+#[cfg(feature = "record")]
+impl PlaybackError {
+    pub(crate) fn check(result: k4a_sys::k4a_result_t) -> Result<(), PlaybackError> {
+        match result {
+            k4a_sys::k4a_result_t_K4A_RESULT_SUCCEEDED => Ok(()),
+            k4a_sys::k4a_result_t_K4A_RESULT_FAILED => Err(PlaybackError::FailedError),
+            // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
+            // Linux uses u32 and Windows uses i32.
+            // This should be fixed in the `k4a-sys` build script.
+            _ => Err(PlaybackError::UnexpectedError(result as i32)),
+        }
+    }
+}
+
+#[cfg(feature = "record")]
+impl fmt::Display for PlaybackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlaybackError::InvalidPathError =>
+                write!(f, "PlaybackError::InvalidPathError"),
+            PlaybackError::FailedError =>
+                write!(f, "PlaybackError::FailedError"),
+            PlaybackError::UnexpectedError(code) =>
+                write!(f, "PlaybackError::UnexpectedError (code: {})", code),
+        }
+    }
+}
+
+#[cfg(feature = "record")]
+impl StdError for PlaybackError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
         None
     }
 }
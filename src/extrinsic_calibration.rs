@@ -0,0 +1,795 @@
+use crate::Calibration;
+use crate::CalibrationType;
+use crate::Checkerboard;
+use crate::Extrinsics;
+use crate::Image;
+use crate::error::ExtrinsicCalibrationError;
+use crate::math::{self, Mat3, Vec3};
+
+use k4a_sys_temp as k4a_sys;
+
+/// Levenberg-Marquardt iterations used when refining poses.
+const REFINEMENT_ITERATIONS: usize = 30;
+
+/// A camera's intrinsics and lens distortion, using the Azure Kinect's distortion models.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct CameraModel {
+    fx: f64,
+    fy: f64,
+    cx: f64,
+    cy: f64,
+    k: [f64; 6],
+    p1: f64,
+    p2: f64,
+    codx: f64,
+    cody: f64,
+    metric_radius: f64,
+    rational_6kt: bool,
+}
+
+impl CameraModel {
+    pub fn from_calibration(calibration: &Calibration, camera: CalibrationType) -> Option<Self> {
+        let camera_calibration = calibration.get_camera_calibration(camera)?;
+        // NB: This is a union field, so we have to use unsafe access
+        let p = unsafe { camera_calibration.intrinsics.parameters.param };
+
+        Some(Self {
+            fx: p.fx as f64,
+            fy: p.fy as f64,
+            cx: p.cx as f64,
+            cy: p.cy as f64,
+            k: [p.k1 as f64, p.k2 as f64, p.k3 as f64, p.k4 as f64, p.k5 as f64, p.k6 as f64],
+            p1: p.p1 as f64,
+            p2: p.p2 as f64,
+            codx: p.codx as f64,
+            cody: p.cody as f64,
+            metric_radius: p.metric_radius as f64,
+            rational_6kt: camera_calibration.intrinsics.type_
+                == k4a_sys::k4a_calibration_model_type_t_K4A_CALIBRATION_LENS_DISTORTION_MODEL_RATIONAL_6KT,
+        })
+    }
+
+    /// Apply lens distortion to a point on the normalized (z = 1) image plane.
+    fn distort(&self, point: [f64; 2]) -> Option<[f64; 2]> {
+        let xp = point[0] - self.codx;
+        let yp = point[1] - self.cody;
+        let (xp2, yp2, xyp) = (xp * xp, yp * yp, xp * yp);
+        let rs = xp2 + yp2;
+
+        if self.metric_radius > 0.0 && rs > self.metric_radius * self.metric_radius {
+            return None;
+        }
+
+        let (rss, rsc) = (rs * rs, rs * rs * rs);
+        let a = 1.0 + self.k[0] * rs + self.k[1] * rss + self.k[2] * rsc;
+        let b = 1.0 + self.k[3] * rs + self.k[4] * rss + self.k[5] * rsc;
+        let d = if b != 0.0 { a / b } else { a };
+
+        // The rational model and Brown-Conrady differ by a factor of two in the tangential terms.
+        let tangential = if self.rational_6kt { 1.0 } else { 2.0 };
+        let x = xp * d + (rs + 2.0 * xp2) * self.p2 + tangential * xyp * self.p1 + self.codx;
+        let y = yp * d + (rs + 2.0 * yp2) * self.p1 + tangential * xyp * self.p2 + self.cody;
+
+        Some([x, y])
+    }
+
+    /// Project a point in camera coordinates (millimeters) to pixels.
+    pub fn project(&self, point: Vec3) -> Option<[f64; 2]> {
+        if point[2] <= 0.0 {
+            return None;
+        }
+        let [x, y] = self.distort([point[0] / point[2], point[1] / point[2]])?;
+        Some([x * self.fx + self.cx, y * self.fy + self.cy])
+    }
+
+    /// Map a pixel to the normalized (z = 1) image plane, removing lens distortion.
+    pub fn unproject(&self, pixel: [f64; 2]) -> Option<[f64; 2]> {
+        let target = [(pixel[0] - self.cx) / self.fx, (pixel[1] - self.cy) / self.fy];
+        let mut point = target;
+
+        // Invert the distortion with Newton's method and a numerical Jacobian.
+        for _ in 0..20 {
+            let distorted = self.distort(point)?;
+            let error = [distorted[0] - target[0], distorted[1] - target[1]];
+            if error[0].abs() < 1e-12 && error[1].abs() < 1e-12 {
+                break;
+            }
+
+            let h = 1e-7;
+            let dx = self.distort([point[0] + h, point[1]])?;
+            let dy = self.distort([point[0], point[1] + h])?;
+            let j = [
+                [(dx[0] - distorted[0]) / h, (dy[0] - distorted[0]) / h],
+                [(dx[1] - distorted[1]) / h, (dy[1] - distorted[1]) / h],
+            ];
+            let det = j[0][0] * j[1][1] - j[0][1] * j[1][0];
+            if det.abs() < 1e-15 {
+                return None;
+            }
+            point[0] -= (j[1][1] * error[0] - j[0][1] * error[1]) / det;
+            point[1] -= (j[0][0] * error[1] - j[1][0] * error[0]) / det;
+        }
+
+        Some(point)
+    }
+}
+
+/// A rigid transformation in double precision, `target = rotation * source + translation`.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Pose {
+    pub rotation: Mat3,
+    pub translation: Vec3,
+}
+
+impl Pose {
+    pub fn identity() -> Self {
+        Self { rotation: math::IDENTITY, translation: [0.0; 3] }
+    }
+
+    pub fn apply(&self, point: Vec3) -> Vec3 {
+        math::add(math::mat_vec(&self.rotation, point), self.translation)
+    }
+
+    pub fn inverse(&self) -> Self {
+        let rotation = math::transpose(&self.rotation);
+        let translation = math::scale(math::mat_vec(&rotation, self.translation), -1.0);
+        Self { rotation, translation }
+    }
+
+    /// The pose that applies `other` first, then `self`.
+    pub fn compose(&self, other: &Pose) -> Self {
+        Self {
+            rotation: math::mat_mul(&self.rotation, &other.rotation),
+            translation: self.apply(other.translation),
+        }
+    }
+}
+
+impl From<Pose> for Extrinsics {
+    fn from(pose: Pose) -> Self {
+        let r = pose.rotation;
+        Self {
+            rotation: [
+                r[0][0] as f32, r[0][1] as f32, r[0][2] as f32,
+                r[1][0] as f32, r[1][1] as f32, r[1][2] as f32,
+                r[2][0] as f32, r[2][1] as f32, r[2][2] as f32,
+            ],
+            translation: [pose.translation[0] as f32, pose.translation[1] as f32, pose.translation[2] as f32],
+        }
+    }
+}
+
+impl From<Extrinsics> for Pose {
+    fn from(extrinsics: Extrinsics) -> Self {
+        let r = extrinsics.rotation;
+        let t = extrinsics.translation;
+        Self {
+            rotation: [
+                [r[0] as f64, r[1] as f64, r[2] as f64],
+                [r[3] as f64, r[4] as f64, r[5] as f64],
+                [r[6] as f64, r[7] as f64, r[8] as f64],
+            ],
+            translation: [t[0] as f64, t[1] as f64, t[2] as f64],
+        }
+    }
+}
+
+/// A calibration target seen by one camera.
+#[derive(Debug, Clone)]
+pub struct TargetObservation {
+    /// Detected target corners in pixels, in the order of `Checkerboard::get_corner_positions`.
+    pub corners: Vec<[f32; 2]>,
+    /// Transformation from the target to the camera coordinate system.
+    pub target_to_camera: Extrinsics,
+    /// RMS reprojection error of the target corners, in pixels.
+    pub reprojection_error_px: f32,
+}
+
+/// The result of calibrating one camera of a `MultiCameraCalibrator`.
+#[derive(Debug, Clone)]
+pub struct CameraExtrinsics {
+    /// Transformation from this camera's coordinate system to the reference (first) camera's.
+    pub camera_to_reference: Extrinsics,
+    /// RMS reprojection error in this camera of target corners seen by the reference camera, in
+    /// pixels. For the reference camera itself, this is the mean error of its target poses.
+    pub reprojection_error_px: f32,
+    /// The number of views of the target shared with the reference camera.
+    pub view_count: usize,
+}
+
+/// Detect the target in an image and solve for its pose relative to the camera (perspective-n-point).
+///
+/// `camera` selects the color or depth camera intrinsics of `calibration`. Use the depth camera
+/// for IR images.
+pub fn observe_target(image: &Image,
+                      calibration: &Calibration,
+                      camera: CalibrationType,
+                      target: &Checkerboard)
+                      -> Result<TargetObservation, ExtrinsicCalibrationError>
+{
+    let model = CameraModel::from_calibration(calibration, camera)
+        .ok_or(ExtrinsicCalibrationError::UnsupportedCameraError(camera))?;
+    let corners = target.detect(image)?;
+    solve_target_pose(&model, target, corners)
+}
+
+pub(crate) fn solve_target_pose(model: &CameraModel,
+                                target: &Checkerboard,
+                                corners: Vec<[f32; 2]>)
+                                -> Result<TargetObservation, ExtrinsicCalibrationError>
+{
+    let object_points: Vec<Vec3> = target.get_corner_positions().iter()
+        .map(|p| [p[0] as f64, p[1] as f64, p[2] as f64])
+        .collect();
+    let image_points: Vec<[f64; 2]> = corners.iter()
+        .map(|c| [c[0] as f64, c[1] as f64])
+        .collect();
+
+    let normalized = image_points.iter()
+        .map(|pixel| model.unproject(*pixel))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(ExtrinsicCalibrationError::DegenerateTargetError)?;
+
+    let initial = planar_pose_from_homography(&object_points, &normalized)
+        .ok_or(ExtrinsicCalibrationError::DegenerateTargetError)?;
+    let (pose, error) = refine_pose(model, &object_points, &image_points, initial);
+
+    Ok(TargetObservation {
+        corners,
+        target_to_camera: pose.into(),
+        reprojection_error_px: error as f32,
+    })
+}
+
+/// Initial pose of a planar (z = 0) target from the homography between the target plane and the
+/// normalized image plane.
+fn planar_pose_from_homography(object_points: &[Vec3], normalized: &[[f64; 2]]) -> Option<Pose> {
+    if object_points.len() < 4 {
+        return None;
+    }
+
+    let plane: Vec<[f64; 2]> = object_points.iter().map(|p| [p[0], p[1]]).collect();
+    let (plane_points, plane_transform) = normalize_points(&plane)?;
+    let (image_points, image_transform) = normalize_points(normalized)?;
+
+    // Direct linear transform: each correspondence gives two rows of A h = 0.
+    let mut ata = vec![0.0; 81];
+    for (p, q) in plane_points.iter().zip(image_points.iter()) {
+        let rows = [
+            [p[0], p[1], 1.0, 0.0, 0.0, 0.0, -q[0] * p[0], -q[0] * p[1], -q[0]],
+            [0.0, 0.0, 0.0, p[0], p[1], 1.0, -q[1] * p[0], -q[1] * p[1], -q[1]],
+        ];
+        for row in rows.iter() {
+            for i in 0..9 {
+                for j in 0..9 {
+                    ata[i * 9 + j] += row[i] * row[j];
+                }
+            }
+        }
+    }
+
+    let (_, vectors) = math::symmetric_eigen(&ata, 9);
+    let h = &vectors[0];
+    let normalized_homography = [[h[0], h[1], h[2]], [h[3], h[4], h[5]], [h[6], h[7], h[8]]];
+
+    // NB: `h` has unit norm, so a near-zero determinant means the corners collapse onto a line
+    // in the image and no pose explains them.
+    if math::determinant(&normalized_homography).abs() <= 1e-6 {
+        return None;
+    }
+
+    // Undo the normalization: H = T_image^-1 * H_normalized * T_plane.
+    let homography = math::mat_mul(
+        &math::inverse(&image_transform)?,
+        &math::mat_mul(&normalized_homography, &plane_transform),
+    );
+
+    let column = |i: usize| [homography[0][i], homography[1][i], homography[2][i]];
+    let (h1, h2, h3) = (column(0), column(1), column(2));
+
+    let mut lambda = 2.0 / (math::norm(h1) + math::norm(h2));
+    if lambda * h3[2] < 0.0 {
+        lambda = -lambda; // The target must be in front of the camera.
+    }
+
+    let r1 = math::scale(h1, lambda);
+    let r2 = math::scale(h2, lambda);
+    let r3 = math::cross(r1, r2);
+    let rotation = math::orthonormalize(&math::transpose(&[r1, r2, r3]));
+
+    Some(Pose {
+        rotation,
+        translation: math::scale(h3, lambda),
+    })
+}
+
+/// Translate points to their centroid and scale them to an average distance of sqrt(2).
+fn normalize_points(points: &[[f64; 2]]) -> Option<(Vec<[f64; 2]>, Mat3)> {
+    let n = points.len() as f64;
+    let cx = points.iter().map(|p| p[0]).sum::<f64>() / n;
+    let cy = points.iter().map(|p| p[1]).sum::<f64>() / n;
+    let mean_distance = points.iter()
+        .map(|p| ((p[0] - cx).powi(2) + (p[1] - cy).powi(2)).sqrt())
+        .sum::<f64>() / n;
+
+    // Coincident points (up to rounding) can't be normalized.
+    if mean_distance <= 1e-9 {
+        return None;
+    }
+
+    let s = std::f64::consts::SQRT_2 / mean_distance;
+    let normalized = points.iter().map(|p| [(p[0] - cx) * s, (p[1] - cy) * s]).collect();
+    let transform = [[s, 0.0, -cx * s], [0.0, s, -cy * s], [0.0, 0.0, 1.0]];
+
+    Some((normalized, transform))
+}
+
+/// Refine a pose by minimizing the pixel reprojection error of `object_points` (Levenberg-Marquardt).
+/// Returns the refined pose and the RMS reprojection error in pixels.
+pub(crate) fn refine_pose(model: &CameraModel,
+                          object_points: &[Vec3],
+                          image_points: &[[f64; 2]],
+                          initial: Pose)
+                          -> (Pose, f64)
+{
+    let residuals = |pose: &Pose| -> Vec<f64> {
+        object_points.iter().zip(image_points.iter())
+            .flat_map(|(object, image)| {
+                match model.project(pose.apply(*object)) {
+                    Some(projected) => vec![projected[0] - image[0], projected[1] - image[1]],
+                    // Points that can't be projected are penalized heavily.
+                    None => vec![1e3, 1e3],
+                }
+            })
+            .collect()
+    };
+    let cost = |r: &[f64]| r.iter().map(|v| v * v).sum::<f64>();
+
+    // Perturbs the pose by a rotation (axis-angle) and a translation.
+    let perturb = |pose: &Pose, delta: &[f64]| Pose {
+        rotation: math::mat_mul(&math::rotation_from_axis_angle([delta[0], delta[1], delta[2]]), &pose.rotation),
+        translation: math::add(pose.translation, [delta[3], delta[4], delta[5]]),
+    };
+
+    let mut pose = initial;
+    let mut current = residuals(&pose);
+    let mut current_cost = cost(&current);
+    let mut damping = 1e-3;
+
+    for _ in 0..REFINEMENT_ITERATIONS {
+        // Numerical Jacobian of the residuals with respect to the six pose parameters.
+        let steps = [1e-6, 1e-6, 1e-6, 1e-4, 1e-4, 1e-4];
+        let jacobian: Vec<Vec<f64>> = (0..6)
+            .map(|k| {
+                let mut delta = [0.0; 6];
+                delta[k] = steps[k];
+                residuals(&perturb(&pose, &delta)).iter().zip(current.iter())
+                    .map(|(moved, base)| (moved - base) / steps[k])
+                    .collect()
+            })
+            .collect();
+
+        let mut jtj = vec![0.0; 36];
+        let mut jtr = vec![0.0; 6];
+        for a in 0..6 {
+            for b in 0..6 {
+                jtj[a * 6 + b] = jacobian[a].iter().zip(jacobian[b].iter()).map(|(x, y)| x * y).sum();
+            }
+            jtr[a] = -jacobian[a].iter().zip(current.iter()).map(|(x, y)| x * y).sum::<f64>();
+        }
+
+        let mut improved = false;
+        while damping < 1e10 {
+            let mut damped = jtj.clone();
+            for a in 0..6 {
+                damped[a * 6 + a] *= 1.0 + damping;
+            }
+            let delta = match math::solve(&damped, &jtr) {
+                Some(delta) => delta,
+                None => break,
+            };
+            let candidate = perturb(&pose, &delta);
+            let candidate_residuals = residuals(&candidate);
+            let candidate_cost = cost(&candidate_residuals);
+
+            if candidate_cost < current_cost {
+                let converged = current_cost - candidate_cost < 1e-12 * current_cost.max(1e-12);
+                pose = candidate;
+                current = candidate_residuals;
+                current_cost = candidate_cost;
+                damping = (damping * 0.1).max(1e-12);
+                improved = !converged;
+                break;
+            }
+            damping *= 10.0;
+        }
+
+        if !improved {
+            break;
+        }
+    }
+
+    pose.rotation = math::orthonormalize(&pose.rotation);
+    let error = (cost(&residuals(&pose)) / object_points.len().max(1) as f64).sqrt();
+    (pose, error)
+}
+
+/// Estimates the extrinsics between several cameras (eg. the color cameras of a `SyncedRig`) from
+/// simultaneous views of a checkerboard target.
+///
+/// The first camera is the reference. Each other camera's transformation to the reference is
+/// first estimated by averaging the relative target poses over every view both cameras share, then
+/// refined by minimizing the reprojection error of the target corners, as located by the reference
+/// camera, in the other camera.
+pub struct MultiCameraCalibrator {
+    target: Checkerboard,
+    cameras: Vec<CameraModel>,
+    views: Vec<Vec<Option<TargetObservation>>>,
+}
+
+impl MultiCameraCalibrator {
+    /// Create a calibrator for a target and a set of cameras, each given by its device calibration
+    /// and which of the device's cameras is used.
+    pub fn new(target: Checkerboard,
+               cameras: &[(&Calibration, CalibrationType)])
+               -> Result<Self, ExtrinsicCalibrationError>
+    {
+        let cameras = cameras.iter()
+            .map(|(calibration, camera)| {
+                CameraModel::from_calibration(calibration, *camera)
+                    .ok_or(ExtrinsicCalibrationError::UnsupportedCameraError(*camera))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            target,
+            cameras,
+            views: Vec::new(),
+        })
+    }
+
+    /// The number of views added so far.
+    pub fn view_count(&self) -> usize {
+        self.views.len()
+    }
+
+    /// Add one view: an image from each camera, taken at the same time, in camera order.
+    ///
+    /// Returns whether the target was found in each image. Images the target isn't found in are
+    /// skipped, but other failures (such as unsupported image formats) are returned as errors.
+    pub fn add_images(&mut self, images: &[&Image]) -> Result<Vec<bool>, ExtrinsicCalibrationError> {
+        if images.len() != self.cameras.len() {
+            return Err(ExtrinsicCalibrationError::ImageCountError {
+                expected: self.cameras.len(),
+                actual: images.len(),
+            });
+        }
+
+        let mut view = Vec::with_capacity(images.len());
+        for (image, model) in images.iter().zip(self.cameras.iter()) {
+            let observation = match self.target.detect(image) {
+                Ok(corners) => solve_target_pose(model, &self.target, corners).ok(),
+                Err(ExtrinsicCalibrationError::TargetNotFoundError) => None,
+                Err(error) => return Err(error),
+            };
+            view.push(observation);
+        }
+
+        let found = view.iter().map(|observation| observation.is_some()).collect();
+        self.views.push(view);
+        Ok(found)
+    }
+
+    /// Add one view from target corners detected elsewhere, one entry per camera in camera order.
+    pub fn add_corners(&mut self, corners: Vec<Option<Vec<[f32; 2]>>>) -> Result<(), ExtrinsicCalibrationError> {
+        if corners.len() != self.cameras.len() {
+            return Err(ExtrinsicCalibrationError::ImageCountError {
+                expected: self.cameras.len(),
+                actual: corners.len(),
+            });
+        }
+
+        let view = corners.into_iter().zip(self.cameras.iter())
+            .map(|(corners, model)| {
+                corners.filter(|corners| corners.len() == self.target.corner_count())
+                    .and_then(|corners| solve_target_pose(model, &self.target, corners).ok())
+            })
+            .collect();
+
+        self.views.push(view);
+        Ok(())
+    }
+
+    /// Solve for the transformation from each camera to the reference (first) camera.
+    pub fn solve(&self) -> Result<Vec<CameraExtrinsics>, ExtrinsicCalibrationError> {
+        let target_points: Vec<Vec3> = self.target.get_corner_positions().iter()
+            .map(|p| [p[0] as f64, p[1] as f64, p[2] as f64])
+            .collect();
+
+        let mut results = Vec::with_capacity(self.cameras.len());
+
+        for (camera_index, model) in self.cameras.iter().enumerate() {
+            // Views seen by both the reference camera and this camera.
+            let shared: Vec<(&TargetObservation, &TargetObservation)> = self.views.iter()
+                .filter_map(|view| match (&view[0], &view[camera_index]) {
+                    (Some(reference), Some(observation)) => Some((reference, observation)),
+                    _ => None,
+                })
+                .collect();
+
+            if shared.is_empty() {
+                return Err(ExtrinsicCalibrationError::NoSharedViewsError { camera_index });
+            }
+
+            if camera_index == 0 {
+                let error = shared.iter().map(|(reference, _)| reference.reprojection_error_px).sum::<f32>()
+                    / shared.len() as f32;
+                results.push(CameraExtrinsics {
+                    camera_to_reference: Pose::identity().into(),
+                    reprojection_error_px: error,
+                    view_count: shared.len(),
+                });
+                continue;
+            }
+
+            // Initial estimate: average the per-view reference-from-camera poses.
+            let relative_poses: Vec<Pose> = shared.iter()
+                .map(|(reference, observation)| {
+                    let reference_pose = Pose::from(reference.target_to_camera);
+                    let camera_pose = Pose::from(observation.target_to_camera);
+                    camera_pose.compose(&reference_pose.inverse())
+                })
+                .collect();
+            let initial = average_poses(&relative_poses);
+
+            // Refine the reference-to-camera pose against every shared corner observation.
+            let mut object_points = Vec::new();
+            let mut image_points = Vec::new();
+            for (reference, observation) in shared.iter() {
+                let reference_pose = Pose::from(reference.target_to_camera);
+                for (point, corner) in target_points.iter().zip(observation.corners.iter()) {
+                    object_points.push(reference_pose.apply(*point));
+                    image_points.push([corner[0] as f64, corner[1] as f64]);
+                }
+            }
+            let (reference_to_camera, error) = refine_pose(model, &object_points, &image_points, initial);
+
+            results.push(CameraExtrinsics {
+                camera_to_reference: reference_to_camera.inverse().into(),
+                reprojection_error_px: error as f32,
+                view_count: shared.len(),
+            });
+        }
+
+        Ok(results)
+    }
+}
+
+/// Average rigid transformations: quaternion average for rotation, mean for translation.
+fn average_poses(poses: &[Pose]) -> Pose {
+    let first = math::quaternion_from_rotation(&poses[0].rotation);
+    let mut quaternion = [0.0; 4];
+    let mut translation = [0.0; 3];
+
+    for pose in poses.iter() {
+        let mut q = math::quaternion_from_rotation(&pose.rotation);
+        // q and -q are the same rotation; keep them in the same hemisphere before summing.
+        if q.iter().zip(first.iter()).map(|(a, b)| a * b).sum::<f64>() < 0.0 {
+            q = [-q[0], -q[1], -q[2], -q[3]];
+        }
+        for k in 0..4 {
+            quaternion[k] += q[k];
+        }
+        translation = math::add(translation, pose.translation);
+    }
+
+    Pose {
+        rotation: math::rotation_from_quaternion(quaternion),
+        translation: math::scale(translation, 1.0 / poses.len() as f64),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::checkerboard::GrayImage;
+
+    /// Brightness of dark squares, and of light squares and everything around the board.
+    const DARK: f32 = 30.0;
+    const LIGHT: f32 = 220.0;
+
+    /// A distortion free 640x480 camera.
+    pub(crate) fn pinhole_camera() -> CameraModel {
+        CameraModel {
+            fx: 500.0,
+            fy: 500.0,
+            cx: 319.5,
+            cy: 239.5,
+            k: [0.0; 6],
+            p1: 0.0,
+            p2: 0.0,
+            codx: 0.0,
+            cody: 0.0,
+            metric_radius: 0.0,
+            rational_6kt: true,
+        }
+    }
+
+    pub(crate) fn pose(axis_angle: Vec3, translation: Vec3) -> Pose {
+        Pose { rotation: math::rotation_from_axis_angle(axis_angle), translation }
+    }
+
+    /// Render `target` as seen by a distortion free `camera` with 4x4 supersampling.
+    pub(crate) fn render_target(camera: &CameraModel,
+                                target: &Checkerboard,
+                                target_to_camera: &Pose,
+                                width: usize,
+                                height: usize) -> GrayImage
+    {
+        const SAMPLES: usize = 4;
+        let camera_to_target = target_to_camera.inverse();
+        let origin = camera_to_target.translation;
+        let square = target.square_size_mm as f64;
+
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = 0.0;
+                for sy in 0..SAMPLES {
+                    for sx in 0..SAMPLES {
+                        let u = x as f64 + (sx as f64 + 0.5) / SAMPLES as f64 - 0.5;
+                        let v = y as f64 + (sy as f64 + 0.5) / SAMPLES as f64 - 0.5;
+                        let ray = [(u - camera.cx) / camera.fx, (v - camera.cy) / camera.fy, 1.0];
+                        let direction = math::mat_vec(&camera_to_target.rotation, ray);
+                        let t = -origin[2] / direction[2];
+                        let point = math::add(origin, math::scale(direction, t));
+
+                        let i = (point[0] / square).floor() as i64;
+                        let j = (point[1] / square).floor() as i64;
+                        let on_board = t > 0.0
+                            && i >= -1 && i < target.columns as i64
+                            && j >= -1 && j < target.rows as i64;
+                        sum += if on_board && (i + j).rem_euclid(2) == 0 { DARK } else { LIGHT };
+                    }
+                }
+                data.push(sum / (SAMPLES * SAMPLES) as f32);
+            }
+        }
+
+        GrayImage::new(width, height, data)
+    }
+
+    /// Where `target`'s corners project to.
+    pub(crate) fn project_corners(camera: &CameraModel, target: &Checkerboard, target_to_camera: &Pose) -> Vec<[f64; 2]> {
+        target.get_corner_positions().iter()
+            .map(|p| camera.project(target_to_camera.apply([p[0] as f64, p[1] as f64, p[2] as f64])).unwrap())
+            .collect()
+    }
+
+    /// The angle between two rotations, in degrees.
+    fn rotation_error_degrees(a: &Mat3, b: &Mat3) -> f64 {
+        let relative = math::mat_mul(&math::transpose(a), b);
+        let trace = relative[0][0] + relative[1][1] + relative[2][2];
+        ((trace - 1.0) / 2.0).clamp(-1.0, 1.0).acos().to_degrees()
+    }
+
+    fn translation_error_mm(a: Vec3, b: Vec3) -> f64 {
+        math::norm([a[0] - b[0], a[1] - b[1], a[2] - b[2]])
+    }
+
+    #[test]
+    fn unproject_inverts_project_with_distortion() {
+        let camera = CameraModel {
+            k: [0.3, -0.05, 0.01, 0.6, -0.02, 0.005],
+            p1: 1e-4,
+            p2: -2e-4,
+            metric_radius: 1.7,
+            ..pinhole_camera()
+        };
+
+        for point in [[0.0, 0.0, 500.0], [120.0, -80.0, 600.0], [-300.0, 200.0, 900.0]].iter() {
+            let pixel = camera.project(*point).unwrap();
+            let normalized = camera.unproject(pixel).unwrap();
+            assert!((normalized[0] - point[0] / point[2]).abs() < 1e-9);
+            assert!((normalized[1] - point[1] / point[2]).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn project_rejects_points_behind_the_camera() {
+        assert!(pinhole_camera().project([0.0, 0.0, -100.0]).is_none());
+    }
+
+    #[test]
+    fn solve_target_pose_recovers_rendered_pose() {
+        let target = Checkerboard::new(7, 5, 30.0);
+        let camera = pinhole_camera();
+        let target_to_camera = pose([0.25, -0.2, 0.05], [-100.0, -50.0, 650.0]);
+
+        let image = render_target(&camera, &target, &target_to_camera, 640, 480);
+        let corners = target.detect_gray(&image).expect("the target is found");
+        let observation = solve_target_pose(&camera, &target, corners).unwrap();
+        let solved = Pose::from(observation.target_to_camera);
+
+        assert!(observation.reprojection_error_px < 0.1, "{}", observation.reprojection_error_px);
+        assert!(rotation_error_degrees(&solved.rotation, &target_to_camera.rotation) < 0.2);
+        assert!(translation_error_mm(solved.translation, target_to_camera.translation) < 2.0);
+    }
+
+    #[test]
+    fn solve_target_pose_rejects_degenerate_corners() {
+        let target = Checkerboard::new(3, 2, 30.0);
+
+        let coincident = vec![[100.0, 100.0]; target.corner_count()];
+        assert!(solve_target_pose(&pinhole_camera(), &target, coincident).is_err());
+
+        let collinear = (0..target.corner_count()).map(|i| [100.0 + 10.0 * i as f32, 50.0 + 5.0 * i as f32]).collect();
+        assert!(solve_target_pose(&pinhole_camera(), &target, collinear).is_err());
+    }
+
+    #[test]
+    fn multi_camera_solve_recovers_extrinsics() {
+        let target = Checkerboard::new(7, 5, 30.0);
+        let reference = pinhole_camera();
+        let other = CameraModel { fx: 520.0, fy: 515.0, cx: 322.0, cy: 236.0, ..pinhole_camera() };
+        let other_to_reference = pose([0.02, 0.15, -0.03], [160.0, 10.0, -20.0]);
+        let reference_to_other = other_to_reference.inverse();
+
+        let mut calibrator = MultiCameraCalibrator {
+            target,
+            cameras: vec![reference, other],
+            views: Vec::new(),
+        };
+
+        let views = [
+            pose([0.2, 0.1, 0.0], [-40.0, -60.0, 700.0]),
+            pose([-0.15, 0.3, 0.05], [-20.0, -40.0, 750.0]),
+            pose([0.05, -0.1, -0.08], [-70.0, -70.0, 650.0]),
+        ];
+        for target_to_reference in views.iter() {
+            let target_to_other = reference_to_other.compose(target_to_reference);
+            let corners = [(&reference, *target_to_reference), (&other, target_to_other)].iter()
+                .map(|(camera, target_to_camera)| {
+                    target.detect_gray(&render_target(camera, &target, target_to_camera, 640, 480))
+                })
+                .collect::<Vec<_>>();
+            assert!(corners.iter().all(Option::is_some));
+            calibrator.add_corners(corners).unwrap();
+        }
+
+        let results = calibrator.solve().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].view_count, views.len());
+        assert!(results[1].reprojection_error_px < 0.2, "{}", results[1].reprojection_error_px);
+
+        let solved = Pose::from(results[1].camera_to_reference);
+        assert!(rotation_error_degrees(&solved.rotation, &other_to_reference.rotation) < 0.2);
+        assert!(translation_error_mm(solved.translation, other_to_reference.translation) < 2.0);
+
+        let identity = Pose::from(results[0].camera_to_reference);
+        assert_eq!(identity.translation, [0.0; 3]);
+    }
+
+    #[test]
+    fn multi_camera_solve_needs_shared_views() {
+        let target = Checkerboard::new(7, 5, 30.0);
+        let camera = pinhole_camera();
+        let mut calibrator = MultiCameraCalibrator {
+            target,
+            cameras: vec![camera, camera],
+            views: Vec::new(),
+        };
+
+        let target_to_camera = pose([0.1, 0.1, 0.0], [-90.0, -60.0, 700.0]);
+        let corners = project_corners(&camera, &target, &target_to_camera).iter()
+            .map(|c| [c[0] as f32, c[1] as f32])
+            .collect();
+        calibrator.add_corners(vec![Some(corners), None]).unwrap();
+
+        match calibrator.solve() {
+            Err(ExtrinsicCalibrationError::NoSharedViewsError { camera_index: 1 }) => {},
+            result => panic!("unexpected {:?}", result.map(|r| r.len())),
+        }
+        assert!(calibrator.add_corners(vec![None]).is_err());
+    }
+}
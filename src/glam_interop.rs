@@ -0,0 +1,92 @@
+//! Conversions between calibration types and `glam`'s, with the `glam` feature.
+//!
+//! The calibration conversions (`Calibration::convert_2d_to_3d` and friends) also take and return
+//! `Vec2` and `Vec3`.
+
+use crate::calibration::{CalibrationPoint2, CalibrationPoint3};
+use crate::Extrinsics;
+use crate::Intrinsics;
+
+use ::glam::{Affine3A, Mat3, Vec2, Vec3};
+
+/// glam matrices are column major, and calibration matrices row major.
+fn transpose(matrix: &[f32; 9]) -> [f32; 9] {
+    [matrix[0], matrix[3], matrix[6],
+     matrix[1], matrix[4], matrix[7],
+     matrix[2], matrix[5], matrix[8]]
+}
+
+impl From<Extrinsics> for Affine3A {
+    fn from(extrinsics: Extrinsics) -> Self {
+        let rotation = Mat3::from_cols_array(&transpose(&extrinsics.rotation));
+        Affine3A::from_mat3_translation(rotation, Vec3::from_array(extrinsics.translation))
+    }
+}
+
+/// Keeps whatever linear part the affine transformation has, which is a rotation only if it was
+/// made from one.
+impl From<Affine3A> for Extrinsics {
+    fn from(affine: Affine3A) -> Self {
+        Self {
+            rotation: transpose(&affine.matrix3.to_cols_array()),
+            translation: affine.translation.to_array(),
+        }
+    }
+}
+
+/// The camera matrix. Distortion is left out.
+impl From<Intrinsics> for Mat3 {
+    fn from(intrinsics: Intrinsics) -> Self {
+        Mat3::from_cols_array(&transpose(&intrinsics.camera_matrix()))
+    }
+}
+
+impl CalibrationPoint2 for Vec2 {
+    type Point3 = Vec3;
+
+    fn to_array(self) -> [f32; 2] {
+        Vec2::to_array(&self)
+    }
+
+    fn from_array(array: [f32; 2]) -> Self {
+        Vec2::from_array(array)
+    }
+}
+
+impl CalibrationPoint3 for Vec3 {
+    type Point2 = Vec2;
+
+    fn to_array(self) -> [f32; 3] {
+        Vec3::to_array(&self)
+    }
+
+    fn from_array(array: [f32; 3]) -> Self {
+        Vec3::from_array(array)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calibration::tests::{assert_close, extrinsics, intrinsics, transform};
+
+    #[test]
+    fn affines_transform_like_extrinsics() {
+        let affine = Affine3A::from(extrinsics());
+        let point = affine.transform_point3(Vec3::new(1.0, 2.0, 3.0));
+        assert_close(point.to_array(), transform(&extrinsics(), [1.0, 2.0, 3.0]));
+    }
+
+    #[test]
+    fn extrinsics_round_trip_through_affines() {
+        assert_eq!(Extrinsics::from(Affine3A::from(extrinsics())), extrinsics());
+    }
+
+    #[test]
+    fn camera_matrices_have_fx_and_cx_in_the_first_row() {
+        let matrix = Mat3::from(intrinsics());
+        assert_eq!(matrix.row(0), Vec3::new(500.0, 0.0, 320.0));
+        assert_eq!(matrix.row(1), Vec3::new(0.0, 510.0, 240.0));
+        assert_eq!(matrix.row(2), Vec3::new(0.0, 0.0, 1.0));
+    }
+}
@@ -0,0 +1,254 @@
+use k4a_sys_temp as k4a_sys;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A single firmware version.
+///
+/// Versions compare semantically: by major version, then minor version, then iteration.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    /// Major version; represents a breaking change.
+    pub major: u32,
+    /// Minor version; represents additional features, no regression from lower versions with the
+    /// same major version.
+    pub minor: u32,
+    /// Reserved by libk4a.
+    pub iteration: u32,
+}
+
+impl Version {
+    pub fn new(major: u32, minor: u32, iteration: u32) -> Self {
+        Self { major, minor, iteration }
+    }
+}
+
+impl From<k4a_sys::k4a_version_t> for Version {
+    fn from(version: k4a_sys::k4a_version_t) -> Self {
+        Self {
+            major: version.major,
+            minor: version.minor,
+            iteration: version.iteration,
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.iteration)
+    }
+}
+
+/// Build type reported by the firmware.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FirmwareBuild {
+    /// Production firmware.
+    Release,
+    /// Pre-production firmware.
+    Debug,
+    /// Unknown build type reported by libk4a.
+    Unknown,
+}
+
+impl From<k4a_sys::k4a_firmware_build_t> for FirmwareBuild {
+    fn from(build: k4a_sys::k4a_firmware_build_t) -> Self {
+        match build {
+            k4a_sys::k4a_firmware_build_t_K4A_FIRMWARE_BUILD_RELEASE => FirmwareBuild::Release,
+            k4a_sys::k4a_firmware_build_t_K4A_FIRMWARE_BUILD_DEBUG => FirmwareBuild::Debug,
+            _ => FirmwareBuild::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for FirmwareBuild {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FirmwareBuild::Release => write!(f, "release"),
+            FirmwareBuild::Debug => write!(f, "debug"),
+            FirmwareBuild::Unknown => write!(f, "unknown build"),
+        }
+    }
+}
+
+/// Signature type of the firmware.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FirmwareSignature {
+    /// Microsoft signed firmware.
+    Microsoft,
+    /// Test signed firmware.
+    Test,
+    /// Unsigned firmware.
+    Unsigned,
+    /// Unknown signature type reported by libk4a.
+    Unknown,
+}
+
+impl From<k4a_sys::k4a_firmware_signature_t> for FirmwareSignature {
+    fn from(signature: k4a_sys::k4a_firmware_signature_t) -> Self {
+        match signature {
+            k4a_sys::k4a_firmware_signature_t_K4A_FIRMWARE_SIGNATURE_MSFT => FirmwareSignature::Microsoft,
+            k4a_sys::k4a_firmware_signature_t_K4A_FIRMWARE_SIGNATURE_TEST => FirmwareSignature::Test,
+            k4a_sys::k4a_firmware_signature_t_K4A_FIRMWARE_SIGNATURE_UNSIGNED => FirmwareSignature::Unsigned,
+            _ => FirmwareSignature::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for FirmwareSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FirmwareSignature::Microsoft => write!(f, "Microsoft signed"),
+            FirmwareSignature::Test => write!(f, "test signed"),
+            FirmwareSignature::Unsigned => write!(f, "unsigned"),
+            FirmwareSignature::Unknown => write!(f, "unknown signature"),
+        }
+    }
+}
+
+/// The firmware versions of each component of a device.
+///
+/// Hardware versions are only partially ordered: one version is greater than another if none of
+/// its component versions are lower, and versions with mixed component ordering are incomparable.
+/// Versions with a different firmware build or signature are always incomparable, since those are
+/// different firmware lines rather than older or newer releases. This makes `version >= minimum`
+/// a check that every component is at least the minimum, on the same kind of firmware; use
+/// `meets_minimum` to compare component versions only.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct HardwareVersion {
+    /// Color camera firmware version.
+    pub rgb: Version,
+    /// Depth camera firmware version.
+    pub depth: Version,
+    /// Audio device firmware version.
+    pub audio: Version,
+    /// Depth sensor firmware version.
+    pub depth_sensor: Version,
+    /// Build type reported by the firmware.
+    pub firmware_build: FirmwareBuild,
+    /// Signature type of the firmware.
+    pub firmware_signature: FirmwareSignature,
+}
+
+impl HardwareVersion {
+    /// Returns true if every component firmware version is at least that of `minimum`.
+    /// Unlike `>=`, this ignores the firmware build type and signature.
+    pub fn meets_minimum(&self, minimum: &HardwareVersion) -> bool {
+        self.rgb >= minimum.rgb
+            && self.depth >= minimum.depth
+            && self.audio >= minimum.audio
+            && self.depth_sensor >= minimum.depth_sensor
+    }
+
+    fn components(&self) -> [Version; 4] {
+        [self.rgb, self.depth, self.audio, self.depth_sensor]
+    }
+}
+
+impl PartialOrd for HardwareVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.firmware_build != other.firmware_build || self.firmware_signature != other.firmware_signature {
+            return None;
+        }
+
+        let mut ordering = Ordering::Equal;
+
+        for (ours, theirs) in self.components().iter().zip(other.components().iter()) {
+            match (ordering, ours.cmp(theirs)) {
+                (_, Ordering::Equal) => {},
+                (Ordering::Equal, component_ordering) => ordering = component_ordering,
+                (overall, component_ordering) if overall == component_ordering => {},
+                _ => return None,
+            }
+        }
+
+        Some(ordering)
+    }
+}
+
+impl From<k4a_sys::k4a_hardware_version_t> for HardwareVersion {
+    fn from(version: k4a_sys::k4a_hardware_version_t) -> Self {
+        Self {
+            rgb: version.rgb.into(),
+            depth: version.depth.into(),
+            audio: version.audio.into(),
+            depth_sensor: version.depth_sensor.into(),
+            firmware_build: version.firmware_build.into(),
+            firmware_signature: version.firmware_signature.into(),
+        }
+    }
+}
+
+impl fmt::Display for HardwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RGB {}, depth {}, audio {}, depth sensor {} ({}, {})",
+               self.rgb, self.depth, self.audio, self.depth_sensor,
+               self.firmware_build, self.firmware_signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hardware_version(rgb: u32, depth: u32, audio: u32, depth_sensor: u32) -> HardwareVersion {
+        HardwareVersion {
+            rgb: Version::new(1, rgb, 0),
+            depth: Version::new(1, depth, 0),
+            audio: Version::new(1, audio, 0),
+            depth_sensor: Version::new(1, depth_sensor, 0),
+            firmware_build: FirmwareBuild::Release,
+            firmware_signature: FirmwareSignature::Microsoft,
+        }
+    }
+
+    #[test]
+    fn versions_compare_semantically() {
+        assert!(Version::new(1, 6, 110) > Version::new(1, 6, 102));
+        assert!(Version::new(1, 10, 0) > Version::new(1, 9, 99));
+        assert!(Version::new(2, 0, 0) > Version::new(1, 99, 99));
+        assert_eq!(Version::new(1, 6, 110).to_string(), "1.6.110");
+    }
+
+    #[test]
+    fn hardware_versions_are_partially_ordered() {
+        let base = hardware_version(6, 6, 6, 6);
+
+        assert_eq!(base.partial_cmp(&base), Some(Ordering::Equal));
+        assert!(hardware_version(7, 6, 6, 6) > base);
+        assert!(hardware_version(7, 7, 7, 7) >= base);
+        assert!(hardware_version(6, 5, 6, 6) < base);
+
+        // Mixed component ordering.
+        let mixed = hardware_version(7, 5, 6, 6);
+        assert_eq!(mixed.partial_cmp(&base), None);
+        let (at_least, at_most) = (mixed >= base, mixed <= base);
+        assert!(!at_least && !at_most);
+
+        // A different build or signature is never comparable, whatever the components.
+        let debug = HardwareVersion { firmware_build: FirmwareBuild::Debug, ..hardware_version(7, 7, 7, 7) };
+        let test_signed = HardwareVersion { firmware_signature: FirmwareSignature::Test, ..base };
+        for other in [debug, test_signed].iter() {
+            assert_eq!(other.partial_cmp(&base), None);
+            assert_eq!(base.partial_cmp(other), None);
+            assert_ne!(*other, base);
+        }
+    }
+
+    #[test]
+    fn meets_minimum_checks_every_component() {
+        let minimum = hardware_version(6, 6, 6, 6);
+
+        assert!(minimum.meets_minimum(&minimum));
+        assert!(hardware_version(7, 6, 8, 6).meets_minimum(&minimum));
+        assert!(!hardware_version(7, 7, 7, 5).meets_minimum(&minimum));
+        assert!(!hardware_version(5, 7, 7, 7).meets_minimum(&minimum));
+
+        // Unlike `>=`, the build and signature don't matter.
+        let debug = HardwareVersion {
+            firmware_build: FirmwareBuild::Debug,
+            firmware_signature: FirmwareSignature::Unsigned,
+            ..hardware_version(7, 7, 7, 7)
+        };
+        let at_least = debug >= minimum;
+        assert!(debug.meets_minimum(&minimum) && !at_least);
+    }
+}
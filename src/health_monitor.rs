@@ -0,0 +1,322 @@
+use crate::error::{DeviceGetCaptureError, DeviceGetImuSampleError, DeviceGetSyncJackStatusError};
+use crate::Capture;
+use crate::Device;
+use crate::ImuSample;
+use crate::SynchronizationJackStatus;
+
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+/// Default number of consecutive capture timeouts that make a stall.
+const DEFAULT_STALL_TIMEOUTS: u32 = 3;
+
+/// Default time between temperature events of each sensor.
+const DEFAULT_TEMPERATURE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Default time between polls of the synchronization jacks.
+const DEFAULT_SYNC_JACK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where a temperature was read.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TemperatureSensor {
+    /// The device temperature reported with captures.
+    Capture,
+    /// The temperature reported with IMU samples.
+    Imu,
+}
+
+/// Something a `HealthMonitor` noticed about a device.
+#[derive(Debug, Clone)]
+pub enum HealthEvent {
+    /// A temperature reading, sent at most once per `temperature_interval` for each sensor.
+    Temperature { sensor: TemperatureSensor, temperature_c: f32 },
+    /// A temperature reading above `max_temperature_c`, sent for every such reading.
+    Overheating { sensor: TemperatureSensor, temperature_c: f32, max_temperature_c: f32 },
+    /// The synchronization jacks were (dis)connected. The first poll always sends this.
+    SyncJackStatusChanged(SynchronizationJackStatus),
+    /// The synchronization jacks could not be polled.
+    SyncJackStatusError(DeviceGetSyncJackStatusError),
+    /// `stall_timeouts` captures in a row timed out.
+    Stalled { consecutive_timeouts: u32 },
+    /// A capture arrived after a stall.
+    Resumed { consecutive_timeouts: u32 },
+    /// Getting a capture failed, which usually means the device was lost.
+    CaptureFailed(DeviceGetCaptureError),
+    /// Getting an IMU sample failed (timeouts aren't reported).
+    ImuSampleFailed(DeviceGetImuSampleError),
+}
+
+/// Watches a device that runs unattended, reporting its temperatures, synchronization jack
+/// changes, stalls and failures as `HealthEvent`s.
+///
+/// Read captures (and IMU samples) through the monitor with `get_capture` and `get_imu_sample`,
+/// or pass the results of reading them yourself to `observe_capture` and `observe_imu_sample`.
+/// `get_capture` also polls the synchronization jacks every `sync_jack_interval`; when reading
+/// captures yourself, call `poll_sync_jack` instead.
+///
+/// Events go to a handler, which runs on the thread that reads captures, or to a channel.
+pub struct HealthMonitor {
+    /// Number of consecutive capture timeouts that make a stall.
+    pub stall_timeouts: u32,
+    /// Temperature above which readings are reported as `Overheating`, if any.
+    pub max_temperature_c: Option<f32>,
+    /// Minimum time between `Temperature` events of each sensor.
+    pub temperature_interval: Duration,
+    /// Time between polls of the synchronization jacks by `get_capture`.
+    pub sync_jack_interval: Duration,
+    handler: Box<dyn FnMut(HealthEvent) + Send>,
+    consecutive_timeouts: u32,
+    sync_jack_status: Option<SynchronizationJackStatus>,
+    last_sync_jack_poll: Option<Instant>,
+    last_temperature: [Option<Instant>; 2],
+}
+
+impl HealthMonitor {
+    /// Create a monitor that passes events to `handler`.
+    pub fn new<F: FnMut(HealthEvent) + Send + 'static>(handler: F) -> Self {
+        Self {
+            stall_timeouts: DEFAULT_STALL_TIMEOUTS,
+            max_temperature_c: None,
+            temperature_interval: DEFAULT_TEMPERATURE_INTERVAL,
+            sync_jack_interval: DEFAULT_SYNC_JACK_INTERVAL,
+            handler: Box::new(handler),
+            consecutive_timeouts: 0,
+            sync_jack_status: None,
+            last_sync_jack_poll: None,
+            last_temperature: [None, None],
+        }
+    }
+
+    /// Create a monitor that sends events to a channel.
+    ///
+    /// Events sent after the receiver is dropped are discarded.
+    pub fn channel() -> (Self, Receiver<HealthEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        let monitor = Self::new(move |event| {
+            let _ = sender.send(event);
+        });
+        (monitor, receiver)
+    }
+
+    /// Whether the last `stall_timeouts` (or more) captures timed out.
+    pub fn is_stalled(&self) -> bool {
+        self.consecutive_timeouts >= self.stall_timeouts
+    }
+
+    /// Get a capture from `device`, reporting on it and polling the synchronization jacks if due.
+    pub fn get_capture(&mut self, device: &Device, timeout_ms: i32) -> Result<Capture, DeviceGetCaptureError> {
+        let result = device.get_capture(timeout_ms);
+        self.observe_capture(&result);
+
+        let now = Instant::now();
+        let poll_due = self.last_sync_jack_poll
+            .map(|last| now.duration_since(last) >= self.sync_jack_interval)
+            .unwrap_or(true);
+        if poll_due {
+            self.poll_sync_jack(device);
+        }
+
+        result
+    }
+
+    /// Get an IMU sample from `device`, reporting its temperature.
+    pub fn get_imu_sample(&mut self, device: &Device, timeout_ms: i32) -> Result<ImuSample, DeviceGetImuSampleError> {
+        let result = device.get_imu_sample(timeout_ms);
+        self.observe_imu_sample(&result);
+        result
+    }
+
+    /// Report on the result of getting a capture.
+    pub fn observe_capture(&mut self, result: &Result<Capture, DeviceGetCaptureError>) {
+        match result {
+            Ok(capture) => {
+                if self.is_stalled() {
+                    let consecutive_timeouts = self.consecutive_timeouts;
+                    self.emit(HealthEvent::Resumed { consecutive_timeouts });
+                }
+                self.consecutive_timeouts = 0;
+
+                if let Some(temperature_c) = capture.get_temperature_c() {
+                    self.observe_temperature(TemperatureSensor::Capture, temperature_c);
+                }
+            },
+            Err(DeviceGetCaptureError::TimeoutError { .. }) => {
+                self.consecutive_timeouts += 1;
+                if self.consecutive_timeouts == self.stall_timeouts {
+                    let consecutive_timeouts = self.consecutive_timeouts;
+                    self.emit(HealthEvent::Stalled { consecutive_timeouts });
+                }
+            },
+            Err(error) => self.emit(HealthEvent::CaptureFailed(*error)),
+        }
+    }
+
+    /// Report on the result of getting an IMU sample.
+    pub fn observe_imu_sample(&mut self, result: &Result<ImuSample, DeviceGetImuSampleError>) {
+        match result {
+            Ok(sample) => self.observe_temperature(TemperatureSensor::Imu, sample.temperature_c),
+            Err(DeviceGetImuSampleError::TimeoutError { .. }) => {},
+            Err(error) => self.emit(HealthEvent::ImuSampleFailed(*error)),
+        }
+    }
+
+    /// Poll the synchronization jacks of `device`, reporting if they changed.
+    pub fn poll_sync_jack(&mut self, device: &Device) {
+        self.last_sync_jack_poll = Some(Instant::now());
+
+        match device.get_synchronization_jack_status() {
+            Ok(status) => {
+                if self.sync_jack_status != Some(status) {
+                    self.sync_jack_status = Some(status);
+                    self.emit(HealthEvent::SyncJackStatusChanged(status));
+                }
+            },
+            Err(error) => self.emit(HealthEvent::SyncJackStatusError(error)),
+        }
+    }
+
+    fn observe_temperature(&mut self, sensor: TemperatureSensor, temperature_c: f32) {
+        let now = Instant::now();
+        let due = self.last_temperature[sensor as usize]
+            .map(|last| now.duration_since(last) >= self.temperature_interval)
+            .unwrap_or(true);
+        if due {
+            self.last_temperature[sensor as usize] = Some(now);
+            self.emit(HealthEvent::Temperature { sensor, temperature_c });
+        }
+
+        if let Some(max_temperature_c) = self.max_temperature_c {
+            if temperature_c > max_temperature_c {
+                self.emit(HealthEvent::Overheating { sensor, temperature_c, max_temperature_c });
+            }
+        }
+    }
+
+    fn emit(&mut self, event: HealthEvent) {
+        (self.handler)(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k4a_sys_temp as k4a_sys;
+    use std::ptr::null_mut;
+
+    const TIMEOUT: Result<Capture, DeviceGetCaptureError> =
+        Err(DeviceGetCaptureError::TimeoutError { timeout_millis: 10 });
+
+    /// A capture taken at `temperature_c`, or without a temperature for NaN.
+    fn capture(temperature_c: f32) -> Result<Capture, DeviceGetCaptureError> {
+        let mut handle = null_mut();
+        unsafe {
+            assert_eq!(k4a_sys::k4a_capture_create(&mut handle), k4a_sys::k4a_result_t_K4A_RESULT_SUCCEEDED);
+            k4a_sys::k4a_capture_set_temperature_c(handle, temperature_c);
+        }
+        Ok(Capture(handle))
+    }
+
+    fn imu_sample(temperature_c: f32) -> Result<ImuSample, DeviceGetImuSampleError> {
+        Ok(ImuSample {
+            temperature_c,
+            acc_sample: [0.0, 0.0, 9.8],
+            acc_timestamp_usec: 0,
+            gyro_sample: [0.0; 3],
+            gyro_timestamp_usec: 0,
+        })
+    }
+
+    fn events(receiver: &Receiver<HealthEvent>) -> Vec<HealthEvent> {
+        receiver.try_iter().collect()
+    }
+
+    #[test]
+    fn stalls_are_reported_once_and_resumed() {
+        let (mut monitor, receiver) = HealthMonitor::channel();
+        monitor.stall_timeouts = 3;
+
+        for _ in 0..2 {
+            monitor.observe_capture(&TIMEOUT);
+        }
+        assert!(events(&receiver).is_empty());
+        assert!(!monitor.is_stalled());
+
+        monitor.observe_capture(&TIMEOUT);
+        assert!(matches!(events(&receiver)[..], [HealthEvent::Stalled { consecutive_timeouts: 3 }]));
+        assert!(monitor.is_stalled());
+
+        // Further timeouts don't report the same stall again.
+        for _ in 0..2 {
+            monitor.observe_capture(&TIMEOUT);
+        }
+        assert!(events(&receiver).is_empty());
+
+        monitor.observe_capture(&capture(f32::NAN));
+        assert!(matches!(events(&receiver)[..], [HealthEvent::Resumed { consecutive_timeouts: 5 }]));
+        assert!(!monitor.is_stalled());
+
+        // Timeouts short of a stall aren't reported at all.
+        monitor.observe_capture(&TIMEOUT);
+        monitor.observe_capture(&capture(f32::NAN));
+        assert!(events(&receiver).is_empty());
+    }
+
+    #[test]
+    fn failures_are_reported_but_imu_timeouts_are_not() {
+        let (mut monitor, receiver) = HealthMonitor::channel();
+
+        monitor.observe_imu_sample(&Err(DeviceGetImuSampleError::TimeoutError { timeout_millis: 10 }));
+        assert!(events(&receiver).is_empty());
+
+        monitor.observe_imu_sample(&Err(DeviceGetImuSampleError::FailedError));
+        monitor.observe_capture(&Err(DeviceGetCaptureError::FailedError));
+        assert!(matches!(events(&receiver)[..], [
+            HealthEvent::ImuSampleFailed(DeviceGetImuSampleError::FailedError),
+            HealthEvent::CaptureFailed(DeviceGetCaptureError::FailedError),
+        ]));
+    }
+
+    #[test]
+    fn temperatures_are_rate_limited_per_sensor() {
+        let (mut monitor, receiver) = HealthMonitor::channel();
+        monitor.temperature_interval = Duration::from_secs(3600);
+
+        monitor.observe_capture(&capture(30.0));
+        monitor.observe_capture(&capture(31.0));
+        monitor.observe_imu_sample(&imu_sample(35.0));
+        monitor.observe_imu_sample(&imu_sample(36.0));
+
+        let events = events(&receiver);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], HealthEvent::Temperature { sensor: TemperatureSensor::Capture, temperature_c }
+                         if temperature_c == 30.0));
+        assert!(matches!(events[1], HealthEvent::Temperature { sensor: TemperatureSensor::Imu, temperature_c }
+                         if temperature_c == 35.0));
+
+        // Captures without a temperature report none.
+        monitor.temperature_interval = Duration::from_secs(0);
+        monitor.observe_capture(&capture(f32::NAN));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn every_reading_over_the_maximum_is_overheating() {
+        let (mut monitor, receiver) = HealthMonitor::channel();
+        monitor.temperature_interval = Duration::from_secs(3600);
+        monitor.max_temperature_c = Some(60.0);
+
+        monitor.observe_capture(&capture(59.0));
+        monitor.observe_capture(&capture(61.0));
+        monitor.observe_imu_sample(&imu_sample(65.0));
+
+        let events = events(&receiver);
+        assert_eq!(events.len(), 4, "{:?}", events);
+        assert!(matches!(events[0], HealthEvent::Temperature { sensor: TemperatureSensor::Capture, .. }));
+        assert!(matches!(events[1], HealthEvent::Overheating { sensor: TemperatureSensor::Capture, .. }));
+        if let HealthEvent::Overheating { temperature_c, max_temperature_c, .. } = events[1] {
+            assert_eq!((temperature_c, max_temperature_c), (61.0, 60.0));
+        }
+        assert!(matches!(events[2], HealthEvent::Temperature { sensor: TemperatureSensor::Imu, .. }));
+        assert!(matches!(events[3], HealthEvent::Overheating { sensor: TemperatureSensor::Imu, .. }));
+    }
+}
@@ -1,7 +1,6 @@
 #![allow(unused)]
 
 use crate::ImageFormat;
-use crate::KinectError;
 use k4a_sys_temp as k4a_sys;
 use std::ptr::null_mut;
 use crate::error::CreateImageError;
@@ -33,11 +32,12 @@ impl Image {
     ///   stride_bytes - The number of bytes per horizontal line of the image. If set to 0, the stride
     ///                  will be set to the minimum size given the format and width_pixels.
     ///
+    /// The image is returned as an `ImageMut`, so it can be filled in before it's shared.
     pub fn create(format: ImageFormat,
                   width: u32,
                   height: u32,
                   stride_bytes: u32)
-                  -> Result<Self, CreateImageError>
+                  -> Result<ImageMut, CreateImageError>
     {
         let mut handle = null_mut();
 
@@ -58,7 +58,7 @@ impl Image {
             return Err(CreateImageError { error_code: result as i32 });
         }
 
-        Ok(Image(handle))
+        Ok(ImageMut(Image(handle)))
     }
 
     pub fn get_height_pixels(&self) -> usize {
@@ -91,6 +91,55 @@ impl Image {
         }
     }
 
+    /// Get the image buffer as a byte slice.
+    ///
+    /// Rows are `get_stride_bytes()` apart, which may be more than the bytes used by each row.
+    pub fn get_data(&self) -> &[u8] {
+        let buffer = self.get_buffer();
+        if buffer.is_null() {
+            return &[];
+        }
+        unsafe {
+            std::slice::from_raw_parts(buffer, self.get_size())
+        }
+    }
+
+    /// Copy out the pixels of a 16-bit image (eg. `Depth16` or `Ir16`), row by row without the
+    /// stride padding.
+    pub(crate) fn read_u16_pixels(&self) -> Vec<u16> {
+        let width = self.get_width_pixels();
+        let height = self.get_height_pixels();
+        let stride = self.get_stride_bytes();
+        let buffer = self.get_data();
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let row = &buffer[y * stride..y * stride + width * 2];
+            pixels.extend(row.chunks_exact(2).map(|pixel| u16::from_le_bytes([pixel[0], pixel[1]])));
+        }
+
+        pixels
+    }
+
+    /// Get the image's device timestamp in microseconds.
+    ///
+    /// This is the time the image was captured, according to the device's own clock. Device
+    /// timestamps of devices in a wired synchronization rig are comparable with each other.
+    pub fn get_device_timestamp_usec(&self) -> u64 {
+        unsafe {
+            k4a_sys::k4a_image_get_device_timestamp_usec(self.0)
+        }
+    }
+
+    /// Get the image's system timestamp in nanoseconds.
+    ///
+    /// This is the time the host system received the image, according to the host's monotonic clock.
+    pub fn get_system_timestamp_nsec(&self) -> u64 {
+        unsafe {
+            k4a_sys::k4a_image_get_system_timestamp_nsec(self.0)
+        }
+    }
+
     /// Use this function to determine the format of the image buffer.
     /// This function is not expected to fail, all k4a_image_t's are created with a
     /// known format. If the image_handle is invalid, the function will return
@@ -127,9 +176,7 @@ impl Clone for Image {
         unsafe {
             k4a_sys::k4a_image_reference(handle);
         }
-        Self {
-            0: handle,
-        }
+        Self(handle)
     }
 
     fn clone_from(&mut self, source: &Self) {
@@ -145,3 +192,103 @@ impl Clone for Image {
     }
 }
 
+
+/// An image with a single owner, whose buffer can be written.
+///
+/// `Image`s share their buffer with their clones through the libk4a refcount, so they only give
+/// out read access. `Image::create` and `ImagePool::acquire` return an `ImageMut` instead, which
+/// can't be cloned, so `get_data_mut` and the `_into` functions of `Transformation` and
+/// `visualization` can write its buffer without anything else seeing it. Convert it into an
+/// `Image` with `into_image` once it's filled in.
+#[derive(Debug)]
+pub struct ImageMut(Image);
+
+impl ImageMut {
+    /// Take ownership of a handle with a single refcount, which nothing else may use.
+    pub(crate) fn from_handle(handle: k4a_sys::k4a_image_t) -> Self {
+        ImageMut(Image(handle))
+    }
+
+    pub fn get_height_pixels(&self) -> usize {
+        self.0.get_height_pixels()
+    }
+
+    pub fn get_width_pixels(&self) -> usize {
+        self.0.get_width_pixels()
+    }
+
+    pub fn get_stride_bytes(&self) -> usize {
+        self.0.get_stride_bytes()
+    }
+
+    pub fn get_size(&self) -> usize {
+        self.0.get_size()
+    }
+
+    pub fn get_format(&self) -> ImageFormat {
+        self.0.get_format()
+    }
+
+    /// Get the image buffer as a byte slice.
+    ///
+    /// Rows are `get_stride_bytes()` apart, which may be more than the bytes used by each row.
+    pub fn get_data(&self) -> &[u8] {
+        self.0.get_data()
+    }
+
+    /// Get the image buffer as a mutable byte slice.
+    ///
+    /// Rows are `get_stride_bytes()` apart, which may be more than the bytes used by each row.
+    pub fn get_data_mut(&mut self) -> &mut [u8] {
+        let buffer = self.0.get_buffer();
+        if buffer.is_null() {
+            return &mut [];
+        }
+        // NB: No clone of the image exists, so `&mut self` is the only way to reach its buffer.
+        unsafe {
+            std::slice::from_raw_parts_mut(buffer, self.get_size())
+        }
+    }
+
+    /// Returns the underlying opaque handle *without* an additional refcount.
+    /// Do not deallocate it, or add references to it.
+    pub fn get_handle(&self) -> k4a_sys::k4a_image_t {
+        self.0.get_handle()
+    }
+
+    /// Give up write access, returning an `Image` that can be cloned and shared.
+    pub fn into_image(self) -> Image {
+        self.0
+    }
+}
+
+impl From<ImageMut> for Image {
+    fn from(image: ImageMut) -> Self {
+        image.into_image()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// An image of `pixels`, whose rows are `padding` bytes longer than the pixels, filled with `0xee`.
+    pub(crate) fn padded_image(format: ImageFormat,
+                               width: usize,
+                               height: usize,
+                               bytes_per_pixel: usize,
+                               padding: usize,
+                               pixels: &[u8]) -> Image
+    {
+        let stride = width * bytes_per_pixel + padding;
+        let mut image = Image::create(format, width as u32, height as u32, stride as u32).unwrap();
+        let data = image.get_data_mut();
+        for (y, row) in pixels.chunks_exact(width * bytes_per_pixel).enumerate() {
+            data[y * stride..y * stride + row.len()].copy_from_slice(row);
+            for byte in data[y * stride + row.len()..(y + 1) * stride].iter_mut() {
+                *byte = 0xee;
+            }
+        }
+        image.into_image()
+    }
+}
@@ -0,0 +1,187 @@
+//! Conversions between `Image` and the `image` crate's buffers, with the `image` feature.
+//!
+//! Conversions copy, since libk4a and the `image` crate each own their buffers. Rows are read and
+//! written at the libk4a image's stride, and BGRA is swizzled to and from RGBA.
+
+use crate::error::ImageConversionError;
+use crate::Image;
+use crate::ImageFormat;
+
+use ::image::{ImageBuffer, Luma, RgbaImage};
+use std::convert::TryFrom;
+
+/// A 16-bit grayscale buffer, which depth and IR images convert to.
+pub type Gray16Image = ImageBuffer<Luma<u16>, Vec<u16>>;
+
+/// Converts `ColorBgra32` images.
+impl TryFrom<&Image> for RgbaImage {
+    type Error = ImageConversionError;
+
+    fn try_from(image: &Image) -> Result<Self, Self::Error> {
+        match image.get_format() {
+            ImageFormat::ColorBgra32 => {},
+            format => return Err(ImageConversionError::UnsupportedImageFormatError(format)),
+        }
+
+        let width = image.get_width_pixels();
+        let height = image.get_height_pixels();
+        let stride = image.get_stride_bytes();
+        let data = image.get_data();
+
+        let mut pixels = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            for bgra in data[y * stride..y * stride + width * 4].chunks_exact(4) {
+                pixels.extend_from_slice(&[bgra[2], bgra[1], bgra[0], bgra[3]]);
+            }
+        }
+
+        Ok(RgbaImage::from_raw(width as u32, height as u32, pixels).expect("a buffer of width * height pixels"))
+    }
+}
+
+/// Converts `Depth16`, `Ir16` and `Custom16` images.
+impl TryFrom<&Image> for Gray16Image {
+    type Error = ImageConversionError;
+
+    fn try_from(image: &Image) -> Result<Self, Self::Error> {
+        match image.get_format() {
+            ImageFormat::Depth16 | ImageFormat::Ir16 | ImageFormat::Custom16 => {},
+            format => return Err(ImageConversionError::UnsupportedImageFormatError(format)),
+        }
+
+        let width = image.get_width_pixels();
+        let height = image.get_height_pixels();
+        let stride = image.get_stride_bytes();
+        let data = image.get_data();
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for sample in data[y * stride..y * stride + width * 2].chunks_exact(2) {
+                pixels.push(u16::from_le_bytes([sample[0], sample[1]]));
+            }
+        }
+
+        Ok(Gray16Image::from_raw(width as u32, height as u32, pixels).expect("a buffer of width * height pixels"))
+    }
+}
+
+/// Creates a `ColorBgra32` image.
+impl TryFrom<&RgbaImage> for Image {
+    type Error = ImageConversionError;
+
+    fn try_from(buffer: &RgbaImage) -> Result<Self, Self::Error> {
+        let mut image = Image::create(ImageFormat::ColorBgra32, buffer.width(), buffer.height(), 0)
+            .map_err(ImageConversionError::CreateImageError)?;
+
+        let width = image.get_width_pixels();
+        let stride = image.get_stride_bytes();
+        let data = image.get_data_mut();
+
+        for (y, row) in buffer.as_raw().chunks_exact(width * 4).enumerate() {
+            for (rgba, bgra) in row.chunks_exact(4).zip(data[y * stride..].chunks_exact_mut(4)) {
+                bgra.copy_from_slice(&[rgba[2], rgba[1], rgba[0], rgba[3]]);
+            }
+        }
+
+        Ok(image.into_image())
+    }
+}
+
+/// Creates a `Depth16` image. Use `Image::from_gray16` for IR.
+impl TryFrom<&Gray16Image> for Image {
+    type Error = ImageConversionError;
+
+    fn try_from(buffer: &Gray16Image) -> Result<Self, Self::Error> {
+        Image::from_gray16(buffer, ImageFormat::Depth16)
+    }
+}
+
+impl Image {
+    /// Create a `Depth16`, `Ir16` or `Custom16` image from a 16-bit grayscale buffer.
+    pub fn from_gray16(buffer: &Gray16Image, format: ImageFormat) -> Result<Self, ImageConversionError> {
+        match format {
+            ImageFormat::Depth16 | ImageFormat::Ir16 | ImageFormat::Custom16 => {},
+            format => return Err(ImageConversionError::UnsupportedImageFormatError(format)),
+        }
+
+        let mut image = Image::create(format, buffer.width(), buffer.height(), 0)
+            .map_err(ImageConversionError::CreateImageError)?;
+
+        let width = image.get_width_pixels();
+        let stride = image.get_stride_bytes();
+        let data = image.get_data_mut();
+
+        for (y, row) in buffer.as_raw().chunks_exact(width).enumerate() {
+            for (sample, bytes) in row.iter().zip(data[y * stride..].chunks_exact_mut(2)) {
+                bytes.copy_from_slice(&sample.to_le_bytes());
+            }
+        }
+
+        Ok(image.into_image())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::tests::padded_image;
+
+    #[test]
+    fn rgba_images_round_trip() {
+        let rgba = RgbaImage::from_fn(3, 2, |x, y| ::image::Rgba([x as u8, y as u8, 10 * x as u8 + y as u8, 255]));
+
+        let image = Image::try_from(&rgba).unwrap();
+        assert!(matches!(image.get_format(), ImageFormat::ColorBgra32));
+        assert_eq!((image.get_width_pixels(), image.get_height_pixels()), (3, 2));
+        // The pixel at (2, 1) is stored as BGRA.
+        assert_eq!(&image.get_data()[image.get_stride_bytes() + 8..][..4], &[21, 1, 2, 255]);
+
+        assert_eq!(RgbaImage::try_from(&image).unwrap(), rgba);
+    }
+
+    #[test]
+    fn gray16_images_round_trip() {
+        let gray = Gray16Image::from_fn(3, 2, |x, y| Luma([1000 * x as u16 + y as u16 + 300]));
+
+        let depth = Image::try_from(&gray).unwrap();
+        assert!(matches!(depth.get_format(), ImageFormat::Depth16));
+        assert_eq!(Gray16Image::try_from(&depth).unwrap(), gray);
+
+        let ir = Image::from_gray16(&gray, ImageFormat::Ir16).unwrap();
+        assert!(matches!(ir.get_format(), ImageFormat::Ir16));
+        assert_eq!(Gray16Image::try_from(&ir).unwrap(), gray);
+
+        assert!(matches!(Image::from_gray16(&gray, ImageFormat::ColorBgra32),
+                         Err(ImageConversionError::UnsupportedImageFormatError(_))));
+    }
+
+    #[test]
+    fn row_padding_is_skipped() {
+        let bgra = [
+            1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,
+            13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
+        ];
+        let image = padded_image(ImageFormat::ColorBgra32, 3, 2, 4, 4, &bgra);
+        let rgba = RgbaImage::try_from(&image).unwrap();
+        assert_eq!(rgba.get_pixel(0, 0).0, [3, 2, 1, 4]);
+        assert_eq!(rgba.get_pixel(2, 1).0, [23, 22, 21, 24]);
+
+        // Back to a tightly packed image and out again, the pixels are unchanged.
+        assert_eq!(RgbaImage::try_from(&Image::try_from(&rgba).unwrap()).unwrap(), rgba);
+
+        let samples: Vec<u8> = [100u16, 200, 300, 400, 500, 600].iter()
+            .flat_map(|sample| sample.to_le_bytes().to_vec())
+            .collect();
+        let image = padded_image(ImageFormat::Depth16, 3, 2, 2, 2, &samples);
+        let gray = Gray16Image::try_from(&image).unwrap();
+        assert_eq!(gray.as_raw(), &vec![100, 200, 300, 400, 500, 600]);
+        assert_eq!(Gray16Image::try_from(&Image::try_from(&gray).unwrap()).unwrap(), gray);
+    }
+
+    #[test]
+    fn other_formats_are_rejected() {
+        let image = Image::create(ImageFormat::Custom8, 2, 2, 0).unwrap().into_image();
+        assert!(matches!(RgbaImage::try_from(&image), Err(ImageConversionError::UnsupportedImageFormatError(_))));
+        assert!(matches!(Gray16Image::try_from(&image), Err(ImageConversionError::UnsupportedImageFormatError(_))));
+    }
+}
@@ -0,0 +1,251 @@
+//! Reusing image buffers across frames.
+
+use crate::allocator::{ImageAllocator, SystemAllocator};
+use crate::error::{CreateImageError, ImagePoolError};
+use crate::ImageMut;
+use crate::ImageFormat;
+use k4a_sys_temp as k4a_sys;
+
+use std::collections::HashMap;
+use std::os::raw::c_void;
+use std::ptr::null_mut;
+use std::sync::{Arc, Mutex, Weak};
+
+/// A pool of image buffers, keyed by format, size and stride.
+///
+/// `acquire` hands out `ImageMut`s backed by pooled buffers. When libk4a releases an image (its
+/// last clone is dropped), its buffer goes back to the pool for the next `acquire` of the
+/// same shape, so a pipeline producing the same images every frame stops allocating after the
+/// first few frames. Use pooled images as outputs with the `_into` variants of `Transformation`
+/// and `visualization` functions.
+///
+/// Clones of the pool share its buffers. Buffers of images outliving the pool are freed.
+#[derive(Clone)]
+pub struct ImagePool {
+    inner: Arc<PoolInner>,
+}
+
+/// Counts of an `ImagePool`'s requests and buffers.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ImagePoolStats {
+    /// Acquired images that reused a pooled buffer.
+    pub hits: u64,
+    /// Acquired images that needed a new buffer.
+    pub misses: u64,
+    /// Buffers of acquired images that haven't been released yet.
+    pub outstanding_buffers: usize,
+    /// Buffers waiting in the pool.
+    pub idle_buffers: usize,
+    /// Bytes of buffers waiting in the pool.
+    pub idle_bytes: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct PoolKey {
+    format: k4a_sys::k4a_image_format_t,
+    width: u32,
+    height: u32,
+    stride_bytes: u32,
+}
+
+struct PoolInner {
+    max_idle_per_key: usize,
+    state: Mutex<PoolState>,
+}
+
+#[derive(Default)]
+struct PoolState {
+    idle: HashMap<PoolKey, Vec<Buffer>>,
+    stats: ImagePoolStats,
+}
+
+/// A buffer from `SystemAllocator`, freed on drop.
+struct Buffer {
+    data: *mut u8,
+    size: usize,
+}
+
+unsafe impl Send for Buffer {}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        unsafe {
+            SystemAllocator.free(self.data, self.size);
+        }
+    }
+}
+
+/// A buffer lent to libk4a, passed through the image's release callback context.
+struct Lease {
+    pool: Weak<PoolInner>,
+    key: PoolKey,
+    buffer: Buffer,
+}
+
+impl ImagePool {
+    /// A pool keeping at most 4 idle buffers of each shape.
+    pub fn new() -> Self {
+        Self::with_max_idle_per_key(4)
+    }
+
+    /// A pool keeping at most `max_idle_per_key` idle buffers of each shape. Buffers released
+    /// while the pool is full are freed.
+    pub fn with_max_idle_per_key(max_idle_per_key: usize) -> Self {
+        Self {
+            inner: Arc::new(PoolInner {
+                max_idle_per_key,
+                state: Mutex::new(PoolState::default()),
+            }),
+        }
+    }
+
+    /// Get an image, reusing a pooled buffer if there is one of the same shape.
+    ///
+    /// Arguments are as for `Image::create`, including a `stride_bytes` of 0 for the minimum
+    /// stride. The contents of a reused buffer are left over from its previous image.
+    pub fn acquire(&self,
+                   format: ImageFormat,
+                   width: u32,
+                   height: u32,
+                   stride_bytes: u32) -> Result<ImageMut, ImagePoolError>
+    {
+        let stride_bytes = match stride_bytes {
+            0 => minimum_stride_bytes(format, width).ok_or(ImagePoolError::UnsupportedImageFormatError(format))?,
+            stride_bytes => stride_bytes,
+        };
+        let size = buffer_size(format, height, stride_bytes).ok_or(ImagePoolError::UnsupportedImageFormatError(format))?;
+
+        let key = PoolKey { format: format.to_k4a(), width, height, stride_bytes };
+
+        let pooled = {
+            let mut state = self.inner.lock();
+            let buffer = state.idle.get_mut(&key).and_then(|buffers| buffers.pop());
+            match buffer {
+                Some(_) => {
+                    state.stats.hits += 1;
+                    state.stats.idle_buffers -= 1;
+                    state.stats.idle_bytes -= size;
+                },
+                None => state.stats.misses += 1,
+            }
+            buffer
+        };
+
+        let buffer = match pooled {
+            Some(buffer) => buffer,
+            None => {
+                let data = SystemAllocator.allocate(size);
+                if data.is_null() {
+                    // NB: Mirrors libk4a, which fails to create images it can't allocate.
+                    return Err(ImagePoolError::CreateImageError(CreateImageError {
+                        error_code: k4a_sys::k4a_result_t_K4A_RESULT_FAILED as i32,
+                    }));
+                }
+                Buffer { data, size }
+            },
+        };
+
+        let data = buffer.data;
+        let lease = Box::into_raw(Box::new(Lease { pool: Arc::downgrade(&self.inner), key, buffer }));
+        let mut handle = null_mut();
+
+        let result = unsafe {
+            k4a_sys::k4a_image_create_from_buffer(
+                key.format,
+                width as i32,
+                height as i32,
+                stride_bytes as i32,
+                data,
+                size,
+                Some(release_buffer),
+                lease as *mut c_void,
+                &mut handle,
+            )
+        };
+
+        if result != k4a_sys::k4a_result_t_K4A_RESULT_SUCCEEDED {
+            // libk4a doesn't call the release callback for images it failed to create.
+            drop(unsafe { Box::from_raw(lease) });
+            // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
+            // Linux uses u32 and Windows uses i32.
+            // This should be fixed in the `k4a-sys` build script.
+            return Err(ImagePoolError::CreateImageError(CreateImageError { error_code: result as i32 }));
+        }
+
+        self.inner.lock().stats.outstanding_buffers += 1;
+        Ok(ImageMut::from_handle(handle))
+    }
+
+    pub fn stats(&self) -> ImagePoolStats {
+        self.inner.lock().stats
+    }
+
+    /// Free the idle buffers.
+    pub fn clear(&self) {
+        let idle = {
+            let mut state = self.inner.lock();
+            state.stats.idle_buffers = 0;
+            state.stats.idle_bytes = 0;
+            std::mem::take(&mut state.idle)
+        };
+        drop(idle);
+    }
+}
+
+impl Default for ImagePool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PoolInner {
+    fn lock(&self) -> std::sync::MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+/// The stride of a tightly packed row, or None for formats without a fixed number of bytes per
+/// pixel.
+fn minimum_stride_bytes(format: ImageFormat, width: u32) -> Option<u32> {
+    let bytes_per_pixel = match format {
+        ImageFormat::ColorNv12 | ImageFormat::Custom8 => 1,
+        ImageFormat::ColorYuy2 | ImageFormat::Depth16 | ImageFormat::Ir16 | ImageFormat::Custom16 => 2,
+        ImageFormat::ColorBgra32 => 4,
+        ImageFormat::ColorMjpg | ImageFormat::Custom | ImageFormat::UnknownFormatError => return None,
+    };
+    Some(width * bytes_per_pixel)
+}
+
+/// The buffer size `k4a_image_create` would allocate.
+fn buffer_size(format: ImageFormat, height: u32, stride_bytes: u32) -> Option<usize> {
+    let rows = match format {
+        // The interleaved UV plane adds half as many rows again.
+        ImageFormat::ColorNv12 => height as usize + height as usize / 2,
+        ImageFormat::ColorMjpg | ImageFormat::UnknownFormatError => return None,
+        _ => height as usize,
+    };
+    Some(rows * stride_bytes as usize)
+}
+
+unsafe extern "C" fn release_buffer(_buffer: *mut c_void, context: *mut c_void) {
+    let lease = Box::from_raw(context as *mut Lease);
+    let Lease { pool, key, buffer } = *lease;
+
+    // Unwinding into libk4a is undefined behavior.
+    let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+        let pool = match pool.upgrade() {
+            Some(pool) => pool,
+            None => return,
+        };
+        let mut state = pool.lock();
+        state.stats.outstanding_buffers -= 1;
+
+        let idle = state.idle.entry(key).or_default();
+        if idle.len() < pool.max_idle_per_key {
+            let size = buffer.size;
+            idle.push(buffer);
+            state.stats.idle_buffers += 1;
+            state.stats.idle_bytes += size;
+        }
+    }));
+}
@@ -0,0 +1,200 @@
+//! A k-d tree over the valid points of a cloud, for neighbor queries.
+
+use crate::point_cloud::is_valid_point;
+
+pub(crate) struct KdTree<'a> {
+    points: &'a [[f32; 3]],
+    /// Point indices, arranged so that the median of every range splits it on the range's axis.
+    indices: Vec<usize>,
+}
+
+impl<'a> KdTree<'a> {
+    /// Build a tree over the valid points. Invalid points are never returned by queries.
+    pub(crate) fn new(points: &'a [[f32; 3]]) -> Self {
+        let mut indices: Vec<usize> = (0..points.len()).filter(|i| is_valid_point(&points[*i])).collect();
+        build(points, &mut indices, 0);
+        Self { points, indices }
+    }
+
+    /// The `k` nearest points to `query` other than `exclude`, as (squared distance, index) pairs
+    /// sorted by distance.
+    pub(crate) fn nearest(&self, query: [f32; 3], k: usize, exclude: Option<usize>) -> Vec<(f32, usize)> {
+        let mut nearest = Vec::with_capacity(k + 1);
+        if k > 0 {
+            self.search_nearest(query, k, exclude, 0, self.indices.len(), 0, &mut nearest);
+        }
+        nearest
+    }
+
+    /// The number of points within `radius` of `query` other than `exclude`, counting no further
+    /// than `limit`.
+    pub(crate) fn count_within(&self, query: [f32; 3], radius: f32, exclude: Option<usize>, limit: usize) -> usize {
+        let mut count = 0;
+        self.search_within(query, radius * radius, exclude, limit, 0, self.indices.len(), 0, &mut count);
+        count
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn search_nearest(&self,
+                      query: [f32; 3],
+                      k: usize,
+                      exclude: Option<usize>,
+                      start: usize,
+                      end: usize,
+                      depth: usize,
+                      nearest: &mut Vec<(f32, usize)>)
+    {
+        if start >= end {
+            return;
+        }
+
+        let middle = (start + end) / 2;
+        let index = self.indices[middle];
+        let point = self.points[index];
+
+        if exclude != Some(index) {
+            let distance = squared_distance(query, point);
+            if nearest.len() < k || distance < nearest[nearest.len() - 1].0 {
+                let position = nearest.partition_point(|(d, _)| *d <= distance);
+                nearest.insert(position, (distance, index));
+                nearest.truncate(k);
+            }
+        }
+
+        let axis = depth % 3;
+        let offset = query[axis] - point[axis];
+        let (near, far) = if offset < 0.0 {
+            ((start, middle), (middle + 1, end))
+        } else {
+            ((middle + 1, end), (start, middle))
+        };
+
+        self.search_nearest(query, k, exclude, near.0, near.1, depth + 1, nearest);
+        if nearest.len() < k || offset * offset < nearest[nearest.len() - 1].0 {
+            self.search_nearest(query, k, exclude, far.0, far.1, depth + 1, nearest);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn search_within(&self,
+                     query: [f32; 3],
+                     radius_squared: f32,
+                     exclude: Option<usize>,
+                     limit: usize,
+                     start: usize,
+                     end: usize,
+                     depth: usize,
+                     count: &mut usize)
+    {
+        if start >= end || *count >= limit {
+            return;
+        }
+
+        let middle = (start + end) / 2;
+        let index = self.indices[middle];
+        let point = self.points[index];
+
+        if exclude != Some(index) && squared_distance(query, point) <= radius_squared {
+            *count += 1;
+        }
+
+        let axis = depth % 3;
+        let offset = query[axis] - point[axis];
+
+        if offset <= 0.0 || offset * offset <= radius_squared {
+            self.search_within(query, radius_squared, exclude, limit, start, middle, depth + 1, count);
+        }
+        if offset >= 0.0 || offset * offset <= radius_squared {
+            self.search_within(query, radius_squared, exclude, limit, middle + 1, end, depth + 1, count);
+        }
+    }
+}
+
+fn build(points: &[[f32; 3]], indices: &mut [usize], depth: usize) {
+    if indices.len() <= 1 {
+        return;
+    }
+
+    let axis = depth % 3;
+    let middle = indices.len() / 2;
+    indices.select_nth_unstable_by(middle, |a, b| {
+        points[*a][axis].partial_cmp(&points[*b][axis]).unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let (left, right) = indices.split_at_mut(middle);
+    build(points, left, depth + 1);
+    build(points, &mut right[1..], depth + 1);
+}
+
+fn squared_distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let dx = a[0] - b[0];
+    let dy = a[1] - b[1];
+    let dz = a[2] - b[2];
+    dx * dx + dy * dy + dz * dz
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Points spread over a 1m cube, from a fixed seed, with every tenth point invalid.
+    fn scattered_points(count: usize) -> Vec<[f32; 3]> {
+        let mut state = 0x2545_f491_u32;
+        let mut next = move || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1 << 24) as f32 * 1000.0
+        };
+        (0..count)
+            .map(|i| if i % 10 == 9 { [0.0; 3] } else { [next() - 500.0, next() - 500.0, next() + 500.0] })
+            .collect()
+    }
+
+    fn brute_force_distances(points: &[[f32; 3]], query: [f32; 3], exclude: Option<usize>) -> Vec<f32> {
+        let mut distances: Vec<f32> = (0..points.len())
+            .filter(|i| is_valid_point(&points[*i]) && exclude != Some(*i))
+            .map(|i| squared_distance(query, points[i]))
+            .collect();
+        distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        distances
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let points = scattered_points(500);
+        let tree = KdTree::new(&points);
+
+        for (i, query) in points.iter().enumerate().filter(|(i, _)| i % 7 == 0) {
+            let exclude = if i % 2 == 0 { Some(i) } else { None };
+            let expected = brute_force_distances(&points, *query, exclude);
+
+            for k in [1, 5, 20].iter() {
+                let nearest = tree.nearest(*query, *k, exclude);
+                let distances: Vec<f32> = nearest.iter().map(|(distance, _)| *distance).collect();
+                assert_eq!(distances, expected[..*k]);
+                for (distance, index) in nearest {
+                    assert!(exclude != Some(index) && is_valid_point(&points[index]));
+                    assert_eq!(distance, squared_distance(*query, points[index]));
+                }
+            }
+        }
+
+        assert!(tree.nearest([0.0, 0.0, 1000.0], 0, None).is_empty());
+        assert_eq!(tree.nearest([0.0, 0.0, 1000.0], 1000, None).len(), 450);
+    }
+
+    #[test]
+    fn count_within_matches_brute_force() {
+        let points = scattered_points(500);
+        let tree = KdTree::new(&points);
+
+        for (i, query) in points.iter().enumerate().filter(|(i, _)| i % 11 == 0) {
+            for radius in [50.0_f32, 150.0, 400.0].iter() {
+                let expected = brute_force_distances(&points, *query, Some(i)).iter()
+                    .filter(|distance| **distance <= radius * radius)
+                    .count();
+                assert_eq!(tree.count_within(*query, *radius, Some(i), usize::MAX), expected);
+                assert_eq!(tree.count_within(*query, *radius, Some(i), 3), expected.min(3));
+            }
+        }
+    }
+}
@@ -4,43 +4,118 @@
 // Normally we'd follow k4a-sys upstream, but it doesn't properly build on Linux.
 pub use k4a_sys_temp as k4a_sys;
 
+mod allocator;
+#[cfg(feature = "body-tracking")]
+mod body_tracking;
 mod calibration;
 mod capture;
+mod capture_matcher;
+mod checkerboard;
+mod clock_sync;
+mod debug_message;
 mod device;
 mod device_configuration;
+mod device_info;
+mod extrinsic_calibration;
+#[cfg(feature = "glam")]
+mod glam_interop;
+mod hardware_version;
+mod health_monitor;
 mod image;
 mod image_format;
+#[cfg(feature = "image")]
+mod image_interop;
+mod image_pool;
+mod kd_tree;
+mod math;
+#[cfg(feature = "nalgebra")]
+mod nalgebra_interop;
+#[cfg(feature = "ndarray")]
+mod ndarray_interop;
+mod point_cloud;
+mod point_cloud_io;
+mod point_cloud_processing;
+#[cfg(feature = "record")]
+mod record;
+mod resilient_device;
+mod skeleton;
+mod stream_stats;
+mod synced_rig;
 mod transformation;
 
 pub use {
-    calibration::Calibration,
+    allocator::{allocation_stats, reset_allocator, set_allocator, AllocationStats, ImageAllocator, SystemAllocator},
+    calibration::{Calibration, CalibrationPoint2, CalibrationPoint3, CalibrationType, Extrinsics, Intrinsics},
     capture::Capture,
+    capture_matcher::{CaptureMatcher, CaptureMatcherStats, DeviceTimestamped, Frameset, MatchOutput, Unmatched, UnmatchedReason},
+    checkerboard::Checkerboard,
+    clock_sync::{ClockMapping, ClockSync, HostClockReference},
+    debug_message::{clear_debug_message_handler, set_debug_message_handler, DebugMessage, LogLevel},
     device::Device,
     device_configuration::DeviceConfiguration,
-    image::Image,
+    device_info::DeviceInfo,
+    extrinsic_calibration::{observe_target, CameraExtrinsics, MultiCameraCalibrator, TargetObservation},
+    hardware_version::{FirmwareBuild, FirmwareSignature, HardwareVersion, Version},
+    health_monitor::{HealthEvent, HealthMonitor, TemperatureSensor},
+    image::{Image, ImageMut},
     image_format::ImageFormat,
+    image_pool::{ImagePool, ImagePoolStats},
+    point_cloud::PointCloud,
+    point_cloud_io::{PcdFormat, PlyFormat},
+    resilient_device::{Backoff, CaptureDevice, Gap, ResilientCapture, ResilientDevice},
+    skeleton::{Body, Joint, JointConfidenceLevel, JointId, Skeleton, JOINT_COUNT},
+    stream_stats::{CaptureStream, StreamStats, StreamStatsSummary, StreamSummary},
+    synced_rig::{CaptureSet, RigDevice, SyncRole, SyncedRig},
     transformation::Transformation,
 };
 
+#[cfg(feature = "body-tracking")]
+pub use body_tracking::{
+    ffi as k4abt_sys, BodyFrame, BodyTracker, BodyTrackerConfiguration, ProcessingMode, SensorOrientation,
+    BODY_INDEX_MAP_BACKGROUND,
+};
+
+#[cfg(feature = "record")]
+pub use record::{ffi as k4arecord_sys, Playback, Recorder, SeekOrigin};
+
+#[cfg(any(target_os = "linux", windows))]
+pub use clock_sync::host_clock_nsec;
+
+#[cfg(feature = "image")]
+pub use image_interop::Gray16Image;
+
+#[cfg(feature = "log")]
+pub use debug_message::install_log_handler;
+#[cfg(feature = "tracing")]
+pub use debug_message::install_tracing_handler;
+
+pub mod depth_filter;
 pub mod error;
+pub mod motion_export;
+pub mod visualization;
 
-/// A library error
-#[derive(Debug)]
-pub enum KinectError {
-    UnableToOpen { error_code: u32 },
-    UnableToGetSerialNumber,
-    UnableToStartCameras { error_code: u32 },
-    UnableToCreateImage { error_code: u32 },
-    UnableToGetSyncJackStatus { error_code: i32 },
-}
+pub use error::{Error, ResultCode};
 
 /// Synchronization jack status.
-#[derive(Debug,Copy,Clone)]
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
 pub struct SynchronizationJackStatus {
     pub sync_in_jack_connected: bool,
     pub sync_out_jack_connected: bool,
 }
 
+/// A sample from the inertial measurement unit.
+#[derive(Debug,Copy,Clone)]
+pub struct ImuSample {
+    /// Temperature of the IMU, in degrees Celsius.
+    pub temperature_c: f32,
+    /// Accelerometer reading, in meters per second squared.
+    pub acc_sample: [f32; 3],
+    pub acc_timestamp_usec: u64,
+    /// Gyroscope reading, in radians per second.
+    pub gyro_sample: [f32; 3],
+    pub gyro_timestamp_usec: u64,
+}
+
 #[derive(Clone,Debug)]
 pub struct Resolution {
     pub width: i32,
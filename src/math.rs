@@ -0,0 +1,298 @@
+//! Small dense linear algebra helpers for the pure-Rust geometry code.
+//!
+//! Matrices are 3x3, row major, in double precision.
+
+pub(crate) type Vec3 = [f64; 3];
+pub(crate) type Mat3 = [[f64; 3]; 3];
+
+pub(crate) const IDENTITY: Mat3 = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+pub(crate) fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub(crate) fn scale(a: Vec3, s: f64) -> Vec3 {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+pub(crate) fn dot(a: Vec3, b: Vec3) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub(crate) fn norm(a: Vec3) -> f64 {
+    dot(a, a).sqrt()
+}
+
+pub(crate) fn mat_vec(m: &Mat3, v: Vec3) -> Vec3 {
+    [dot(m[0], v), dot(m[1], v), dot(m[2], v)]
+}
+
+pub(crate) fn mat_mul(a: &Mat3, b: &Mat3) -> Mat3 {
+    let mut out = [[0.0; 3]; 3];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    out
+}
+
+pub(crate) fn transpose(m: &Mat3) -> Mat3 {
+    [
+        [m[0][0], m[1][0], m[2][0]],
+        [m[0][1], m[1][1], m[2][1]],
+        [m[0][2], m[1][2], m[2][2]],
+    ]
+}
+
+pub(crate) fn determinant(m: &Mat3) -> f64 {
+    dot(m[0], cross(m[1], m[2]))
+}
+
+/// Inverse of a 3x3 matrix, or None if it's singular.
+pub(crate) fn inverse(m: &Mat3) -> Option<Mat3> {
+    let det = determinant(m);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    // Columns of the adjugate transpose are cross products of rows.
+    let c0 = cross(m[1], m[2]);
+    let c1 = cross(m[2], m[0]);
+    let c2 = cross(m[0], m[1]);
+    Some(transpose(&[scale(c0, 1.0 / det), scale(c1, 1.0 / det), scale(c2, 1.0 / det)]))
+}
+
+/// The rotation nearest to `m`, by iterating the polar decomposition.
+pub(crate) fn orthonormalize(m: &Mat3) -> Mat3 {
+    let mut r = *m;
+    for _ in 0..30 {
+        let inverse_transpose = match inverse(&r) {
+            Some(inv) => transpose(&inv),
+            None => return IDENTITY,
+        };
+        let mut next = [[0.0; 3]; 3];
+        for i in 0..3 {
+            for j in 0..3 {
+                next[i][j] = 0.5 * (r[i][j] + inverse_transpose[i][j]);
+            }
+        }
+        let change: f64 = (0..3).flat_map(|i| (0..3).map(move |j| (i, j)))
+            .map(|(i, j)| (next[i][j] - r[i][j]).abs())
+            .sum();
+        r = next;
+        if change < 1e-12 {
+            break;
+        }
+    }
+    r
+}
+
+/// Rotation matrix from an axis-angle (Rodrigues) vector.
+pub(crate) fn rotation_from_axis_angle(w: Vec3) -> Mat3 {
+    let theta = norm(w);
+    if theta < 1e-12 {
+        return [[1.0, -w[2], w[1]], [w[2], 1.0, -w[0]], [-w[1], w[0], 1.0]];
+    }
+    let [x, y, z] = scale(w, 1.0 / theta);
+    let (s, c) = theta.sin_cos();
+    let t = 1.0 - c;
+    [
+        [t * x * x + c, t * x * y - s * z, t * x * z + s * y],
+        [t * x * y + s * z, t * y * y + c, t * y * z - s * x],
+        [t * x * z - s * y, t * y * z + s * x, t * z * z + c],
+    ]
+}
+
+/// Unit quaternion `[w, x, y, z]` from a rotation matrix.
+pub(crate) fn quaternion_from_rotation(m: &Mat3) -> [f64; 4] {
+    let trace = m[0][0] + m[1][1] + m[2][2];
+    let q = if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        [0.25 * s, (m[2][1] - m[1][2]) / s, (m[0][2] - m[2][0]) / s, (m[1][0] - m[0][1]) / s]
+    } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+        let s = (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.0;
+        [(m[2][1] - m[1][2]) / s, 0.25 * s, (m[0][1] + m[1][0]) / s, (m[0][2] + m[2][0]) / s]
+    } else if m[1][1] > m[2][2] {
+        let s = (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.0;
+        [(m[0][2] - m[2][0]) / s, (m[0][1] + m[1][0]) / s, 0.25 * s, (m[1][2] + m[2][1]) / s]
+    } else {
+        let s = (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.0;
+        [(m[1][0] - m[0][1]) / s, (m[0][2] + m[2][0]) / s, (m[1][2] + m[2][1]) / s, 0.25 * s]
+    };
+    let n = (q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3]).sqrt();
+    [q[0] / n, q[1] / n, q[2] / n, q[3] / n]
+}
+
+/// Rotation matrix from a quaternion `[w, x, y, z]` (normalized first).
+pub(crate) fn rotation_from_quaternion(q: [f64; 4]) -> Mat3 {
+    let n = (q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3]).sqrt();
+    let [w, x, y, z] = [q[0] / n, q[1] / n, q[2] / n, q[3] / n];
+    [
+        [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y)],
+        [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x)],
+        [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y)],
+    ]
+}
+
+/// Eigen decomposition of a symmetric `n`x`n` matrix (row major) by cyclic Jacobi rotations.
+///
+/// Returns the eigenvalues and the matching eigenvectors (as rows), sorted by ascending eigenvalue.
+pub(crate) fn symmetric_eigen(matrix: &[f64], n: usize) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let mut a = matrix.to_vec();
+    let mut v = vec![0.0; n * n];
+    for i in 0..n {
+        v[i * n + i] = 1.0;
+    }
+
+    for _ in 0..100 {
+        let off_diagonal: f64 = (0..n)
+            .flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[i * n + j] * a[i * n + j])
+            .sum();
+        if off_diagonal < 1e-22 {
+            break;
+        }
+
+        for p in 0..n {
+            for q in (p + 1)..n {
+                let apq = a[p * n + q];
+                if apq.abs() < 1e-300 {
+                    continue;
+                }
+                let theta = (a[q * n + q] - a[p * n + p]) / (2.0 * apq);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                for k in 0..n {
+                    let akp = a[k * n + p];
+                    let akq = a[k * n + q];
+                    a[k * n + p] = c * akp - s * akq;
+                    a[k * n + q] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let apk = a[p * n + k];
+                    let aqk = a[q * n + k];
+                    a[p * n + k] = c * apk - s * aqk;
+                    a[q * n + k] = s * apk + c * aqk;
+                }
+                for k in 0..n {
+                    let vkp = v[k * n + p];
+                    let vkq = v[k * n + q];
+                    v[k * n + p] = c * vkp - s * vkq;
+                    v[k * n + q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|i, j| a[i * n + i].partial_cmp(&a[j * n + j]).unwrap_or(std::cmp::Ordering::Equal));
+
+    let values = order.iter().map(|i| a[i * n + i]).collect();
+    let vectors = order.iter().map(|i| (0..n).map(|k| v[k * n + i]).collect()).collect();
+    (values, vectors)
+}
+
+/// Solve the square system `a x = b` by Gaussian elimination with partial pivoting.
+pub(crate) fn solve(a: &[f64], b: &[f64]) -> Option<Vec<f64>> {
+    let n = b.len();
+    let mut m = a.to_vec();
+    let mut x = b.to_vec();
+
+    for col in 0..n {
+        let pivot = (col..n).max_by(|i, j| {
+            m[i * n + col].abs().partial_cmp(&m[j * n + col].abs()).unwrap_or(std::cmp::Ordering::Equal)
+        })?;
+        if m[pivot * n + col].abs() < 1e-15 {
+            return None;
+        }
+        if pivot != col {
+            for k in 0..n {
+                m.swap(pivot * n + k, col * n + k);
+            }
+            x.swap(pivot, col);
+        }
+        for row in (col + 1)..n {
+            let factor = m[row * n + col] / m[col * n + col];
+            for k in col..n {
+                m[row * n + k] -= factor * m[col * n + k];
+            }
+            x[row] -= factor * x[col];
+        }
+    }
+
+    for col in (0..n).rev() {
+        let sum: f64 = ((col + 1)..n).map(|k| m[col * n + k] * x[k]).sum();
+        x[col] = (x[col] - sum) / m[col * n + col];
+    }
+
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &Mat3, b: &Mat3, tolerance: f64) {
+        for (row_a, row_b) in a.iter().zip(b.iter()) {
+            for (x, y) in row_a.iter().zip(row_b.iter()) {
+                assert!((x - y).abs() < tolerance, "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn quaternions_round_trip_rotations() {
+        for w in [[0.0, 0.0, 0.0], [0.3, -0.2, 0.1], [3.0, 0.1, 0.0], [0.0, -3.1, 0.2], [0.1, 0.0, 3.1]].iter() {
+            let rotation = rotation_from_axis_angle(*w);
+            assert_close(&rotation_from_quaternion(quaternion_from_rotation(&rotation)), &rotation, 1e-12);
+            assert!((determinant(&rotation) - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn inverse_and_orthonormalize() {
+        let m = [[2.0, 1.0, 0.0], [0.0, 3.0, 1.0], [1.0, 0.0, 1.0]];
+        assert_close(&mat_mul(&m, &inverse(&m).unwrap()), &IDENTITY, 1e-12);
+        assert!(inverse(&[[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [0.0, 0.0, 1.0]]).is_none());
+
+        let rotation = rotation_from_axis_angle([0.2, 0.4, -0.1]);
+        let noisy = [
+            [rotation[0][0] + 1e-3, rotation[0][1], rotation[0][2]],
+            rotation[1],
+            [rotation[2][0], rotation[2][1] - 1e-3, rotation[2][2]],
+        ];
+        let fixed = orthonormalize(&noisy);
+        assert_close(&mat_mul(&fixed, &transpose(&fixed)), &IDENTITY, 1e-12);
+        assert_close(&fixed, &rotation, 2e-3);
+    }
+
+    #[test]
+    fn solve_and_symmetric_eigen() {
+        let a = [4.0, 1.0, 0.0, 1.0, 3.0, 1.0, 0.0, 1.0, 2.0];
+        let x = solve(&a, &[1.0, 2.0, 3.0]).unwrap();
+        for row in 0..3 {
+            let b: f64 = (0..3).map(|k| a[row * 3 + k] * x[k]).sum();
+            assert!((b - [1.0, 2.0, 3.0][row]).abs() < 1e-12);
+        }
+        assert!(solve(&[1.0, 2.0, 2.0, 4.0], &[1.0, 1.0]).is_none());
+
+        let (values, vectors) = symmetric_eigen(&a, 3);
+        assert!(values.windows(2).all(|pair| pair[0] <= pair[1]));
+        for (value, vector) in values.iter().zip(vectors.iter()) {
+            for row in 0..3 {
+                let av: f64 = (0..3).map(|k| a[row * 3 + k] * vector[k]).sum();
+                assert!((av - value * vector[row]).abs() < 1e-9);
+            }
+        }
+    }
+}
@@ -0,0 +1,434 @@
+//! Exporting tracked skeletons as BVH motion capture and CSV files.
+
+use crate::error::MotionExportError;
+use crate::math::{self, Mat3};
+use crate::{Body, JointConfidenceLevel, JointId, Skeleton};
+
+use std::io::{BufWriter, Write};
+
+/// A body at a point in time, eg. one body of a `BodyFrame`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SkeletonSample {
+    pub device_timestamp_usec: u64,
+    pub body: Body,
+}
+
+/// Settings for `write_bvh`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BvhOptions {
+    /// Scale from millimeters to BVH units. The default of 0.1 writes centimeters.
+    pub units_per_mm: f32,
+    /// Convert from camera coordinates (y down, z forward) to y up, z backward, which is what
+    /// Blender and most animation tools expect.
+    pub y_up: bool,
+    /// The time between frames. By default, it's the median gap between sample timestamps.
+    pub frame_time_sec: Option<f32>,
+}
+
+impl Default for BvhOptions {
+    fn default() -> Self {
+        Self {
+            units_per_mm: 0.1,
+            y_up: true,
+            frame_time_sec: None,
+        }
+    }
+}
+
+/// Write the motion of one body as BVH, with one frame per sample.
+///
+/// The hierarchy is the Azure Kinect joint hierarchy rooted at the pelvis. Each joint's offset
+/// from its parent is its mean position in the parent joint's coordinate system across samples, and
+/// each frame holds the joint orientations relative to their parents, so the rest pose has every
+/// joint in the orientation the tracker reports for an unrotated joint rather than a T-pose.
+///
+/// BVH frames are evenly spaced, so samples should be consecutive frames. They must all be of the
+/// same body, otherwise `MotionExportError::MixedBodiesError` is returned.
+pub fn write_bvh<W: Write>(writer: W,
+                           samples: &[SkeletonSample],
+                           options: &BvhOptions) -> Result<(), MotionExportError>
+{
+    if samples.is_empty() {
+        return Err(MotionExportError::NoFramesError);
+    }
+    let expected = samples[0].body.id;
+    if let Some(sample) = samples.iter().find(|sample| sample.body.id != expected) {
+        return Err(MotionExportError::MixedBodiesError { expected, found: sample.body.id });
+    }
+
+    let mut writer = BufWriter::new(writer);
+    let scale = options.units_per_mm as f64;
+    let offsets = mean_offsets(samples);
+
+    writeln!(writer, "HIERARCHY")?;
+    write_joint(&mut writer, JointId::Pelvis, &offsets, scale, 0)?;
+
+    let frame_time = options.frame_time_sec.map(|time| time as f64).unwrap_or_else(|| median_frame_time(samples));
+    writeln!(writer, "MOTION")?;
+    writeln!(writer, "Frames: {}", samples.len())?;
+    writeln!(writer, "Frame Time: {:.6}", frame_time)?;
+
+    // Rotating 180 degrees about x turns camera coordinates y up. Only the root needs it, since
+    // every other rotation is relative to its parent.
+    let axes = if options.y_up { [[1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, -1.0]] } else { math::IDENTITY };
+    let order = hierarchy_order();
+
+    for sample in samples {
+        let skeleton = &sample.body.skeleton;
+        let rotations = joint_rotations(skeleton);
+
+        let root = skeleton.get_joint(JointId::Pelvis);
+        let position = math::scale(math::mat_vec(&axes, to_f64(root.position)), scale);
+        let mut values = vec![position[0], position[1], position[2]];
+
+        for joint in order.iter() {
+            let rotation = match joint.parent() {
+                Some(parent) => math::mat_mul(&math::transpose(&rotations[parent.index()]), &rotations[joint.index()]),
+                None => math::mat_mul(&axes, &rotations[joint.index()]),
+            };
+            values.extend(euler_zxy_degrees(&rotation).iter());
+        }
+
+        let line: Vec<String> = values.iter().map(|value| format!("{:.6}", value)).collect();
+        writeln!(writer, "{}", line.join(" "))?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write joint positions (in millimeters, camera coordinates) and confidence levels as CSV, with
+/// one row per sample.
+///
+/// Confidence levels are written as numbers: 0 (none), 1 (low), 2 (medium) and 3 (high).
+pub fn write_csv<W: Write>(writer: W, samples: &[SkeletonSample]) -> Result<(), MotionExportError> {
+    let mut writer = BufWriter::new(writer);
+
+    let mut header = vec!["device_timestamp_usec".to_string(), "body_id".to_string()];
+    for joint in JointId::ALL.iter() {
+        for column in ["x", "y", "z", "confidence"].iter() {
+            header.push(format!("{}_{}", joint.name(), column));
+        }
+    }
+    writeln!(writer, "{}", header.join(","))?;
+
+    for sample in samples {
+        write!(writer, "{},{}", sample.device_timestamp_usec, sample.body.id)?;
+        for joint in sample.body.skeleton.joints.iter() {
+            let [x, y, z] = joint.position;
+            let confidence = match joint.confidence_level {
+                JointConfidenceLevel::None => 0,
+                JointConfidenceLevel::Low => 1,
+                JointConfidenceLevel::Medium => 2,
+                JointConfidenceLevel::High => 3,
+            };
+            write!(writer, ",{},{},{},{}", x, y, z, confidence)?;
+        }
+        writeln!(writer)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_joint<W: Write>(writer: &mut W,
+                         joint: JointId,
+                         offsets: &[math::Vec3],
+                         scale: f64,
+                         depth: usize) -> Result<(), MotionExportError>
+{
+    let indent = "\t".repeat(depth);
+    let offset = math::scale(offsets[joint.index()], scale);
+
+    match joint.parent() {
+        None => {
+            writeln!(writer, "{}ROOT {}", indent, joint.name())?;
+            writeln!(writer, "{}{{", indent)?;
+            writeln!(writer, "{}\tOFFSET {:.6} {:.6} {:.6}", indent, offset[0], offset[1], offset[2])?;
+            writeln!(writer, "{}\tCHANNELS 6 Xposition Yposition Zposition Zrotation Xrotation Yrotation", indent)?;
+        },
+        Some(_) => {
+            writeln!(writer, "{}JOINT {}", indent, joint.name())?;
+            writeln!(writer, "{}{{", indent)?;
+            writeln!(writer, "{}\tOFFSET {:.6} {:.6} {:.6}", indent, offset[0], offset[1], offset[2])?;
+            writeln!(writer, "{}\tCHANNELS 3 Zrotation Xrotation Yrotation", indent)?;
+        },
+    }
+
+    let children = joint.children();
+    if children.is_empty() {
+        writeln!(writer, "{}\tEnd Site", indent)?;
+        writeln!(writer, "{}\t{{", indent)?;
+        writeln!(writer, "{}\t\tOFFSET 0.000000 0.000000 0.000000", indent)?;
+        writeln!(writer, "{}\t}}", indent)?;
+    }
+    for child in children {
+        write_joint(writer, child, offsets, scale, depth + 1)?;
+    }
+
+    writeln!(writer, "{}}}", indent)?;
+    Ok(())
+}
+
+/// Joints in the order their channels appear in BVH frames (depth first, like the hierarchy).
+fn hierarchy_order() -> Vec<JointId> {
+    let mut order = Vec::new();
+    let mut stack = vec![JointId::Pelvis];
+    while let Some(joint) = stack.pop() {
+        order.push(joint);
+        stack.extend(joint.children().into_iter().rev());
+    }
+    order
+}
+
+/// Each joint's offset from its parent in the parent's coordinate system, averaged over the samples
+/// where both joints were seen (or over every sample, if they never were).
+fn mean_offsets(samples: &[SkeletonSample]) -> Vec<math::Vec3> {
+    JointId::ALL.iter()
+        .map(|joint| {
+            let parent = match joint.parent() {
+                Some(parent) => parent,
+                None => return [0.0; 3],
+            };
+
+            let local_offset = |skeleton: &Skeleton| {
+                let rotation = math::rotation_from_quaternion(to_f64_4(skeleton.get_joint(parent).orientation));
+                let position = to_f64(skeleton.get_joint(*joint).position);
+                let parent_position = to_f64(skeleton.get_joint(parent).position);
+                math::mat_vec(&math::transpose(&rotation), math::add(position, math::scale(parent_position, -1.0)))
+            };
+
+            let seen: Vec<math::Vec3> = samples.iter()
+                .map(|sample| &sample.body.skeleton)
+                .filter(|skeleton| {
+                    skeleton.get_joint(*joint).confidence_level != JointConfidenceLevel::None
+                        && skeleton.get_joint(parent).confidence_level != JointConfidenceLevel::None
+                })
+                .map(local_offset)
+                .collect();
+
+            let offsets = if seen.is_empty() {
+                samples.iter().map(|sample| local_offset(&sample.body.skeleton)).collect()
+            } else {
+                seen
+            };
+
+            let sum = offsets.iter().fold([0.0; 3], |sum, offset| math::add(sum, *offset));
+            math::scale(sum, 1.0 / offsets.len() as f64)
+        })
+        .collect()
+}
+
+fn joint_rotations(skeleton: &Skeleton) -> Vec<Mat3> {
+    skeleton.joints.iter().map(|joint| math::rotation_from_quaternion(to_f64_4(joint.orientation))).collect()
+}
+
+/// Angles (z, x, y) in degrees, such that `rotation = Rz * Rx * Ry`.
+fn euler_zxy_degrees(rotation: &Mat3) -> [f64; 3] {
+    let x = rotation[2][1].clamp(-1.0, 1.0).asin();
+    let (z, y) = if rotation[2][1].abs() < 0.999_999 {
+        ((-rotation[0][1]).atan2(rotation[1][1]), (-rotation[2][0]).atan2(rotation[2][2]))
+    } else {
+        // Gimbal lock: only z + y (or z - y) is determined.
+        (rotation[1][0].atan2(rotation[0][0]), 0.0)
+    };
+    [z.to_degrees(), x.to_degrees(), y.to_degrees()]
+}
+
+fn median_frame_time(samples: &[SkeletonSample]) -> f64 {
+    let mut gaps: Vec<u64> = samples.windows(2)
+        .map(|pair| pair[1].device_timestamp_usec.saturating_sub(pair[0].device_timestamp_usec))
+        .filter(|gap| *gap > 0)
+        .collect();
+    if gaps.is_empty() {
+        return 1.0 / 30.0;
+    }
+    gaps.sort_unstable();
+    gaps[gaps.len() / 2] as f64 / 1_000_000.0
+}
+
+fn to_f64(v: [f32; 3]) -> math::Vec3 {
+    [v[0] as f64, v[1] as f64, v[2] as f64]
+}
+
+fn to_f64_4(q: [f32; 4]) -> [f64; 4] {
+    [q[0] as f64, q[1] as f64, q[2] as f64, q[3] as f64]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Joint, JOINT_COUNT};
+
+    const PELVIS_MM: [f32; 3] = [10.0, 20.0, 30.0];
+
+    /// A body with every joint unrotated, each a fixed step from the pelvis.
+    fn sample(device_timestamp_usec: u64, shift_mm: f32) -> SkeletonSample {
+        let mut joints = [Joint {
+            position: [0.0; 3],
+            orientation: [1.0, 0.0, 0.0, 0.0],
+            confidence_level: JointConfidenceLevel::High,
+        }; JOINT_COUNT];
+        for (index, joint) in joints.iter_mut().enumerate() {
+            let step = index as f32;
+            joint.position = [PELVIS_MM[0] + shift_mm + step, PELVIS_MM[1] + 2.0 * step, PELVIS_MM[2] - step];
+        }
+        joints[JointId::Head.index()].confidence_level = JointConfidenceLevel::Low;
+
+        SkeletonSample {
+            device_timestamp_usec,
+            body: Body { id: 7, skeleton: Skeleton { joints } },
+        }
+    }
+
+    fn bvh(samples: &[SkeletonSample], options: &BvhOptions) -> String {
+        let mut buffer = Vec::new();
+        write_bvh(&mut buffer, samples, options).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    fn frames(bvh: &str) -> Vec<Vec<f64>> {
+        bvh.lines()
+            .skip_while(|line| !line.starts_with("Frame Time:"))
+            .skip(1)
+            .map(|line| line.split(' ').map(|value| value.parse().unwrap()).collect())
+            .collect()
+    }
+
+    fn rotation_zxy_degrees(angles: [f64; 3]) -> Mat3 {
+        let [z, x, y] = [angles[0].to_radians(), angles[1].to_radians(), angles[2].to_radians()];
+        math::mat_mul(&math::rotation_from_axis_angle([0.0, 0.0, z]),
+                      &math::mat_mul(&math::rotation_from_axis_angle([x, 0.0, 0.0]),
+                                     &math::rotation_from_axis_angle([0.0, y, 0.0])))
+    }
+
+    fn assert_same_rotation(a: &Mat3, b: &Mat3) {
+        for (row_a, row_b) in a.iter().zip(b.iter()) {
+            for (value_a, value_b) in row_a.iter().zip(row_b.iter()) {
+                assert!((value_a - value_b).abs() < 1e-9, "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn bvh_hierarchy_has_every_joint_and_channel() {
+        let bvh = bvh(&[sample(0, 0.0)], &BvhOptions::default());
+        let lines: Vec<&str> = bvh.lines().map(str::trim).collect();
+
+        assert_eq!(lines[0], "HIERARCHY");
+        assert_eq!(lines[1], "ROOT Pelvis");
+        assert_eq!(lines.iter().filter(|line| line.starts_with("ROOT ")).count(), 1);
+        assert_eq!(lines.iter().filter(|line| line.starts_with("JOINT ")).count(), JOINT_COUNT - 1);
+
+        let leaves = JointId::ALL.iter().filter(|joint| joint.children().is_empty()).count();
+        assert_eq!(lines.iter().filter(|line| **line == "End Site").count(), leaves);
+
+        let channels: usize = lines.iter()
+            .filter_map(|line| line.strip_prefix("CHANNELS "))
+            .map(|channels| channels.split(' ').next().unwrap().parse::<usize>().unwrap())
+            .sum();
+        assert_eq!(channels, 6 + 3 * (JOINT_COUNT - 1));
+
+        // The joint's offset from its parent, in centimeters.
+        let offset = (JointId::Neck.index() - JointId::Neck.parent().unwrap().index()) as f64 * 0.1;
+        let joint_line = lines.iter().position(|line| *line == "JOINT Neck").unwrap();
+        assert_eq!(lines[joint_line + 2], format!("OFFSET {:.6} {:.6} {:.6}", offset, 2.0 * offset, -offset));
+
+        assert_eq!(lines.iter().filter(|line| line.starts_with("Frames: 1")).count(), 1);
+        let frames = frames(&bvh);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].len(), channels);
+    }
+
+    #[test]
+    fn bvh_root_translation_follows_y_up() {
+        let samples = [sample(0, 0.0), sample(33_333, 50.0)];
+
+        let frames_y_up = frames(&bvh(&samples, &BvhOptions::default()));
+        assert_eq!(frames_y_up[0][..3], [1.0, -2.0, -3.0]);
+        assert_eq!(frames_y_up[1][..3], [6.0, -2.0, -3.0]);
+
+        let camera = BvhOptions { y_up: false, units_per_mm: 1.0, ..BvhOptions::default() };
+        let frames_camera = frames(&bvh(&samples, &camera));
+        assert_eq!(frames_camera[1][..3], [60.0, 20.0, 30.0]);
+
+        // Only the root is turned y up; unrotated joints stay unrotated relative to their parents.
+        let root_y_up = rotation_zxy_degrees([frames_y_up[0][3], frames_y_up[0][4], frames_y_up[0][5]]);
+        assert_same_rotation(&root_y_up, &[[1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, -1.0]]);
+        assert!(frames_camera[0][3..].iter().all(|angle| angle.abs() < 1e-9));
+        assert!(frames_y_up[0][6..].iter().all(|angle| angle.abs() < 1e-9));
+    }
+
+    #[test]
+    fn euler_angles_round_trip() {
+        let angles = [
+            [0.0, 0.0, 0.0],
+            [30.0, -20.0, 45.0],
+            [-170.0, 60.0, 120.0],
+            [90.0, -89.0, -90.0],
+        ];
+        for angles in angles.iter() {
+            let rotation = rotation_zxy_degrees(*angles);
+            let recovered = euler_zxy_degrees(&rotation);
+            assert_same_rotation(&rotation_zxy_degrees(recovered), &rotation);
+            for (recovered, angle) in recovered.iter().zip(angles.iter()) {
+                assert!((recovered - angle).abs() < 1e-6, "{:?} != {:?}", recovered, angles);
+            }
+        }
+
+        // At x = +-90 degrees only z + y (or z - y) is determined, which is put into z.
+        for angles in [[10.0, 90.0, 20.0], [-40.0, -90.0, 25.0]].iter() {
+            let rotation = rotation_zxy_degrees(*angles);
+            let recovered = euler_zxy_degrees(&rotation);
+            assert_eq!(recovered[2], 0.0);
+            assert!((recovered[1] - angles[1]).abs() < 1e-6);
+            assert_same_rotation(&rotation_zxy_degrees(recovered), &rotation);
+        }
+    }
+
+    #[test]
+    fn frame_time_is_the_median_gap() {
+        let samples: Vec<SkeletonSample> = [0, 33_333, 66_667, 133_333, 133_333].iter()
+            .map(|timestamp| sample(*timestamp, 0.0))
+            .collect();
+        // Gaps of 33333, 33334 and 66666us; the repeated timestamp is ignored.
+        assert_eq!(median_frame_time(&samples), 0.033_334);
+        assert!(bvh(&samples, &BvhOptions::default()).contains("\nFrame Time: 0.033334\n"));
+
+        let fixed = BvhOptions { frame_time_sec: Some(0.5), ..BvhOptions::default() };
+        assert!(bvh(&samples, &fixed).contains("\nFrame Time: 0.500000\n"));
+
+        assert_eq!(median_frame_time(&samples[..1]), 1.0 / 30.0);
+        assert!(write_bvh(Vec::new(), &[], &BvhOptions::default()).is_err());
+    }
+
+    #[test]
+    fn bvh_rejects_samples_of_different_bodies() {
+        let mut other = sample(33_333, 0.0);
+        other.body.id = 8;
+        let samples = [sample(0, 0.0), other, sample(66_667, 0.0)];
+
+        match write_bvh(Vec::new(), &samples, &BvhOptions::default()) {
+            Err(MotionExportError::MixedBodiesError { expected, found }) => assert_eq!((expected, found), (7, 8)),
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn csv_has_a_column_per_joint_value() {
+        let mut buffer = Vec::new();
+        write_csv(&mut buffer, &[sample(1_000, 0.0), sample(34_333, 0.0)]).unwrap();
+        let csv = String::from_utf8(buffer).unwrap();
+        let rows: Vec<Vec<&str>> = csv.lines().map(|line| line.split(',').collect()).collect();
+
+        let columns = 2 + 4 * JOINT_COUNT;
+        assert_eq!(rows.len(), 3);
+        assert!(rows.iter().all(|row| row.len() == columns));
+        assert_eq!(rows[0][..6], ["device_timestamp_usec", "body_id", "Pelvis_x", "Pelvis_y", "Pelvis_z", "Pelvis_confidence"]);
+        assert_eq!(rows[2][..6], ["34333", "7", "10", "20", "30", "3"]);
+
+        let head = 2 + 4 * JointId::Head.index();
+        assert_eq!(rows[0][head + 3], "Head_confidence");
+        assert_eq!(rows[1][head + 3], "1");
+    }
+}
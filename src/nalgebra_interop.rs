@@ -0,0 +1,119 @@
+//! Conversions between calibration types and `nalgebra`'s, with the `nalgebra` feature.
+//!
+//! The calibration conversions (`Calibration::convert_2d_to_3d` and friends) also take and return
+//! `Point2<f32>`/`Point3<f32>` and `Vector2<f32>`/`Vector3<f32>`.
+
+use crate::calibration::{CalibrationPoint2, CalibrationPoint3};
+use crate::Extrinsics;
+use crate::Intrinsics;
+
+use ::nalgebra::{Isometry3, Matrix3, Point2, Point3, Rotation3, Translation3, UnitQuaternion, Vector2, Vector3};
+
+impl From<Extrinsics> for Isometry3<f32> {
+    fn from(extrinsics: Extrinsics) -> Self {
+        let rotation = Rotation3::from_matrix_unchecked(Matrix3::from_row_slice(&extrinsics.rotation));
+        let [x, y, z] = extrinsics.translation;
+        Isometry3::from_parts(Translation3::new(x, y, z), UnitQuaternion::from_rotation_matrix(&rotation))
+    }
+}
+
+impl From<Isometry3<f32>> for Extrinsics {
+    fn from(isometry: Isometry3<f32>) -> Self {
+        let rotation = isometry.rotation.to_rotation_matrix();
+        let matrix = rotation.matrix();
+        let translation = isometry.translation.vector;
+        Self {
+            rotation: [
+                matrix[(0, 0)], matrix[(0, 1)], matrix[(0, 2)],
+                matrix[(1, 0)], matrix[(1, 1)], matrix[(1, 2)],
+                matrix[(2, 0)], matrix[(2, 1)], matrix[(2, 2)],
+            ],
+            translation: [translation.x, translation.y, translation.z],
+        }
+    }
+}
+
+/// The camera matrix. Distortion is left out.
+impl From<Intrinsics> for Matrix3<f32> {
+    fn from(intrinsics: Intrinsics) -> Self {
+        Matrix3::from_row_slice(&intrinsics.camera_matrix())
+    }
+}
+
+impl CalibrationPoint2 for Point2<f32> {
+    type Point3 = Point3<f32>;
+
+    fn to_array(self) -> [f32; 2] {
+        [self.x, self.y]
+    }
+
+    fn from_array([x, y]: [f32; 2]) -> Self {
+        Point2::new(x, y)
+    }
+}
+
+impl CalibrationPoint3 for Point3<f32> {
+    type Point2 = Point2<f32>;
+
+    fn to_array(self) -> [f32; 3] {
+        [self.x, self.y, self.z]
+    }
+
+    fn from_array([x, y, z]: [f32; 3]) -> Self {
+        Point3::new(x, y, z)
+    }
+}
+
+impl CalibrationPoint2 for Vector2<f32> {
+    type Point3 = Vector3<f32>;
+
+    fn to_array(self) -> [f32; 2] {
+        [self.x, self.y]
+    }
+
+    fn from_array([x, y]: [f32; 2]) -> Self {
+        Vector2::new(x, y)
+    }
+}
+
+impl CalibrationPoint3 for Vector3<f32> {
+    type Point2 = Vector2<f32>;
+
+    fn to_array(self) -> [f32; 3] {
+        [self.x, self.y, self.z]
+    }
+
+    fn from_array([x, y, z]: [f32; 3]) -> Self {
+        Vector3::new(x, y, z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calibration::tests::{assert_close, extrinsics, intrinsics, transform};
+
+    #[test]
+    fn isometries_transform_like_extrinsics() {
+        let isometry = Isometry3::from(extrinsics());
+        let point = isometry * Point3::new(1.0, 2.0, 3.0);
+        assert_close([point.x, point.y, point.z], transform(&extrinsics(), [1.0, 2.0, 3.0]));
+    }
+
+    #[test]
+    fn extrinsics_round_trip_through_isometries() {
+        let round_tripped = Extrinsics::from(Isometry3::from(extrinsics()));
+        for (actual, expected) in round_tripped.rotation.iter().zip(&extrinsics().rotation) {
+            assert!((actual - expected).abs() < 1e-6, "{:?}", round_tripped);
+        }
+        assert_eq!(round_tripped.translation, extrinsics().translation);
+    }
+
+    #[test]
+    fn camera_matrices_have_fx_and_cx_in_the_first_row() {
+        let matrix = Matrix3::from(intrinsics());
+        assert_eq!((matrix[(0, 0)], matrix[(0, 1)], matrix[(0, 2)]), (500.0, 0.0, 320.0));
+        assert_eq!((matrix[(1, 1)], matrix[(1, 2)]), (510.0, 240.0));
+        assert_eq!(matrix.row(2).iter().copied().collect::<Vec<_>>(), vec![0.0, 0.0, 1.0]);
+    }
+}
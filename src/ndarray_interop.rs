@@ -0,0 +1,133 @@
+//! `ndarray` views of image data, with the `ndarray` feature.
+//!
+//! Views borrow the libk4a buffer and step over any padding at the end of rows, so they're free
+//! to make. Point cloud images are copied, since their samples are only two-byte aligned.
+
+use crate::error::ImageConversionError;
+use crate::Image;
+use crate::ImageFormat;
+
+use ::ndarray::{Array3, ArrayView2, ArrayView3, ShapeBuilder};
+use std::mem;
+use std::slice;
+
+impl Image {
+    /// View a `Depth16`, `Ir16` or `Custom16` image as a height × width array.
+    pub fn as_array_view_u16(&self) -> Result<ArrayView2<'_, u16>, ImageConversionError> {
+        match self.get_format() {
+            ImageFormat::Depth16 | ImageFormat::Ir16 | ImageFormat::Custom16 => {},
+            format => return Err(ImageConversionError::UnsupportedImageFormatError(format)),
+        }
+
+        let width = self.get_width_pixels();
+        let height = self.get_height_pixels();
+        let stride = self.get_stride_bytes();
+        let data = self.get_data();
+
+        let sample_size = mem::size_of::<u16>();
+        let row_step = stride / sample_size;
+        if data.as_ptr().align_offset(mem::align_of::<u16>()) != 0 || row_step * sample_size != stride {
+            return Err(ImageConversionError::UnalignedDataError);
+        }
+        let samples = unsafe {
+            slice::from_raw_parts(data.as_ptr() as *const u16, data.len() / sample_size)
+        };
+
+        Ok(ArrayView2::from_shape((height, width).strides((row_step, 1)), samples)
+            .expect("a buffer of height rows of stride bytes"))
+    }
+
+    /// View a `ColorBgra32` image as a height × width × 4 array, with channels in BGRA order.
+    pub fn as_array_view_bgra(&self) -> Result<ArrayView3<'_, u8>, ImageConversionError> {
+        match self.get_format() {
+            ImageFormat::ColorBgra32 => {},
+            format => return Err(ImageConversionError::UnsupportedImageFormatError(format)),
+        }
+
+        let width = self.get_width_pixels();
+        let height = self.get_height_pixels();
+        let stride = self.get_stride_bytes();
+
+        Ok(ArrayView3::from_shape((height, width, 4).strides((stride, 4, 1)), self.get_data())
+            .expect("a buffer of height rows of stride bytes"))
+    }
+
+    /// Copy a point cloud image, as produced by `Transformation::depth_image_to_point_cloud`, to
+    /// a height × width × 3 array of x, y and z in millimeters.
+    pub fn to_point_cloud_array(&self) -> Result<Array3<i16>, ImageConversionError> {
+        let width = self.get_width_pixels();
+        let height = self.get_height_pixels();
+        let stride = self.get_stride_bytes();
+
+        match self.get_format() {
+            ImageFormat::Custom if stride >= width * 6 => {},
+            format => return Err(ImageConversionError::UnsupportedImageFormatError(format)),
+        }
+
+        let data = self.get_data();
+        let mut points = Vec::with_capacity(width * height * 3);
+        for y in 0..height {
+            for sample in data[y * stride..y * stride + width * 6].chunks_exact(2) {
+                points.push(i16::from_le_bytes([sample[0], sample[1]]));
+            }
+        }
+
+        Ok(Array3::from_shape_vec((height, width, 3), points).expect("a buffer of height * width * 3 samples"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::tests::padded_image;
+
+    fn le_bytes(samples: &[i32]) -> Vec<u8> {
+        samples.iter().flat_map(|sample| (*sample as u16).to_le_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn u16_views_skip_row_padding() {
+        let image = padded_image(ImageFormat::Depth16, 3, 2, 2, 4, &le_bytes(&[1, 2, 3, 4, 5, 6]));
+        let view = image.as_array_view_u16().unwrap();
+
+        assert_eq!(view.shape(), &[2, 3]);
+        // Rows are 10 bytes, or 5 samples, apart.
+        assert_eq!(view.strides(), &[5, 1]);
+        assert_eq!(view[[1, 2]], 6);
+        assert_eq!(view[[0, 1]], 2);
+        assert_eq!(view.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn bgra_views_index_by_row_column_and_channel() {
+        let bgra: Vec<u8> = (0..24).collect();
+        let image = padded_image(ImageFormat::ColorBgra32, 3, 2, 4, 4, &bgra);
+        let view = image.as_array_view_bgra().unwrap();
+
+        assert_eq!(view.shape(), &[2, 3, 4]);
+        assert_eq!(view.strides(), &[16, 4, 1]);
+        // The pixel at x = 1, y = 1 is the fifth of the buffer.
+        assert_eq!(view.slice(::ndarray::s![1, 1, ..]).to_vec(), vec![16, 17, 18, 19]);
+        assert_eq!(view[[0, 2, 3]], 11);
+    }
+
+    #[test]
+    fn point_cloud_arrays_hold_signed_millimeters() {
+        let samples = [-100, 200, 1000, 0, 0, 0, 5, -6, 7, 300, -400, 2500];
+        let image = padded_image(ImageFormat::Custom, 2, 2, 6, 2, &le_bytes(&samples));
+        let points = image.to_point_cloud_array().unwrap();
+
+        assert_eq!(points.shape(), &[2, 2, 3]);
+        assert_eq!(points.slice(::ndarray::s![0, 0, ..]).to_vec(), vec![-100, 200, 1000]);
+        assert_eq!(points.slice(::ndarray::s![1, 1, ..]).to_vec(), vec![300, -400, 2500]);
+        assert_eq!(points[[1, 0, 1]], -6);
+    }
+
+    #[test]
+    fn other_formats_are_rejected() {
+        let image = Image::create(ImageFormat::Custom8, 4, 2, 0).unwrap().into_image();
+        assert!(matches!(image.as_array_view_u16(), Err(ImageConversionError::UnsupportedImageFormatError(_))));
+        assert!(matches!(image.as_array_view_bgra(), Err(ImageConversionError::UnsupportedImageFormatError(_))));
+        assert!(matches!(image.to_point_cloud_array(), Err(ImageConversionError::UnsupportedImageFormatError(_))));
+    }
+}
@@ -0,0 +1,126 @@
+use crate::Image;
+use crate::ImageFormat;
+
+/// A set of 3D points, in millimeters, with optional per-point colors and normals.
+///
+/// Clouds made from point cloud images are organized: points are stored row by row in the layout
+/// of the depth image, which is `width` by `height`. Pixels without a depth reading keep their
+/// place in the grid as invalid points at (0, 0, 0). Unorganized clouds have a `height` of 1.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PointCloud {
+    pub width: usize,
+    pub height: usize,
+    /// Point coordinates (x, y, z) in millimeters.
+    pub points: Vec<[f32; 3]>,
+    /// Per-point colors (red, green, blue), if any.
+    pub colors: Option<Vec<[u8; 3]>>,
+    /// Per-point unit normals, if any.
+    pub normals: Option<Vec<[f32; 3]>>,
+}
+
+impl PointCloud {
+    /// Create an unorganized cloud from points.
+    pub fn from_points(points: Vec<[f32; 3]>) -> Self {
+        Self {
+            width: points.len(),
+            height: 1,
+            points,
+            colors: None,
+            normals: None,
+        }
+    }
+
+    /// Create an organized cloud from a point cloud image, as produced by
+    /// `Transformation::depth_image_to_point_cloud`.
+    ///
+    /// Returns None if the image isn't a `Custom` image of three int16 values per pixel.
+    pub fn from_point_cloud_image(image: &Image) -> Option<Self> {
+        let width = image.get_width_pixels();
+        let height = image.get_height_pixels();
+        let stride = image.get_stride_bytes();
+
+        match image.get_format() {
+            ImageFormat::Custom if stride >= width * 6 => {},
+            _ => return None,
+        }
+
+        let data = image.get_data();
+        let mut points = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            let row = &data[y * stride..y * stride + width * 6];
+            for pixel in row.chunks_exact(6) {
+                points.push([
+                    i16::from_le_bytes([pixel[0], pixel[1]]) as f32,
+                    i16::from_le_bytes([pixel[2], pixel[3]]) as f32,
+                    i16::from_le_bytes([pixel[4], pixel[5]]) as f32,
+                ]);
+            }
+        }
+
+        Some(Self {
+            width,
+            height,
+            points,
+            colors: None,
+            normals: None,
+        })
+    }
+
+    /// Color an organized cloud from a BGRA32 image of the same size, eg. as produced by
+    /// `Transformation::color_image_to_depth_camera`.
+    ///
+    /// Returns false (leaving the cloud uncolored) if the image doesn't match the cloud.
+    pub fn set_colors_from_image(&mut self, image: &Image) -> bool {
+        let width = image.get_width_pixels();
+        let height = image.get_height_pixels();
+        let stride = image.get_stride_bytes();
+
+        match image.get_format() {
+            ImageFormat::ColorBgra32 if width == self.width && height == self.height => {},
+            _ => return false,
+        }
+
+        let data = image.get_data();
+        let mut colors = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            let row = &data[y * stride..y * stride + width * 4];
+            for pixel in row.chunks_exact(4) {
+                colors.push([pixel[2], pixel[1], pixel[0]]);
+            }
+        }
+
+        self.colors = Some(colors);
+        true
+    }
+
+    /// The number of points, including invalid points.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Whether the cloud keeps the grid layout of a depth image.
+    pub fn is_organized(&self) -> bool {
+        self.height > 1
+    }
+
+    /// Whether the point at `index` holds a measurement, as opposed to a placeholder at (0, 0, 0)
+    /// or a non-finite value.
+    pub fn is_valid(&self, index: usize) -> bool {
+        is_valid_point(&self.points[index])
+    }
+
+    /// The number of valid points.
+    pub fn valid_count(&self) -> usize {
+        self.points.iter().filter(|point| is_valid_point(point)).count()
+    }
+}
+
+pub(crate) fn is_valid_point(point: &[f32; 3]) -> bool {
+    point.iter().all(|v| v.is_finite()) && point.iter().any(|v| *v != 0.0)
+}
@@ -0,0 +1,815 @@
+//! Reading and writing point clouds as PLY, PCD and XYZ files.
+//!
+//! PLY and XYZ files hold only the valid points of a cloud, so clouds read from them are
+//! unorganized. PCD files keep the grid layout of organized clouds and mark invalid points with
+//! NaN coordinates, following PCL; they are read back as invalid points at (0, 0, 0).
+
+use crate::error::PointCloudIoError;
+use crate::point_cloud::is_valid_point;
+use crate::PointCloud;
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// The most points to allocate for before reading them.
+const MAX_RESERVED_POINTS: usize = 1 << 20;
+
+/// Encoding of the vertex data in a PLY file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+/// Encoding of the point data in a PCD file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PcdFormat {
+    Ascii,
+    Binary,
+}
+
+impl PointCloud {
+    /// Write the valid points as PLY, with colors and normals if present.
+    pub fn write_ply<W: Write>(&self, writer: W, format: PlyFormat) -> Result<(), PointCloudIoError> {
+        let mut writer = BufWriter::new(writer);
+        let indices = self.valid_indices();
+
+        writeln!(writer, "ply")?;
+        match format {
+            PlyFormat::Ascii => writeln!(writer, "format ascii 1.0")?,
+            PlyFormat::BinaryLittleEndian => writeln!(writer, "format binary_little_endian 1.0")?,
+        }
+        writeln!(writer, "element vertex {}", indices.len())?;
+        writeln!(writer, "property float x\nproperty float y\nproperty float z")?;
+        if self.normals.is_some() {
+            writeln!(writer, "property float nx\nproperty float ny\nproperty float nz")?;
+        }
+        if self.colors.is_some() {
+            writeln!(writer, "property uchar red\nproperty uchar green\nproperty uchar blue")?;
+        }
+        writeln!(writer, "end_header")?;
+
+        for i in indices {
+            match format {
+                PlyFormat::Ascii => {
+                    let [x, y, z] = self.points[i];
+                    write!(writer, "{} {} {}", x, y, z)?;
+                    if let Some(normals) = &self.normals {
+                        let [nx, ny, nz] = normals[i];
+                        write!(writer, " {} {} {}", nx, ny, nz)?;
+                    }
+                    if let Some(colors) = &self.colors {
+                        let [r, g, b] = colors[i];
+                        write!(writer, " {} {} {}", r, g, b)?;
+                    }
+                    writeln!(writer)?;
+                },
+                PlyFormat::BinaryLittleEndian => {
+                    write_f32s(&mut writer, &self.points[i])?;
+                    if let Some(normals) = &self.normals {
+                        write_f32s(&mut writer, &normals[i])?;
+                    }
+                    if let Some(colors) = &self.colors {
+                        writer.write_all(&colors[i])?;
+                    }
+                },
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read the vertices of a PLY file, in ASCII or binary little endian format.
+    ///
+    /// Vertices need `x`, `y` and `z` properties; `red`, `green` and `blue` and `nx`, `ny` and `nz`
+    /// are read when present. Other properties and elements are ignored.
+    pub fn read_ply<R: Read>(reader: R) -> Result<PointCloud, PointCloudIoError> {
+        let mut reader = BufReader::new(reader);
+
+        let mut line = String::new();
+        if read_header_line(&mut reader, &mut line)? != "ply" {
+            return Err(PointCloudIoError::ParseError("missing PLY magic number".to_string()));
+        }
+
+        let mut format = None;
+        let mut vertex_count = None;
+        let mut vertex_properties = Vec::new();
+        let mut in_vertex_element = false;
+
+        loop {
+            let header = read_header_line(&mut reader, &mut line)?;
+            let tokens: Vec<&str> = header.split_whitespace().collect();
+
+            match tokens.as_slice() {
+                ["end_header"] => break,
+                ["format", "ascii", _] => format = Some(PlyFormat::Ascii),
+                ["format", "binary_little_endian", _] => format = Some(PlyFormat::BinaryLittleEndian),
+                ["format", other, _] =>
+                    return Err(PointCloudIoError::UnsupportedError(format!("PLY format {}", other))),
+                ["comment", ..] | ["obj_info", ..] | [] => {},
+                ["element", name, count] => {
+                    // Elements after the vertices are never read, so only earlier ones matter.
+                    if vertex_count.is_none() && *name != "vertex" {
+                        return Err(PointCloudIoError::UnsupportedError(
+                            format!("PLY element {} before vertices", name)));
+                    }
+                    in_vertex_element = *name == "vertex";
+                    if in_vertex_element {
+                        vertex_count = Some(parse_token::<usize>(count)?);
+                    }
+                },
+                ["property", "list", ..] if in_vertex_element =>
+                    return Err(PointCloudIoError::UnsupportedError("PLY vertex list properties".to_string())),
+                ["property", data_type, name] if in_vertex_element => {
+                    let data_type = ScalarType::from_ply_name(data_type).ok_or_else(|| {
+                        PointCloudIoError::ParseError(format!("unknown PLY property type {}", data_type))
+                    })?;
+                    vertex_properties.push((data_type, name.to_string()));
+                },
+                ["property", ..] => {},
+                _ => return Err(PointCloudIoError::ParseError(format!("unexpected PLY header line: {}", header))),
+            }
+        }
+
+        let format = format.ok_or_else(|| PointCloudIoError::ParseError("missing PLY format".to_string()))?;
+        let vertex_count = vertex_count
+            .ok_or_else(|| PointCloudIoError::ParseError("missing PLY vertex element".to_string()))?;
+        let layout = FieldLayout::new(vertex_properties.iter().map(|(data_type, name)| (*data_type, 1, name.as_str())))?;
+        let value_types: Vec<ScalarType> = vertex_properties.iter().map(|(data_type, _)| *data_type).collect();
+
+        let mut cloud = layout.empty_cloud(vertex_count);
+        let mut values = vec![0.0; vertex_properties.len()];
+
+        for _ in 0..vertex_count {
+            match format {
+                PlyFormat::Ascii => read_ascii_values(&mut reader, &mut line, &mut values)?,
+                PlyFormat::BinaryLittleEndian => read_binary_values(&mut reader, &value_types, None, &mut values)?,
+            }
+            layout.push_point(&mut cloud, &values);
+        }
+
+        cloud.width = cloud.points.len();
+        cloud.height = 1;
+        Ok(cloud)
+    }
+
+    /// Write the cloud as PCD v0.7, keeping the layout of organized clouds.
+    ///
+    /// Invalid points are written with NaN coordinates. Colors are packed into a single `rgb`
+    /// float field, as PCL does.
+    pub fn write_pcd<W: Write>(&self, writer: W, format: PcdFormat) -> Result<(), PointCloudIoError> {
+        let mut writer = BufWriter::new(writer);
+
+        let mut fields = vec!["x", "y", "z"];
+        if self.normals.is_some() {
+            fields.extend(["normal_x", "normal_y", "normal_z"]);
+        }
+        if self.colors.is_some() {
+            fields.push("rgb");
+        }
+
+        writeln!(writer, "# .PCD v0.7 - Point Cloud Data file format")?;
+        writeln!(writer, "VERSION 0.7")?;
+        writeln!(writer, "FIELDS {}", fields.join(" "))?;
+        writeln!(writer, "SIZE {}", vec!["4"; fields.len()].join(" "))?;
+        writeln!(writer, "TYPE {}", vec!["F"; fields.len()].join(" "))?;
+        writeln!(writer, "COUNT {}", vec!["1"; fields.len()].join(" "))?;
+        writeln!(writer, "WIDTH {}", self.width)?;
+        writeln!(writer, "HEIGHT {}", self.height)?;
+        writeln!(writer, "VIEWPOINT 0 0 0 1 0 0 0")?;
+        writeln!(writer, "POINTS {}", self.points.len())?;
+        match format {
+            PcdFormat::Ascii => writeln!(writer, "DATA ascii")?,
+            PcdFormat::Binary => writeln!(writer, "DATA binary")?,
+        }
+
+        for (i, point) in self.points.iter().enumerate() {
+            let point = if is_valid_point(point) { *point } else { [f32::NAN; 3] };
+            let rgb = self.colors.as_ref().map(|colors| {
+                let [r, g, b] = colors[i];
+                f32::from_bits((r as u32) << 16 | (g as u32) << 8 | b as u32)
+            });
+
+            match format {
+                PcdFormat::Ascii => {
+                    if point[0].is_nan() {
+                        write!(writer, "nan nan nan")?;
+                    } else {
+                        write!(writer, "{} {} {}", point[0], point[1], point[2])?;
+                    }
+                    if let Some(normals) = &self.normals {
+                        let [nx, ny, nz] = normals[i];
+                        write!(writer, " {} {} {}", nx, ny, nz)?;
+                    }
+                    if let Some(rgb) = rgb {
+                        // PCL writes packed colors as integers in ASCII data.
+                        write!(writer, " {}", rgb.to_bits())?;
+                    }
+                    writeln!(writer)?;
+                },
+                PcdFormat::Binary => {
+                    write_f32s(&mut writer, &point)?;
+                    if let Some(normals) = &self.normals {
+                        write_f32s(&mut writer, &normals[i])?;
+                    }
+                    if let Some(rgb) = rgb {
+                        write_f32s(&mut writer, &[rgb])?;
+                    }
+                },
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read a PCD file with ASCII or binary data.
+    ///
+    /// Points need `x`, `y` and `z` fields; `rgb` (or `rgba`) and `normal_x`, `normal_y` and
+    /// `normal_z` are read when present. Other fields are ignored.
+    pub fn read_pcd<R: Read>(reader: R) -> Result<PointCloud, PointCloudIoError> {
+        let mut reader = BufReader::new(reader);
+        let mut line = String::new();
+
+        let mut names: Vec<String> = Vec::new();
+        let mut sizes: Vec<usize> = Vec::new();
+        let mut types: Vec<char> = Vec::new();
+        let mut counts: Vec<usize> = Vec::new();
+        let mut width = None;
+        let mut height = 1;
+        let mut point_count = None;
+
+        let format = loop {
+            let header = read_header_line(&mut reader, &mut line)?;
+            let mut tokens = header.split_whitespace();
+            let keyword = tokens.next().unwrap_or("");
+
+            match keyword {
+                "" => {},
+                _ if keyword.starts_with('#') => {},
+                "VERSION" | "VIEWPOINT" => {},
+                "FIELDS" => names = tokens.map(str::to_string).collect(),
+                "SIZE" => sizes = tokens.map(parse_token).collect::<Result<_, _>>()?,
+                "TYPE" => types = tokens.filter_map(|token| token.chars().next()).collect(),
+                "COUNT" => counts = tokens.map(parse_token).collect::<Result<_, _>>()?,
+                "WIDTH" => width = Some(parse_token::<usize>(tokens.next().unwrap_or(""))?),
+                "HEIGHT" => height = parse_token(tokens.next().unwrap_or(""))?,
+                "POINTS" => point_count = Some(parse_token::<usize>(tokens.next().unwrap_or(""))?),
+                "DATA" => match tokens.next() {
+                    Some("ascii") => break PcdFormat::Ascii,
+                    Some("binary") => break PcdFormat::Binary,
+                    Some(other) =>
+                        return Err(PointCloudIoError::UnsupportedError(format!("PCD data {}", other))),
+                    None => return Err(PointCloudIoError::ParseError("missing PCD data type".to_string())),
+                },
+                _ => return Err(PointCloudIoError::ParseError(format!("unexpected PCD header line: {}", header))),
+            }
+        };
+
+        if counts.is_empty() {
+            counts = vec![1; names.len()];
+        }
+        if sizes.len() != names.len() || types.len() != names.len() || counts.len() != names.len() {
+            return Err(PointCloudIoError::ParseError("PCD field descriptions don't match".to_string()));
+        }
+
+        let field_types = sizes.iter().zip(&types)
+            .map(|(size, type_char)| {
+                ScalarType::from_pcd(*type_char, *size).ok_or_else(|| {
+                    PointCloudIoError::ParseError(format!("unknown PCD field type {}{}", type_char, size))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let width = width.ok_or_else(|| PointCloudIoError::ParseError("missing PCD width".to_string()))?;
+        let size = width.checked_mul(height).ok_or_else(|| {
+            PointCloudIoError::ParseError(format!("PCD size {} by {} is too large", width, height))
+        })?;
+        let point_count = point_count.unwrap_or(size);
+        if point_count != size {
+            return Err(PointCloudIoError::ParseError(format!(
+                "PCD has {} points but is {} by {}", point_count, width, height)));
+        }
+
+        let layout = FieldLayout::new(field_types.iter().zip(&counts).zip(&names)
+            .map(|((data_type, count), name)| (*data_type, *count, name.as_str())))?;
+
+        // Each value of a field with a count above 1 is read separately.
+        let value_types: Vec<ScalarType> = field_types.iter().zip(&counts)
+            .flat_map(|(data_type, count)| (0..*count).map(move |_| *data_type))
+            .collect();
+        let packed_color = match layout.color {
+            Some(ColorLayout::Packed(index)) => Some(index),
+            _ => None,
+        };
+
+        let mut cloud = layout.empty_cloud(point_count);
+        let mut values = vec![0.0; value_types.len()];
+
+        for _ in 0..point_count {
+            match format {
+                PcdFormat::Ascii => read_ascii_values(&mut reader, &mut line, &mut values)?,
+                PcdFormat::Binary => read_binary_values(&mut reader, &value_types, packed_color, &mut values)?,
+            }
+            layout.push_point(&mut cloud, &values);
+        }
+
+        for point in cloud.points.iter_mut() {
+            if !is_valid_point(point) {
+                *point = [0.0; 3];
+            }
+        }
+
+        cloud.width = width;
+        cloud.height = height;
+        Ok(cloud)
+    }
+
+    /// Write the valid points as plain text, one point per line: `x y z`, followed by `r g b`
+    /// if the cloud has colors and `nx ny nz` if it has normals. A `# x y z ...` header comment
+    /// names the columns.
+    pub fn write_xyz<W: Write>(&self, writer: W) -> Result<(), PointCloudIoError> {
+        let mut writer = BufWriter::new(writer);
+
+        write!(writer, "# x y z")?;
+        if self.colors.is_some() {
+            write!(writer, " r g b")?;
+        }
+        if self.normals.is_some() {
+            write!(writer, " nx ny nz")?;
+        }
+        writeln!(writer)?;
+
+        for i in self.valid_indices() {
+            let [x, y, z] = self.points[i];
+            write!(writer, "{} {} {}", x, y, z)?;
+            if let Some(colors) = &self.colors {
+                let [r, g, b] = colors[i];
+                write!(writer, " {} {} {}", r, g, b)?;
+            }
+            if let Some(normals) = &self.normals {
+                let [nx, ny, nz] = normals[i];
+                write!(writer, " {} {} {}", nx, ny, nz)?;
+            }
+            writeln!(writer)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read points written by `write_xyz`.
+    ///
+    /// The columns are named by a `# x y z ...` header comment before the first point, where `r`,
+    /// `g` and `b` are colors and `nx`, `ny` and `nz` are normals; other columns are ignored.
+    /// Without a header, lines have 3 columns (`x y z`), 6 (`x y z nx ny nz`, as MeshLab writes
+    /// them) or 9 (`x y z r g b nx ny nz`).
+    pub fn read_xyz<R: Read>(reader: R) -> Result<PointCloud, PointCloudIoError> {
+        let reader = BufReader::new(reader);
+        let mut cloud = PointCloud::default();
+        let mut layout: Option<XyzLayout> = None;
+
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.is_empty() {
+                continue;
+            }
+            if let Some(comment) = line.trim_start().strip_prefix('#') {
+                if layout.is_none() {
+                    layout = XyzLayout::from_header(comment);
+                }
+                continue;
+            }
+
+            if layout.is_none() {
+                layout = Some(XyzLayout::from_column_count(tokens.len()).ok_or_else(|| {
+                    PointCloudIoError::ParseError(format!(
+                        "line {} has {} columns, expected 3, 6 or 9", number + 1, tokens.len()))
+                })?);
+            }
+            let layout = layout.expect("the layout was just set");
+            if tokens.len() != layout.columns {
+                return Err(PointCloudIoError::ParseError(format!(
+                    "line {} has {} columns, expected {}", number + 1, tokens.len(), layout.columns)));
+            }
+
+            let pick = |indices: [usize; 3]| [tokens[indices[0]], tokens[indices[1]], tokens[indices[2]]];
+
+            cloud.points.push(parse_vector(&pick(layout.position))?);
+            if let Some(indices) = layout.color {
+                let color = parse_color(&pick(indices)).ok_or_else(|| {
+                    PointCloudIoError::ParseError(format!("line {} has invalid colors", number + 1))
+                })?;
+                cloud.colors.get_or_insert_with(Vec::new).push(color);
+            }
+            if let Some(indices) = layout.normal {
+                cloud.normals.get_or_insert_with(Vec::new).push(parse_vector(&pick(indices))?);
+            }
+        }
+
+        cloud.width = cloud.points.len();
+        cloud.height = 1;
+        Ok(cloud)
+    }
+
+    /// Save to a file, choosing the format by extension: binary `.ply`, binary `.pcd` or `.xyz`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), PointCloudIoError> {
+        let path = path.as_ref();
+        match extension(path).as_deref() {
+            Some("ply") => self.write_ply(File::create(path)?, PlyFormat::BinaryLittleEndian),
+            Some("pcd") => self.write_pcd(File::create(path)?, PcdFormat::Binary),
+            Some("xyz") => self.write_xyz(File::create(path)?),
+            _ => Err(PointCloudIoError::UnsupportedError(format!("file extension of {}", path.display()))),
+        }
+    }
+
+    /// Load from a file, choosing the format by extension: `.ply`, `.pcd` or `.xyz`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<PointCloud, PointCloudIoError> {
+        let path = path.as_ref();
+        match extension(path).as_deref() {
+            Some("ply") => Self::read_ply(File::open(path)?),
+            Some("pcd") => Self::read_pcd(File::open(path)?),
+            Some("xyz") => Self::read_xyz(File::open(path)?),
+            _ => Err(PointCloudIoError::UnsupportedError(format!("file extension of {}", path.display()))),
+        }
+    }
+
+    fn valid_indices(&self) -> Vec<usize> {
+        (0..self.points.len()).filter(|i| self.is_valid(*i)).collect()
+    }
+}
+
+/// Which columns of an XYZ file hold what.
+#[derive(Debug, Copy, Clone)]
+struct XyzLayout {
+    columns: usize,
+    position: [usize; 3],
+    color: Option<[usize; 3]>,
+    normal: Option<[usize; 3]>,
+}
+
+impl XyzLayout {
+    /// The layout named by a header comment (without its `#`), if it names the coordinates.
+    fn from_header(header: &str) -> Option<Self> {
+        let names: Vec<&str> = header.split_whitespace().collect();
+        let find = |group: [&str; 3]| -> Option<[usize; 3]> {
+            let mut indices = [0; 3];
+            for (index, name) in indices.iter_mut().zip(group.iter()) {
+                *index = names.iter().position(|column| column == name)?;
+            }
+            Some(indices)
+        };
+
+        Some(Self {
+            columns: names.len(),
+            position: find(["x", "y", "z"])?,
+            color: find(["r", "g", "b"]),
+            normal: find(["nx", "ny", "nz"]),
+        })
+    }
+
+    fn from_column_count(columns: usize) -> Option<Self> {
+        let (color, normal) = match columns {
+            3 => (None, None),
+            6 => (None, Some([3, 4, 5])),
+            9 => (Some([3, 4, 5]), Some([6, 7, 8])),
+            _ => return None,
+        };
+        Some(Self { columns, position: [0, 1, 2], color, normal })
+    }
+}
+
+/// A scalar type of a PLY property or PCD field.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn from_ply_name(name: &str) -> Option<Self> {
+        match name {
+            "char" | "int8" => Some(ScalarType::I8),
+            "uchar" | "uint8" => Some(ScalarType::U8),
+            "short" | "int16" => Some(ScalarType::I16),
+            "ushort" | "uint16" => Some(ScalarType::U16),
+            "int" | "int32" => Some(ScalarType::I32),
+            "uint" | "uint32" => Some(ScalarType::U32),
+            "float" | "float32" => Some(ScalarType::F32),
+            "double" | "float64" => Some(ScalarType::F64),
+            _ => None,
+        }
+    }
+
+    fn from_pcd(type_char: char, size: usize) -> Option<Self> {
+        match (type_char, size) {
+            ('I', 1) => Some(ScalarType::I8),
+            ('U', 1) => Some(ScalarType::U8),
+            ('I', 2) => Some(ScalarType::I16),
+            ('U', 2) => Some(ScalarType::U16),
+            ('I', 4) => Some(ScalarType::I32),
+            ('U', 4) => Some(ScalarType::U32),
+            ('F', 4) => Some(ScalarType::F32),
+            ('F', 8) => Some(ScalarType::F64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => 1,
+            ScalarType::I16 | ScalarType::U16 => 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::F64 => 8,
+        }
+    }
+
+    fn read_le(self, bytes: &[u8]) -> f64 {
+        match self {
+            ScalarType::I8 => bytes[0] as i8 as f64,
+            ScalarType::U8 => bytes[0] as f64,
+            ScalarType::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ScalarType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ScalarType::I32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            ScalarType::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            ScalarType::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            ScalarType::F64 => {
+                let mut array = [0; 8];
+                array.copy_from_slice(&bytes[..8]);
+                f64::from_le_bytes(array)
+            },
+        }
+    }
+}
+
+/// Where the position, color and normal values are among the values read for each point.
+struct FieldLayout {
+    position: [usize; 3],
+    color: Option<ColorLayout>,
+    normal: Option<[usize; 3]>,
+}
+
+enum ColorLayout {
+    /// Separate red, green and blue values.
+    Channels([usize; 3]),
+    /// PCL style color packed into a single 32-bit value. Readers store the packed bits as an
+    /// integer value.
+    Packed(usize),
+}
+
+impl FieldLayout {
+    /// Find the fields of interest among `(type, count, name)` field descriptions.
+    fn new<'a, I>(fields: I) -> Result<Self, PointCloudIoError>
+        where I: Iterator<Item = (ScalarType, usize, &'a str)>
+    {
+        let mut indices: Vec<(&str, usize, ScalarType)> = Vec::new();
+        let mut offset = 0;
+        for (data_type, count, name) in fields {
+            indices.push((name, offset, data_type));
+            offset += count;
+        }
+
+        let find = |names: &[&str]| {
+            indices.iter().find(|(name, _, _)| names.contains(name)).map(|(_, index, data_type)| (*index, *data_type))
+        };
+        let find_three = |names: [&[&str]; 3]| -> Option<[usize; 3]> {
+            Some([find(names[0])?.0, find(names[1])?.0, find(names[2])?.0])
+        };
+
+        let position = find_three([&["x"], &["y"], &["z"]])
+            .ok_or_else(|| PointCloudIoError::ParseError("missing x, y or z".to_string()))?;
+        let normal = find_three([&["nx", "normal_x"], &["ny", "normal_y"], &["nz", "normal_z"]]);
+        let color = find_three([&["red", "r"], &["green", "g"], &["blue", "b"]])
+            .map(ColorLayout::Channels)
+            .or_else(|| {
+                find(&["rgb", "rgba"])
+                    .filter(|(_, data_type)| data_type.size() == 4)
+                    .map(|(index, _)| ColorLayout::Packed(index))
+            });
+
+        Ok(Self { position, color, normal })
+    }
+
+    /// A cloud with room for `point_count` points, up to `MAX_RESERVED_POINTS`. Counts come from
+    /// file headers, which can't be trusted to allocate up front; past that the cloud grows as
+    /// points are read.
+    fn empty_cloud(&self, point_count: usize) -> PointCloud {
+        let capacity = point_count.min(MAX_RESERVED_POINTS);
+        PointCloud {
+            width: 0,
+            height: 0,
+            points: Vec::with_capacity(capacity),
+            colors: self.color.as_ref().map(|_| Vec::with_capacity(capacity)),
+            normals: self.normal.map(|_| Vec::with_capacity(capacity)),
+        }
+    }
+
+    fn push_point(&self, cloud: &mut PointCloud, values: &[f64]) {
+        let [x, y, z] = self.position;
+        cloud.points.push([values[x] as f32, values[y] as f32, values[z] as f32]);
+
+        if let (Some(normal), Some(normals)) = (self.normal, cloud.normals.as_mut()) {
+            let [x, y, z] = normal;
+            normals.push([values[x] as f32, values[y] as f32, values[z] as f32]);
+        }
+
+        if let (Some(color), Some(colors)) = (&self.color, cloud.colors.as_mut()) {
+            colors.push(match color {
+                ColorLayout::Channels([r, g, b]) => [values[*r] as u8, values[*g] as u8, values[*b] as u8],
+                ColorLayout::Packed(index) => {
+                    let value = values[*index];
+                    // Older PCL versions wrote packed colors in ASCII data as floats.
+                    let bits = if value.fract() == 0.0 { value as u32 } else { (value as f32).to_bits() };
+                    [(bits >> 16) as u8, (bits >> 8) as u8, bits as u8]
+                },
+            });
+        }
+    }
+}
+
+fn write_f32s<W: Write>(writer: &mut W, values: &[f32]) -> Result<(), PointCloudIoError> {
+    for value in values {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Read a header line into `line`, returning it trimmed.
+fn read_header_line<'a, R: BufRead>(reader: &mut R, line: &'a mut String) -> Result<&'a str, PointCloudIoError> {
+    line.clear();
+    if reader.read_line(line)? == 0 {
+        return Err(PointCloudIoError::ParseError("unexpected end of header".to_string()));
+    }
+    Ok(line.trim())
+}
+
+fn read_ascii_values<R: BufRead>(reader: &mut R,
+                                 line: &mut String,
+                                 values: &mut [f64]) -> Result<(), PointCloudIoError>
+{
+    line.clear();
+    if reader.read_line(line)? == 0 {
+        return Err(PointCloudIoError::ParseError("unexpected end of data".to_string()));
+    }
+
+    let mut tokens = line.split_whitespace();
+    for value in values.iter_mut() {
+        let token = tokens.next()
+            .ok_or_else(|| PointCloudIoError::ParseError(format!("too few values: {}", line.trim())))?;
+        *value = parse_token(token)?;
+    }
+    Ok(())
+}
+
+/// Read one little endian value of each type. The value at `packed_index` is read as the
+/// integer bits of a packed color, whatever its type.
+fn read_binary_values<R: Read>(reader: &mut R,
+                               types: &[ScalarType],
+                               packed_index: Option<usize>,
+                               values: &mut [f64]) -> Result<(), PointCloudIoError>
+{
+    let mut buffer = [0; 8];
+    for (index, (data_type, value)) in types.iter().zip(values.iter_mut()).enumerate() {
+        let bytes = &mut buffer[..data_type.size()];
+        reader.read_exact(bytes)?;
+        *value = if packed_index == Some(index) {
+            u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+        } else {
+            data_type.read_le(bytes)
+        };
+    }
+    Ok(())
+}
+
+fn parse_token<T: std::str::FromStr>(token: &str) -> Result<T, PointCloudIoError> {
+    token.parse().map_err(|_| PointCloudIoError::ParseError(format!("invalid number: {}", token)))
+}
+
+fn parse_vector(tokens: &[&str]) -> Result<[f32; 3], PointCloudIoError> {
+    Ok([parse_token(tokens[0])?, parse_token(tokens[1])?, parse_token(tokens[2])?])
+}
+
+fn parse_color(tokens: &[&str]) -> Option<[u8; 3]> {
+    Some([tokens[0].parse().ok()?, tokens[1].parse().ok()?, tokens[2].parse().ok()?])
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension().and_then(|extension| extension.to_str()).map(str::to_ascii_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cloud(colors: bool, normals: bool) -> PointCloud {
+        let mut cloud = PointCloud::from_points(vec![
+            [1.5, -2.25, 1000.0],
+            [-310.125, 42.0, 2503.75],
+            [0.1, 0.2, 0.3],
+            [1e-3, -7.0, 123456.7],
+        ]);
+        if colors {
+            cloud.colors = Some(vec![[0, 0, 0], [255, 128, 1], [7, 8, 9], [255, 255, 255]]);
+        }
+        if normals {
+            cloud.normals = Some(vec![[0.0, 0.0, -1.0], [0.6, -0.8, 0.0], [0.1, 0.2, 0.974_679_4], [1.0, 0.0, 0.0]]);
+        }
+        cloud
+    }
+
+    fn variants() -> Vec<(bool, bool)> {
+        vec![(false, false), (true, false), (false, true), (true, true)]
+    }
+
+    fn round_trip<W, R>(write: W, read: R)
+        where W: Fn(&PointCloud, &mut Vec<u8>) -> Result<(), PointCloudIoError>,
+              R: Fn(&[u8]) -> Result<PointCloud, PointCloudIoError>
+    {
+        for (colors, normals) in variants() {
+            let cloud = cloud(colors, normals);
+            let mut buffer = Vec::new();
+            write(&cloud, &mut buffer).unwrap();
+            assert_eq!(read(&buffer).unwrap(), cloud, "colors: {}, normals: {}", colors, normals);
+        }
+    }
+
+    #[test]
+    fn ply_round_trips() {
+        for format in [PlyFormat::Ascii, PlyFormat::BinaryLittleEndian].iter() {
+            round_trip(|cloud, buffer| cloud.write_ply(buffer, *format), |bytes| PointCloud::read_ply(bytes));
+        }
+    }
+
+    #[test]
+    fn pcd_round_trips() {
+        for format in [PcdFormat::Ascii, PcdFormat::Binary].iter() {
+            round_trip(|cloud, buffer| cloud.write_pcd(buffer, *format), |bytes| PointCloud::read_pcd(bytes));
+        }
+    }
+
+    #[test]
+    fn xyz_round_trips() {
+        round_trip(|cloud, buffer| cloud.write_xyz(buffer), |bytes| PointCloud::read_xyz(bytes));
+    }
+
+    #[test]
+    fn pcd_keeps_organized_clouds_and_invalid_points() {
+        let mut cloud = cloud(true, true);
+        cloud.width = 2;
+        cloud.height = 2;
+        cloud.points[2] = [0.0; 3];
+
+        for format in [PcdFormat::Ascii, PcdFormat::Binary].iter() {
+            let mut buffer = Vec::new();
+            cloud.write_pcd(&mut buffer, *format).unwrap();
+            assert_eq!(PointCloud::read_pcd(&buffer[..]).unwrap(), cloud);
+        }
+
+        // PLY and XYZ drop the invalid point.
+        let mut buffer = Vec::new();
+        cloud.write_ply(&mut buffer, PlyFormat::BinaryLittleEndian).unwrap();
+        let read = PointCloud::read_ply(&buffer[..]).unwrap();
+        assert_eq!((read.width, read.height), (3, 1));
+        assert_eq!(read.points[2], cloud.points[3]);
+    }
+
+    #[test]
+    fn xyz_columns_follow_the_header() {
+        let read = PointCloud::read_xyz(&b"# nx ny nz x y z\n0 0 1 10 20 30\n"[..]).unwrap();
+        assert_eq!(read.points, vec![[10.0, 20.0, 30.0]]);
+        assert_eq!(read.normals, Some(vec![[0.0, 0.0, 1.0]]));
+        assert_eq!(read.colors, None);
+
+        // Without a header, six columns are coordinates and normals.
+        let read = PointCloud::read_xyz(&b"1 2 3 0 1 0\n4 5 6 1 0 0\n"[..]).unwrap();
+        assert_eq!(read.normals, Some(vec![[0.0, 1.0, 0.0], [1.0, 0.0, 0.0]]));
+        assert_eq!(read.colors, None);
+
+        assert!(PointCloud::read_xyz(&b"1 2 3\n4 5 6 7\n"[..]).is_err());
+        assert!(PointCloud::read_xyz(&b"1 2 3 4\n"[..]).is_err());
+        assert!(PointCloud::read_xyz(&b"# x y z r g b\n1 2 3 4 5 256\n"[..]).is_err());
+    }
+
+    #[test]
+    fn huge_header_counts_fail_without_allocating() {
+        let ply = b"ply\nformat ascii 1.0\nelement vertex 18446744073709551615\n\
+                    property float x\nproperty float y\nproperty float z\nend_header\n1 2 3\n";
+        assert!(matches!(PointCloud::read_ply(&ply[..]), Err(PointCloudIoError::ParseError(_))));
+
+        let pcd = b"FIELDS x y z\nSIZE 4 4 4\nTYPE F F F\nWIDTH 18446744073709551615\nHEIGHT 2\n\
+                    DATA ascii\n1 2 3\n";
+        assert!(matches!(PointCloud::read_pcd(&pcd[..]), Err(PointCloudIoError::ParseError(_))));
+
+        let pcd = b"FIELDS x y z\nSIZE 4 4 4\nTYPE F F F\nWIDTH 4294967296\nHEIGHT 1\nDATA ascii\n1 2 3\n";
+        assert!(matches!(PointCloud::read_pcd(&pcd[..]), Err(PointCloudIoError::ParseError(_))));
+    }
+}
@@ -0,0 +1,426 @@
+//! Point cloud filters: voxel downsampling, outlier removal, cropping and normal estimation.
+//!
+//! Filters that remove points keep the layout of organized clouds by marking removed points as
+//! invalid; points are only dropped from unorganized clouds. With the `rayon` feature, per-point
+//! work is spread across threads.
+
+use crate::error::PointCloudProcessingError;
+use crate::kd_tree::KdTree;
+use crate::math;
+use crate::point_cloud::is_valid_point;
+use crate::PointCloud;
+
+use std::collections::HashMap;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+impl PointCloud {
+    /// Replace the points in each cube of `voxel_size_mm` by their centroid, averaging colors and
+    /// normals too. The result is unorganized and ordered by voxel.
+    ///
+    /// `voxel_size_mm` must be positive.
+    pub fn voxel_downsample(&self, voxel_size_mm: f32) -> Result<PointCloud, PointCloudProcessingError> {
+        check_positive("voxel_size_mm", voxel_size_mm)?;
+
+        #[derive(Default)]
+        struct Voxel {
+            count: usize,
+            point: [f64; 3],
+            color: [f64; 3],
+            normal: [f64; 3],
+        }
+
+        let mut voxels: HashMap<[i64; 3], Voxel> = HashMap::new();
+
+        for (i, point) in self.points.iter().enumerate() {
+            if !is_valid_point(point) {
+                continue;
+            }
+
+            let key = [
+                (point[0] / voxel_size_mm).floor() as i64,
+                (point[1] / voxel_size_mm).floor() as i64,
+                (point[2] / voxel_size_mm).floor() as i64,
+            ];
+            let voxel = voxels.entry(key).or_default();
+
+            voxel.count += 1;
+            for axis in 0..3 {
+                voxel.point[axis] += point[axis] as f64;
+                if let Some(colors) = &self.colors {
+                    voxel.color[axis] += colors[i][axis] as f64;
+                }
+                if let Some(normals) = &self.normals {
+                    voxel.normal[axis] += normals[i][axis] as f64;
+                }
+            }
+        }
+
+        let mut voxels: Vec<([i64; 3], Voxel)> = voxels.into_iter().collect();
+        voxels.sort_unstable_by_key(|(key, _)| *key);
+
+        let points: Vec<[f32; 3]> = voxels.iter()
+            .map(|(_, voxel)| {
+                let point = math::scale(voxel.point, 1.0 / voxel.count as f64);
+                [point[0] as f32, point[1] as f32, point[2] as f32]
+            })
+            .collect();
+
+        let colors = self.colors.as_ref().map(|_| {
+            voxels.iter()
+                .map(|(_, voxel)| {
+                    let color = math::scale(voxel.color, 1.0 / voxel.count as f64);
+                    [color[0].round() as u8, color[1].round() as u8, color[2].round() as u8]
+                })
+                .collect()
+        });
+
+        let normals = self.normals.as_ref().map(|_| {
+            voxels.iter().map(|(_, voxel)| unit_or_zero(voxel.normal)).collect()
+        });
+
+        Ok(PointCloud {
+            width: points.len(),
+            height: 1,
+            points,
+            colors,
+            normals,
+        })
+    }
+
+    /// Remove points whose mean distance to their `neighbors` nearest points is more than
+    /// `std_ratio` standard deviations above the mean over the whole cloud.
+    ///
+    /// `neighbors` must be positive.
+    pub fn remove_statistical_outliers(&self,
+                                       neighbors: usize,
+                                       std_ratio: f32) -> Result<PointCloud, PointCloudProcessingError>
+    {
+        check_positive("neighbors", neighbors as f32)?;
+
+        let tree = KdTree::new(&self.points);
+
+        let mean_distances: Vec<Option<f64>> = map_indices(self.points.len(), |i| {
+            if !self.is_valid(i) {
+                return None;
+            }
+            let nearest = tree.nearest(self.points[i], neighbors, Some(i));
+            if nearest.is_empty() {
+                return None;
+            }
+            Some(nearest.iter().map(|(distance, _)| (*distance as f64).sqrt()).sum::<f64>() / nearest.len() as f64)
+        });
+
+        let distances: Vec<f64> = mean_distances.iter().flatten().copied().collect();
+        if distances.is_empty() {
+            return Ok(self.clone());
+        }
+
+        let mean = distances.iter().sum::<f64>() / distances.len() as f64;
+        let variance = distances.iter().map(|d| (d - mean) * (d - mean)).sum::<f64>() / distances.len() as f64;
+        let threshold = mean + std_ratio as f64 * variance.sqrt();
+
+        let keep: Vec<bool> = mean_distances.iter()
+            .map(|distance| distance.map(|d| d <= threshold).unwrap_or(false))
+            .collect();
+
+        Ok(self.retain(&keep))
+    }
+
+    /// Remove points with fewer than `min_neighbors` other points within `radius_mm`, which must
+    /// be positive.
+    pub fn remove_radius_outliers(&self,
+                                  radius_mm: f32,
+                                  min_neighbors: usize) -> Result<PointCloud, PointCloudProcessingError>
+    {
+        check_positive("radius_mm", radius_mm)?;
+
+        let tree = KdTree::new(&self.points);
+
+        let keep = map_indices(self.points.len(), |i| {
+            self.is_valid(i) && tree.count_within(self.points[i], radius_mm, Some(i), min_neighbors) >= min_neighbors
+        });
+
+        Ok(self.retain(&keep))
+    }
+
+    /// Remove points outside the axis-aligned box from `min` to `max` (inclusive), in millimeters
+    /// in the cloud's camera coordinates.
+    pub fn crop_box(&self, min: [f32; 3], max: [f32; 3]) -> PointCloud {
+        let keep = map_indices(self.points.len(), |i| {
+            let point = self.points[i];
+            self.is_valid(i) && (0..3).all(|axis| point[axis] >= min[axis] && point[axis] <= max[axis])
+        });
+
+        self.retain(&keep)
+    }
+
+    /// Estimate normals of an organized cloud from its neighbors in the image grid.
+    ///
+    /// Each normal is fit to the valid points within `window_radius` pixels whose depth is within
+    /// `max_depth_jump_mm` of the center point, so surfaces aren't blended across depth edges.
+    /// Normals face the camera. Points with too few neighbors (and invalid points) get zero
+    /// normals.
+    ///
+    /// Returns false (leaving the cloud unchanged) if the cloud isn't organized.
+    pub fn estimate_normals(&mut self, window_radius: usize, max_depth_jump_mm: f32) -> bool {
+        if !self.is_organized() || self.width * self.height != self.points.len() {
+            return false;
+        }
+
+        let width = self.width;
+        let height = self.height;
+        let points = &self.points;
+        let radius = window_radius.max(1);
+
+        let normals = map_indices(points.len(), |i| {
+            let center = points[i];
+            if !is_valid_point(&center) {
+                return [0.0; 3];
+            }
+
+            let (x, y) = (i % width, i / width);
+            let neighbors = (y.saturating_sub(radius)..(y + radius + 1).min(height))
+                .flat_map(|ny| (x.saturating_sub(radius)..(x + radius + 1).min(width)).map(move |nx| ny * width + nx))
+                .map(|j| points[j])
+                .filter(|point| is_valid_point(point) && (point[2] - center[2]).abs() <= max_depth_jump_mm);
+
+            fit_normal(center, neighbors)
+        });
+
+        self.normals = Some(normals);
+        true
+    }
+
+    /// Keep the points flagged in `keep`, invalidating the others in organized clouds and
+    /// dropping them from unorganized clouds.
+    fn retain(&self, keep: &[bool]) -> PointCloud {
+        if self.is_organized() {
+            let mut cloud = self.clone();
+            for (point, keep) in cloud.points.iter_mut().zip(keep) {
+                if !keep {
+                    *point = [0.0; 3];
+                }
+            }
+            return cloud;
+        }
+
+        let select = |count: usize| (0..count).filter(|i| keep[*i]);
+        let points: Vec<[f32; 3]> = select(self.points.len()).map(|i| self.points[i]).collect();
+
+        PointCloud {
+            width: points.len(),
+            height: 1,
+            points,
+            colors: self.colors.as_ref().map(|colors| select(colors.len()).map(|i| colors[i]).collect()),
+            normals: self.normals.as_ref().map(|normals| select(normals.len()).map(|i| normals[i]).collect()),
+        }
+    }
+}
+
+fn check_positive(name: &'static str, value: f32) -> Result<(), PointCloudProcessingError> {
+    if value.is_nan() || value <= 0.0 {
+        return Err(PointCloudProcessingError::InvalidParameterError { name, value });
+    }
+    Ok(())
+}
+
+/// The direction of least variance of the points, facing the camera at the origin.
+fn fit_normal<I: Iterator<Item = [f32; 3]>>(center: [f32; 3], points: I) -> [f32; 3] {
+    let mut count = 0;
+    let mut sum = [0.0; 3];
+    let mut products = [0.0; 9];
+
+    for point in points {
+        // Relative to the center, for precision.
+        let p = [
+            (point[0] - center[0]) as f64,
+            (point[1] - center[1]) as f64,
+            (point[2] - center[2]) as f64,
+        ];
+        count += 1;
+        sum = math::add(sum, p);
+        for row in 0..3 {
+            for column in 0..3 {
+                products[row * 3 + column] += p[row] * p[column];
+            }
+        }
+    }
+
+    if count < 3 {
+        return [0.0; 3];
+    }
+
+    let n = count as f64;
+    let mut covariance = [0.0; 9];
+    for row in 0..3 {
+        for column in 0..3 {
+            covariance[row * 3 + column] = products[row * 3 + column] / n - sum[row] * sum[column] / (n * n);
+        }
+    }
+
+    let (values, vectors) = math::symmetric_eigen(&covariance, 3);
+    // Collinear neighbors (eg. along a thin edge) don't determine a plane.
+    if values[1] <= 1e-9 {
+        return [0.0; 3];
+    }
+
+    let mut normal = [vectors[0][0], vectors[0][1], vectors[0][2]];
+    let center = [center[0] as f64, center[1] as f64, center[2] as f64];
+    if math::dot(normal, center) > 0.0 {
+        normal = math::scale(normal, -1.0);
+    }
+
+    unit_or_zero(normal)
+}
+
+fn unit_or_zero(v: math::Vec3) -> [f32; 3] {
+    let length = math::norm(v);
+    if length < 1e-12 {
+        return [0.0; 3];
+    }
+    [(v[0] / length) as f32, (v[1] / length) as f32, (v[2] / length) as f32]
+}
+
+#[cfg(feature = "rayon")]
+fn map_indices<T, F>(count: usize, f: F) -> Vec<T>
+    where T: Send, F: Fn(usize) -> T + Sync + Send
+{
+    (0..count).into_par_iter().map(f).collect()
+}
+
+#[cfg(not(feature = "rayon"))]
+fn map_indices<T, F>(count: usize, f: F) -> Vec<T>
+    where F: Fn(usize) -> T
+{
+    (0..count).map(f).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 5 by 5 grid of points 10mm apart at 1m, and one point far off it.
+    fn grid_with_outlier() -> PointCloud {
+        let mut points: Vec<[f32; 3]> = (0..25).map(|i| [(i % 5) as f32 * 10.0, (i / 5) as f32 * 10.0, 1000.0]).collect();
+        points.push([300.0, -200.0, 1400.0]);
+        PointCloud::from_points(points)
+    }
+
+    fn assert_close(a: [f32; 3], b: [f32; 3], tolerance: f32) {
+        assert!((0..3).all(|axis| (a[axis] - b[axis]).abs() <= tolerance), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn voxel_downsample_averages_each_voxel() {
+        let mut cloud = PointCloud::from_points(vec![
+            [1.0, 1.0, 1001.0],
+            [3.0, 5.0, 1009.0],
+            [-1.0, 1.0, 1001.0],
+            [0.0, 0.0, 0.0],
+            [12.0, 1.0, 1001.0],
+        ]);
+        cloud.colors = Some(vec![[0, 10, 200], [255, 11, 100], [1, 2, 3], [9, 9, 9], [4, 5, 6]]);
+        cloud.normals = Some(vec![[0.0, 0.0, -1.0], [0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]]);
+
+        let downsampled = cloud.voxel_downsample(10.0).unwrap();
+
+        // Ordered by voxel, and the invalid point is skipped.
+        assert_eq!((downsampled.width, downsampled.height), (3, 1));
+        assert_eq!(downsampled.points, vec![[-1.0, 1.0, 1001.0], [2.0, 3.0, 1005.0], [12.0, 1.0, 1001.0]]);
+        assert_eq!(downsampled.colors.as_ref().unwrap()[1], [128, 11, 150]);
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        assert_close(downsampled.normals.as_ref().unwrap()[1], [0.0, -half, -half], 1e-6);
+    }
+
+    #[test]
+    fn filters_reject_bad_parameters() {
+        let cloud = grid_with_outlier();
+        let invalid = |result: Result<PointCloud, PointCloudProcessingError>| {
+            matches!(result, Err(PointCloudProcessingError::InvalidParameterError { .. }))
+        };
+
+        assert!(invalid(cloud.voxel_downsample(0.0)));
+        assert!(invalid(cloud.voxel_downsample(-5.0)));
+        assert!(invalid(cloud.voxel_downsample(f32::NAN)));
+        assert!(invalid(cloud.remove_statistical_outliers(0, 1.0)));
+        assert!(invalid(cloud.remove_radius_outliers(0.0, 2)));
+        assert!(invalid(cloud.remove_radius_outliers(f32::NAN, 2)));
+    }
+
+    #[test]
+    fn statistical_outlier_removal_drops_far_points() {
+        let cloud = grid_with_outlier();
+        let filtered = cloud.remove_statistical_outliers(4, 1.0).unwrap();
+        assert_eq!(filtered.points, cloud.points[..25]);
+        assert_eq!(filtered.width, 25);
+    }
+
+    #[test]
+    fn radius_outlier_removal_keeps_the_layout_of_organized_clouds() {
+        let cloud = grid_with_outlier();
+        let filtered = cloud.remove_radius_outliers(10.5, 2).unwrap();
+        assert_eq!(filtered.points, cloud.points[..25]);
+
+        // Corners have exactly two neighbors that close.
+        assert_eq!(cloud.remove_radius_outliers(10.5, 3).unwrap().points.len(), 21);
+
+        let mut organized = cloud.clone();
+        organized.points.truncate(24);
+        organized.points.push([300.0, -200.0, 1400.0]);
+        organized.width = 5;
+        organized.height = 5;
+        let filtered = organized.remove_radius_outliers(10.5, 2).unwrap();
+        assert_eq!((filtered.width, filtered.height, filtered.points.len()), (5, 5, 25));
+        assert_eq!(filtered.points[24], [0.0; 3]);
+        assert_eq!(filtered.valid_count(), 24);
+    }
+
+    #[test]
+    fn crop_box_is_inclusive() {
+        let mut cloud = grid_with_outlier();
+        cloud.colors = Some((0..26).map(|i| [i as u8, 0, 0]).collect());
+
+        let cropped = cloud.crop_box([10.0, 0.0, 900.0], [20.0, 10.0, 1000.0]);
+        assert_eq!(cropped.points, vec![[10.0, 0.0, 1000.0], [20.0, 0.0, 1000.0], [10.0, 10.0, 1000.0], [20.0, 10.0, 1000.0]]);
+        assert_eq!(cropped.colors, Some(vec![[1, 0, 0], [2, 0, 0], [6, 0, 0], [7, 0, 0]]));
+    }
+
+    #[test]
+    fn normals_of_a_tilted_plane_face_the_camera() {
+        // z = 1000 + x / 2, seen by a 20 by 20 organized cloud with a hole and a depth edge.
+        let mut points: Vec<[f32; 3]> = (0..400)
+            .map(|i| {
+                let (x, y) = ((i % 20) as f32 * 5.0 - 50.0, (i / 20) as f32 * 5.0 - 50.0);
+                [x, y, 1000.0 + x / 2.0]
+            })
+            .collect();
+        points[210] = [0.0; 3];
+        for point in points[380..].iter_mut() {
+            point[2] += 500.0;
+        }
+        let mut cloud = PointCloud { width: 20, height: 20, points, colors: None, normals: None };
+
+        assert!(cloud.estimate_normals(2, 30.0));
+        let normals = cloud.normals.as_ref().unwrap();
+
+        let length = (1.25_f32).sqrt();
+        let expected = [0.5 / length, 0.0, -1.0 / length];
+        for y in 0..19 {
+            for x in 0..20 {
+                let i = y * 20 + x;
+                if i == 210 {
+                    assert_eq!(normals[i], [0.0; 3]);
+                } else {
+                    assert_close(normals[i], expected, 1e-4);
+                }
+            }
+        }
+
+        // The far row behind the edge is fit on its own, as a line, which doesn't give a normal.
+        assert!(normals[380..].iter().all(|normal| *normal == [0.0; 3]));
+
+        assert!(!PointCloud::from_points(vec![[0.0, 0.0, 1000.0]; 4]).estimate_normals(1, 10.0));
+    }
+}
@@ -0,0 +1,269 @@
+//! Recording captures to Matroska (.mkv) files and playing them back, with the Azure Kinect
+//! recording library (libk4arecord).
+//!
+//! The library isn't covered by `k4a-sys`, so the part of its API used here is declared in `ffi`
+//! below, following `record.h`, `playback.h` and `types.h` from SDK 1.4.
+
+use crate::error::{PlaybackError, RecordError};
+use crate::Calibration;
+use crate::Capture;
+use crate::Device;
+use crate::DeviceConfiguration;
+use crate::ImageFormat;
+
+use k4a_sys_temp as k4a_sys;
+use std::ffi::CString;
+use std::mem::MaybeUninit;
+use std::path::Path;
+use std::ptr::null_mut;
+
+/// Writes captures from a device to a recording.
+///
+/// Write the header with `write_header` before the first capture. The recording is finalized
+/// when the recorder is dropped.
+pub struct Recorder {
+    recording: ffi::k4a_record_t,
+}
+
+unsafe impl Send for Recorder {}
+
+impl Recorder {
+    /// Create a recording at `path` for captures from `device` running with `configuration`.
+    pub fn create<P: AsRef<Path>>(path: P,
+                                  device: &Device,
+                                  configuration: &DeviceConfiguration) -> Result<Self, RecordError>
+    {
+        let path = path_to_c_string(path.as_ref()).ok_or(RecordError::InvalidPathError)?;
+        let mut recording = null_mut();
+
+        let result = unsafe {
+            ffi::k4a_record_create(path.as_ptr(), device.device_pointer, configuration.0, &mut recording)
+        };
+        RecordError::check(result)?;
+
+        Ok(Self { recording })
+    }
+
+    /// Write the recording header. Must be called once, before `write_capture`.
+    pub fn write_header(&mut self) -> Result<(), RecordError> {
+        let result = unsafe {
+            ffi::k4a_record_write_header(self.recording)
+        };
+        RecordError::check(result)
+    }
+
+    /// Add a capture to the recording. Captures must be written in order of their timestamps.
+    pub fn write_capture(&mut self, capture: &Capture) -> Result<(), RecordError> {
+        let result = unsafe {
+            ffi::k4a_record_write_capture(self.recording, capture.get_handle())
+        };
+        RecordError::check(result)
+    }
+
+    /// Write buffered captures to disk.
+    pub fn flush(&mut self) -> Result<(), RecordError> {
+        let result = unsafe {
+            ffi::k4a_record_flush(self.recording)
+        };
+        RecordError::check(result)
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::k4a_record_close(self.recording);
+        }
+        self.recording = null_mut();
+    }
+}
+
+/// Where `Playback::seek_timestamp` measures its offset from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SeekOrigin {
+    /// The start of the recording.
+    Begin,
+    /// The end of the recording; offsets should be negative.
+    End,
+    /// Device time zero, so offsets are device timestamps.
+    DeviceTime,
+}
+
+/// Reads captures back from a recording.
+pub struct Playback {
+    playback: ffi::k4a_playback_t,
+}
+
+unsafe impl Send for Playback {}
+
+impl Playback {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, PlaybackError> {
+        let path = path_to_c_string(path.as_ref()).ok_or(PlaybackError::InvalidPathError)?;
+        let mut playback = null_mut();
+
+        let result = unsafe {
+            ffi::k4a_playback_open(path.as_ptr(), &mut playback)
+        };
+        PlaybackError::check(result)?;
+
+        Ok(Self { playback })
+    }
+
+    /// The calibration of the device the recording was made with.
+    pub fn get_calibration(&self) -> Result<Calibration, PlaybackError> {
+        let mut calibration = MaybeUninit::<k4a_sys::k4a_calibration_t>::uninit();
+
+        let result = unsafe {
+            ffi::k4a_playback_get_calibration(self.playback, calibration.as_mut_ptr())
+        };
+        PlaybackError::check(result)?;
+
+        Ok(Calibration(unsafe { calibration.assume_init() }))
+    }
+
+    /// The device configuration and tracks of the recording.
+    pub fn get_record_configuration(&self) -> Result<ffi::k4a_record_configuration_t, PlaybackError> {
+        let mut configuration = MaybeUninit::<ffi::k4a_record_configuration_t>::uninit();
+
+        let result = unsafe {
+            ffi::k4a_playback_get_record_configuration(self.playback, configuration.as_mut_ptr())
+        };
+        PlaybackError::check(result)?;
+
+        Ok(unsafe { configuration.assume_init() })
+    }
+
+    /// The length of the recording, from its first to its last timestamp.
+    pub fn get_recording_length_usec(&self) -> u64 {
+        unsafe {
+            ffi::k4a_playback_get_recording_length_usec(self.playback)
+        }
+    }
+
+    /// Decode color images to `format` as they're read (eg. `ColorBgra32` for MJPG recordings).
+    pub fn set_color_conversion(&mut self, format: ImageFormat) -> Result<(), PlaybackError> {
+        let result = unsafe {
+            ffi::k4a_playback_set_color_conversion(self.playback, format.to_k4a())
+        };
+        PlaybackError::check(result)
+    }
+
+    /// Move to the first capture at or after `offset_usec` from `origin`.
+    pub fn seek_timestamp(&mut self, offset_usec: i64, origin: SeekOrigin) -> Result<(), PlaybackError> {
+        let origin = match origin {
+            SeekOrigin::Begin => ffi::k4a_playback_seek_origin_t_K4A_PLAYBACK_SEEK_BEGIN,
+            SeekOrigin::End => ffi::k4a_playback_seek_origin_t_K4A_PLAYBACK_SEEK_END,
+            SeekOrigin::DeviceTime => ffi::k4a_playback_seek_origin_t_K4A_PLAYBACK_SEEK_DEVICE_TIME,
+        };
+        let result = unsafe {
+            ffi::k4a_playback_seek_timestamp(self.playback, offset_usec, origin)
+        };
+        PlaybackError::check(result)
+    }
+
+    /// Read the next capture, or None at the end of the recording.
+    pub fn get_next_capture(&mut self) -> Result<Option<Capture>, PlaybackError> {
+        let mut capture = null_mut();
+
+        let result = unsafe {
+            ffi::k4a_playback_get_next_capture(self.playback, &mut capture)
+        };
+
+        match result {
+            ffi::k4a_stream_result_t_K4A_STREAM_RESULT_SUCCEEDED => Ok(Some(Capture(capture))),
+            ffi::k4a_stream_result_t_K4A_STREAM_RESULT_EOF => Ok(None),
+            ffi::k4a_stream_result_t_K4A_STREAM_RESULT_FAILED => Err(PlaybackError::FailedError),
+            // NB: Linux and Windows platforms differ in integer types used here, so we cast this.
+            // Linux uses u32 and Windows uses i32.
+            // This should be fixed in the `k4a-sys` build script.
+            _ => Err(PlaybackError::UnexpectedError(result as i32)),
+        }
+    }
+}
+
+impl Drop for Playback {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::k4a_playback_close(self.playback);
+        }
+        self.playback = null_mut();
+    }
+}
+
+fn path_to_c_string(path: &Path) -> Option<CString> {
+    CString::new(path.to_str()?).ok()
+}
+
+#[allow(non_camel_case_types, non_upper_case_globals)]
+pub mod ffi {
+    use k4a_sys_temp as k4a_sys;
+    use std::os::raw::c_char;
+
+    #[repr(C)]
+    pub struct _k4a_record_t {
+        _rsvd: usize,
+    }
+    pub type k4a_record_t = *mut _k4a_record_t;
+
+    #[repr(C)]
+    pub struct _k4a_playback_t {
+        _rsvd: usize,
+    }
+    pub type k4a_playback_t = *mut _k4a_playback_t;
+
+    pub type k4a_stream_result_t = u32;
+    pub const k4a_stream_result_t_K4A_STREAM_RESULT_SUCCEEDED: k4a_stream_result_t = 0;
+    pub const k4a_stream_result_t_K4A_STREAM_RESULT_FAILED: k4a_stream_result_t = 1;
+    pub const k4a_stream_result_t_K4A_STREAM_RESULT_EOF: k4a_stream_result_t = 2;
+
+    pub type k4a_playback_seek_origin_t = u32;
+    pub const k4a_playback_seek_origin_t_K4A_PLAYBACK_SEEK_BEGIN: k4a_playback_seek_origin_t = 0;
+    pub const k4a_playback_seek_origin_t_K4A_PLAYBACK_SEEK_END: k4a_playback_seek_origin_t = 1;
+    pub const k4a_playback_seek_origin_t_K4A_PLAYBACK_SEEK_DEVICE_TIME: k4a_playback_seek_origin_t = 2;
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct k4a_record_configuration_t {
+        pub color_format: k4a_sys::k4a_image_format_t,
+        pub color_resolution: k4a_sys::k4a_color_resolution_t,
+        pub depth_mode: k4a_sys::k4a_depth_mode_t,
+        pub camera_fps: k4a_sys::k4a_fps_t,
+        pub color_track_enabled: bool,
+        pub depth_track_enabled: bool,
+        pub ir_track_enabled: bool,
+        pub imu_track_enabled: bool,
+        pub depth_delay_off_color_usec: i32,
+        pub wired_sync_mode: k4a_sys::k4a_wired_sync_mode_t,
+        pub subordinate_delay_off_master_usec: u32,
+        /// The device timestamp of the start of the recording.
+        pub start_timestamp_offset_usec: u32,
+    }
+
+    #[link(name = "k4arecord")]
+    extern "C" {
+        pub fn k4a_record_create(path: *const c_char,
+                                 device: k4a_sys::k4a_device_t,
+                                 device_config: k4a_sys::k4a_device_configuration_t,
+                                 recording_handle: *mut k4a_record_t) -> k4a_sys::k4a_result_t;
+        pub fn k4a_record_write_header(recording_handle: k4a_record_t) -> k4a_sys::k4a_result_t;
+        pub fn k4a_record_write_capture(recording_handle: k4a_record_t,
+                                        capture_handle: k4a_sys::k4a_capture_t) -> k4a_sys::k4a_result_t;
+        pub fn k4a_record_flush(recording_handle: k4a_record_t) -> k4a_sys::k4a_result_t;
+        pub fn k4a_record_close(recording_handle: k4a_record_t);
+
+        pub fn k4a_playback_open(path: *const c_char, playback_handle: *mut k4a_playback_t) -> k4a_sys::k4a_result_t;
+        pub fn k4a_playback_get_calibration(playback_handle: k4a_playback_t,
+                                            calibration: *mut k4a_sys::k4a_calibration_t) -> k4a_sys::k4a_result_t;
+        pub fn k4a_playback_get_record_configuration(playback_handle: k4a_playback_t,
+                                                     config: *mut k4a_record_configuration_t) -> k4a_sys::k4a_result_t;
+        pub fn k4a_playback_get_recording_length_usec(playback_handle: k4a_playback_t) -> u64;
+        pub fn k4a_playback_set_color_conversion(playback_handle: k4a_playback_t,
+                                                 target_format: k4a_sys::k4a_image_format_t) -> k4a_sys::k4a_result_t;
+        pub fn k4a_playback_seek_timestamp(playback_handle: k4a_playback_t,
+                                           offset_usec: i64,
+                                           origin: k4a_playback_seek_origin_t) -> k4a_sys::k4a_result_t;
+        pub fn k4a_playback_get_next_capture(playback_handle: k4a_playback_t,
+                                             capture_handle: *mut k4a_sys::k4a_capture_t) -> k4a_stream_result_t;
+        pub fn k4a_playback_close(playback_handle: k4a_playback_t);
+    }
+}
@@ -0,0 +1,458 @@
+use crate::error::{DeviceGetCaptureError, Error, ResilientDeviceError};
+use crate::Capture;
+use crate::Device;
+use crate::DeviceConfiguration;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The parts of a device `ResilientDevice` needs, so it can run against a stand-in device that
+/// fails on cue as well as against a `Device`.
+pub trait CaptureDevice {
+    type Capture;
+
+    fn start_cameras(&self, configuration: &DeviceConfiguration) -> Result<(), Error>;
+    fn start_imu(&self) -> Result<(), Error>;
+    /// Stop the cameras and IMU.
+    fn stop(&self);
+    fn get_capture(&self, timeout_ms: i32) -> Result<Self::Capture, DeviceGetCaptureError>;
+}
+
+impl CaptureDevice for Device {
+    type Capture = Capture;
+
+    fn start_cameras(&self, configuration: &DeviceConfiguration) -> Result<(), Error> {
+        Ok(Device::start_cameras(self, configuration)?)
+    }
+
+    fn start_imu(&self) -> Result<(), Error> {
+        Ok(Device::start_imu(self)?)
+    }
+
+    fn stop(&self) {
+        self.stop_imu();
+        self.stop_cameras();
+    }
+
+    fn get_capture(&self, timeout_ms: i32) -> Result<Capture, DeviceGetCaptureError> {
+        Device::get_capture(self, timeout_ms)
+    }
+}
+
+/// How often a lost device is reopened.
+#[derive(Debug, Copy, Clone)]
+pub struct Backoff {
+    /// Wait after the first failed attempt.
+    pub initial: Duration,
+    /// Longest wait between attempts.
+    pub max: Duration,
+    /// Factor the wait grows by after each failed attempt.
+    pub multiplier: f64,
+    /// Attempts after which to give up, if any.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(10),
+            multiplier: 2.0,
+            max_attempts: None,
+        }
+    }
+}
+
+impl Backoff {
+    /// The wait after `attempts` failed attempts. The first attempt is made right away.
+    pub fn delay(&self, attempts: u32) -> Duration {
+        if attempts == 0 {
+            return Duration::from_secs(0);
+        }
+        let delay_secs = self.initial.as_secs_f64() * self.multiplier.powi(attempts as i32 - 1);
+        Duration::from_secs_f64(delay_secs.min(self.max.as_secs_f64()))
+    }
+}
+
+/// Marks where captures are missing because the device was lost and reopened.
+#[derive(Debug, Copy, Clone)]
+pub struct Gap {
+    /// The error that got the device treated as lost.
+    pub cause: DeviceGetCaptureError,
+    pub lost_at: Instant,
+    pub resumed_at: Instant,
+    /// Attempts it took to reopen the device.
+    pub attempts: u32,
+}
+
+/// What `ResilientDevice::get_capture` delivers.
+#[derive(Debug)]
+pub enum ResilientCapture<C> {
+    Capture(C),
+    /// The device was lost and has just been reopened; captures continue after this.
+    Gap(Gap),
+}
+
+struct Loss {
+    cause: DeviceGetCaptureError,
+    lost_at: Instant,
+    attempts: u32,
+    next_attempt: Instant,
+}
+
+/// A device that is reopened and restarted when it's lost, eg. after a USB hiccup.
+///
+/// A device is lost when getting a capture fails (or, if `reconnect_after_timeouts` is set, when
+/// that many captures in a row time out). It is then stopped and closed, and `get_capture` tries
+/// to reopen it (by serial number) and restart its cameras, and IMU if it was started, with the
+/// original configuration. Attempts are spaced out by `backoff`. Once the device is back,
+/// `get_capture` delivers a `Gap` and then captures again.
+pub struct ResilientDevice<D: CaptureDevice = Device> {
+    open: Box<dyn FnMut() -> Result<D, Error> + Send>,
+    configuration: DeviceConfiguration,
+    imu: bool,
+    device: Option<D>,
+    loss: Option<Loss>,
+    consecutive_timeouts: u32,
+    /// Spacing of reconnect attempts.
+    pub backoff: Backoff,
+    /// Consecutive capture timeouts that count as losing the device, if any.
+    pub reconnect_after_timeouts: Option<u32>,
+}
+
+impl ResilientDevice<Device> {
+    /// Open the device with `serial_number` and start it with `configuration` (and its IMU, if
+    /// `imu` is set).
+    pub fn open(serial_number: &str, configuration: DeviceConfiguration, imu: bool) -> Result<Self, Error> {
+        let serial_number = serial_number.to_string();
+        Self::with_opener(move || Ok(Device::open_by_serial(&serial_number)?), configuration, imu)
+    }
+}
+
+impl<D: CaptureDevice> ResilientDevice<D> {
+    /// Open a device with `open` and start it with `configuration` (and its IMU, if `imu` is
+    /// set). `open` is called again to reopen the device when it's lost.
+    pub fn with_opener<F>(open: F, configuration: DeviceConfiguration, imu: bool) -> Result<Self, Error>
+        where F: FnMut() -> Result<D, Error> + Send + 'static
+    {
+        let mut resilient_device = Self {
+            open: Box::new(open),
+            configuration,
+            imu,
+            device: None,
+            loss: None,
+            consecutive_timeouts: 0,
+            backoff: Backoff::default(),
+            reconnect_after_timeouts: None,
+        };
+        resilient_device.device = Some(resilient_device.connect()?);
+        Ok(resilient_device)
+    }
+
+    /// The device, unless it's lost.
+    pub fn device(&self) -> Option<&D> {
+        self.device.as_ref()
+    }
+
+    /// Whether the device is lost and waiting to be reopened.
+    pub fn is_lost(&self) -> bool {
+        self.device.is_none()
+    }
+
+    /// Get a capture, reopening the device first if it's lost.
+    ///
+    /// While the device is lost, each call makes at most one reconnect attempt, waiting up to
+    /// `timeout_ms` for it to be due (a negative timeout waits as long as it takes).
+    pub fn get_capture(&mut self, timeout_ms: i32) -> Result<ResilientCapture<D::Capture>, ResilientDeviceError> {
+        if let Some(device) = &self.device {
+            match device.get_capture(timeout_ms) {
+                Ok(capture) => {
+                    self.consecutive_timeouts = 0;
+                    return Ok(ResilientCapture::Capture(capture));
+                },
+                Err(error @ DeviceGetCaptureError::TimeoutError { timeout_millis }) => {
+                    self.consecutive_timeouts += 1;
+                    let lost = self.reconnect_after_timeouts
+                        .map(|timeouts| self.consecutive_timeouts >= timeouts)
+                        .unwrap_or(false);
+                    if !lost {
+                        return Err(ResilientDeviceError::TimeoutError { timeout_millis });
+                    }
+                    self.lose(error);
+                },
+                Err(error) => self.lose(error),
+            }
+        }
+
+        self.reconnect(timeout_ms)
+    }
+
+    fn lose(&mut self, cause: DeviceGetCaptureError) {
+        if let Some(device) = self.device.take() {
+            device.stop();
+        }
+        let now = Instant::now();
+        self.loss = Some(Loss { cause, lost_at: now, attempts: 0, next_attempt: now });
+        self.consecutive_timeouts = 0;
+    }
+
+    fn reconnect(&mut self, timeout_ms: i32) -> Result<ResilientCapture<D::Capture>, ResilientDeviceError> {
+        let (attempts, next_attempt) = self.loss.as_ref()
+            .map(|loss| (loss.attempts, loss.next_attempt))
+            .expect("a device that isn't open has been lost");
+
+        if self.backoff.max_attempts.map(|max| attempts >= max).unwrap_or(false) {
+            return Err(ResilientDeviceError::GaveUpError { attempts });
+        }
+
+        let wait = next_attempt.saturating_duration_since(Instant::now());
+        if timeout_ms >= 0 && wait > Duration::from_millis(timeout_ms as u64) {
+            thread::sleep(Duration::from_millis(timeout_ms as u64));
+            return Err(ResilientDeviceError::DisconnectedError { attempts });
+        }
+        thread::sleep(wait);
+
+        let attempts = attempts + 1;
+        match self.connect() {
+            Ok(device) => {
+                self.device = Some(device);
+                let loss = self.loss.take().expect("a device that isn't open has been lost");
+                Ok(ResilientCapture::Gap(Gap {
+                    cause: loss.cause,
+                    lost_at: loss.lost_at,
+                    resumed_at: Instant::now(),
+                    attempts,
+                }))
+            },
+            Err(error) => {
+                let delay = self.backoff.delay(attempts);
+                if let Some(loss) = &mut self.loss {
+                    loss.attempts = attempts;
+                    loss.next_attempt = Instant::now() + delay;
+                }
+                Err(ResilientDeviceError::ReconnectError { attempts, error: Box::new(error) })
+            },
+        }
+    }
+
+    /// Open and start the device.
+    fn connect(&mut self) -> Result<D, Error> {
+        let device = (self.open)()?;
+        device.start_cameras(&self.configuration)?;
+        if self.imu {
+            if let Err(error) = device.start_imu() {
+                device.stop();
+                return Err(error);
+            }
+        }
+        Ok(device)
+    }
+}
+
+impl<D: CaptureDevice> Drop for ResilientDevice<D> {
+    fn drop(&mut self) {
+        if let Some(device) = &self.device {
+            device.stop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{DeviceOpenError, DeviceStartImuError};
+
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    /// What the fake devices do, in order, and what was done to them.
+    #[derive(Default)]
+    struct Script {
+        /// Whether each open succeeds; opens succeed once this runs out.
+        opens: VecDeque<bool>,
+        /// Whether each IMU start succeeds; starts succeed once this runs out.
+        imu_starts: VecDeque<bool>,
+        /// The result of each capture; captures succeed once this runs out.
+        captures: VecDeque<Result<u32, DeviceGetCaptureError>>,
+        open_count: u32,
+        camera_start_count: u32,
+        imu_start_count: u32,
+        stop_count: u32,
+    }
+
+    struct FakeDevice(Arc<Mutex<Script>>);
+
+    impl CaptureDevice for FakeDevice {
+        type Capture = u32;
+
+        fn start_cameras(&self, _configuration: &DeviceConfiguration) -> Result<(), Error> {
+            self.0.lock().unwrap().camera_start_count += 1;
+            Ok(())
+        }
+
+        fn start_imu(&self) -> Result<(), Error> {
+            let mut script = self.0.lock().unwrap();
+            script.imu_start_count += 1;
+            match script.imu_starts.pop_front().unwrap_or(true) {
+                true => Ok(()),
+                false => Err(DeviceStartImuError { error_code: 1 }.into()),
+            }
+        }
+
+        fn stop(&self) {
+            self.0.lock().unwrap().stop_count += 1;
+        }
+
+        fn get_capture(&self, _timeout_ms: i32) -> Result<u32, DeviceGetCaptureError> {
+            self.0.lock().unwrap().captures.pop_front().unwrap_or(Ok(0))
+        }
+    }
+
+    fn open_fake(script: &Arc<Mutex<Script>>, imu: bool) -> Result<ResilientDevice<FakeDevice>, Error> {
+        let script = script.clone();
+        let open = move || {
+            let succeeds = {
+                let mut script = script.lock().unwrap();
+                script.open_count += 1;
+                script.opens.pop_front().unwrap_or(true)
+            };
+            match succeeds {
+                true => Ok(FakeDevice(script.clone())),
+                false => Err(DeviceOpenError { error_code: 1 }.into()),
+            }
+        };
+        ResilientDevice::with_opener(open, DeviceConfiguration::new(), imu)
+    }
+
+    fn script(opens: &[bool], captures: Vec<Result<u32, DeviceGetCaptureError>>) -> Arc<Mutex<Script>> {
+        Arc::new(Mutex::new(Script {
+            opens: opens.iter().copied().collect(),
+            captures: captures.into_iter().collect(),
+            ..Script::default()
+        }))
+    }
+
+    #[test]
+    fn backoff_grows_up_to_its_max() {
+        let backoff = Backoff {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(500),
+            multiplier: 2.0,
+            max_attempts: None,
+        };
+        let delays: Vec<u128> = (0..6).map(|attempts| backoff.delay(attempts).as_millis()).collect();
+        assert_eq!(delays, vec![0, 100, 200, 400, 500, 500]);
+    }
+
+    #[test]
+    fn reconnects_with_backoff_and_reports_the_gap() {
+        let script = script(&[true, false, false], vec![Ok(1), Err(DeviceGetCaptureError::FailedError)]);
+        let mut device = open_fake(&script, true).unwrap();
+        device.backoff = Backoff {
+            initial: Duration::from_millis(20),
+            max: Duration::from_millis(30),
+            multiplier: 2.0,
+            max_attempts: None,
+        };
+
+        assert!(matches!(device.get_capture(-1), Ok(ResilientCapture::Capture(1))));
+
+        // The failed capture loses the device, and the first reconnect attempt is made right away.
+        let lost_at = Instant::now();
+        match device.get_capture(-1) {
+            Err(ResilientDeviceError::ReconnectError { attempts: 1, .. }) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(device.is_lost());
+        assert!(device.device().is_none());
+        assert_eq!(script.lock().unwrap().stop_count, 1);
+
+        // Not due for another 20ms.
+        assert!(matches!(device.get_capture(0), Err(ResilientDeviceError::DisconnectedError { attempts: 1 })));
+
+        match device.get_capture(-1) {
+            Err(ResilientDeviceError::ReconnectError { attempts: 2, .. }) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(lost_at.elapsed() >= Duration::from_millis(20));
+
+        // The third attempt waits the capped 30ms, then succeeds.
+        let before_third = Instant::now();
+        let gap = match device.get_capture(-1) {
+            Ok(ResilientCapture::Gap(gap)) => gap,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert!(before_third.elapsed() >= Duration::from_millis(30));
+        assert_eq!(gap.attempts, 3);
+        assert!(matches!(gap.cause, DeviceGetCaptureError::FailedError));
+        assert!(gap.resumed_at >= gap.lost_at + Duration::from_millis(50));
+        assert!(!device.is_lost());
+
+        assert!(matches!(device.get_capture(-1), Ok(ResilientCapture::Capture(0))));
+
+        let script = script.lock().unwrap();
+        assert_eq!(script.open_count, 4);
+        assert_eq!(script.camera_start_count, 2);
+        assert_eq!(script.imu_start_count, 2);
+    }
+
+    #[test]
+    fn timeouts_only_lose_the_device_when_configured() {
+        let timeout = || Err(DeviceGetCaptureError::TimeoutError { timeout_millis: 5 });
+        let script = script(&[], vec![timeout(), timeout(), Ok(1), timeout(), timeout()]);
+        let mut device = open_fake(&script, false).unwrap();
+        device.reconnect_after_timeouts = Some(2);
+
+        assert!(matches!(device.get_capture(5), Err(ResilientDeviceError::TimeoutError { timeout_millis: 5 })));
+        // The second timeout in a row loses the device, which reopens on the first attempt.
+        match device.get_capture(5) {
+            Ok(ResilientCapture::Gap(gap)) => {
+                assert_eq!(gap.attempts, 1);
+                assert!(matches!(gap.cause, DeviceGetCaptureError::TimeoutError { timeout_millis: 5 }));
+            },
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // A capture in between resets the count.
+        assert!(matches!(device.get_capture(5), Ok(ResilientCapture::Capture(1))));
+        assert!(matches!(device.get_capture(5), Err(ResilientDeviceError::TimeoutError { .. })));
+        assert!(matches!(device.get_capture(5), Ok(ResilientCapture::Gap(_))));
+        assert_eq!(script.lock().unwrap().open_count, 3);
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let script = script(&[true, false, false, false], vec![Err(DeviceGetCaptureError::FailedError)]);
+        let mut device = open_fake(&script, false).unwrap();
+        device.backoff = Backoff {
+            initial: Duration::from_millis(1),
+            max: Duration::from_millis(1),
+            multiplier: 1.0,
+            max_attempts: Some(2),
+        };
+
+        assert!(matches!(device.get_capture(-1), Err(ResilientDeviceError::ReconnectError { attempts: 1, .. })));
+        assert!(matches!(device.get_capture(-1), Err(ResilientDeviceError::ReconnectError { attempts: 2, .. })));
+        assert!(matches!(device.get_capture(-1), Err(ResilientDeviceError::GaveUpError { attempts: 2 })));
+        assert!(matches!(device.get_capture(-1), Err(ResilientDeviceError::GaveUpError { attempts: 2 })));
+        assert_eq!(script.lock().unwrap().open_count, 3);
+    }
+
+    #[test]
+    fn a_failed_imu_start_stops_the_device() {
+        let script = script(&[], Vec::new());
+        script.lock().unwrap().imu_starts.push_back(false);
+
+        assert!(open_fake(&script, true).is_err());
+        {
+            let script = script.lock().unwrap();
+            assert_eq!(script.camera_start_count, 1);
+            assert_eq!(script.stop_count, 1);
+        }
+
+        // The device is stopped once more when dropped.
+        let device = open_fake(&script, true).unwrap();
+        drop(device);
+        assert_eq!(script.lock().unwrap().stop_count, 2);
+    }
+}
@@ -0,0 +1,214 @@
+//! Skeletons of tracked bodies.
+//!
+//! These are plain data, so they're available without the `body-tracking` feature, eg. for
+//! exporting recorded or synthetic skeletons.
+
+/// The number of joints in a skeleton.
+pub const JOINT_COUNT: usize = 32;
+
+/// The joints of a skeleton, in SDK order.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum JointId {
+    Pelvis,
+    SpineNavel,
+    SpineChest,
+    Neck,
+    ClavicleLeft,
+    ShoulderLeft,
+    ElbowLeft,
+    WristLeft,
+    HandLeft,
+    HandTipLeft,
+    ThumbLeft,
+    ClavicleRight,
+    ShoulderRight,
+    ElbowRight,
+    WristRight,
+    HandRight,
+    HandTipRight,
+    ThumbRight,
+    HipLeft,
+    KneeLeft,
+    AnkleLeft,
+    FootLeft,
+    HipRight,
+    KneeRight,
+    AnkleRight,
+    FootRight,
+    Head,
+    Nose,
+    EyeLeft,
+    EarLeft,
+    EyeRight,
+    EarRight,
+}
+
+impl JointId {
+    /// Every joint, in SDK order.
+    pub const ALL: [JointId; JOINT_COUNT] = [
+        JointId::Pelvis,
+        JointId::SpineNavel,
+        JointId::SpineChest,
+        JointId::Neck,
+        JointId::ClavicleLeft,
+        JointId::ShoulderLeft,
+        JointId::ElbowLeft,
+        JointId::WristLeft,
+        JointId::HandLeft,
+        JointId::HandTipLeft,
+        JointId::ThumbLeft,
+        JointId::ClavicleRight,
+        JointId::ShoulderRight,
+        JointId::ElbowRight,
+        JointId::WristRight,
+        JointId::HandRight,
+        JointId::HandTipRight,
+        JointId::ThumbRight,
+        JointId::HipLeft,
+        JointId::KneeLeft,
+        JointId::AnkleLeft,
+        JointId::FootLeft,
+        JointId::HipRight,
+        JointId::KneeRight,
+        JointId::AnkleRight,
+        JointId::FootRight,
+        JointId::Head,
+        JointId::Nose,
+        JointId::EyeLeft,
+        JointId::EarLeft,
+        JointId::EyeRight,
+        JointId::EarRight,
+    ];
+
+    /// The index of the joint in a skeleton.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// The joint's name, as used in exported files.
+    pub fn name(self) -> &'static str {
+        match self {
+            JointId::Pelvis => "Pelvis",
+            JointId::SpineNavel => "SpineNavel",
+            JointId::SpineChest => "SpineChest",
+            JointId::Neck => "Neck",
+            JointId::ClavicleLeft => "ClavicleLeft",
+            JointId::ShoulderLeft => "ShoulderLeft",
+            JointId::ElbowLeft => "ElbowLeft",
+            JointId::WristLeft => "WristLeft",
+            JointId::HandLeft => "HandLeft",
+            JointId::HandTipLeft => "HandTipLeft",
+            JointId::ThumbLeft => "ThumbLeft",
+            JointId::ClavicleRight => "ClavicleRight",
+            JointId::ShoulderRight => "ShoulderRight",
+            JointId::ElbowRight => "ElbowRight",
+            JointId::WristRight => "WristRight",
+            JointId::HandRight => "HandRight",
+            JointId::HandTipRight => "HandTipRight",
+            JointId::ThumbRight => "ThumbRight",
+            JointId::HipLeft => "HipLeft",
+            JointId::KneeLeft => "KneeLeft",
+            JointId::AnkleLeft => "AnkleLeft",
+            JointId::FootLeft => "FootLeft",
+            JointId::HipRight => "HipRight",
+            JointId::KneeRight => "KneeRight",
+            JointId::AnkleRight => "AnkleRight",
+            JointId::FootRight => "FootRight",
+            JointId::Head => "Head",
+            JointId::Nose => "Nose",
+            JointId::EyeLeft => "EyeLeft",
+            JointId::EarLeft => "EarLeft",
+            JointId::EyeRight => "EyeRight",
+            JointId::EarRight => "EarRight",
+        }
+    }
+
+    /// The joint's parent in the Azure Kinect joint hierarchy, or None for the pelvis (the root).
+    pub fn parent(self) -> Option<JointId> {
+        match self {
+            JointId::Pelvis => None,
+            JointId::SpineNavel | JointId::HipLeft | JointId::HipRight => Some(JointId::Pelvis),
+            JointId::SpineChest => Some(JointId::SpineNavel),
+            JointId::Neck | JointId::ClavicleLeft | JointId::ClavicleRight => Some(JointId::SpineChest),
+            JointId::ShoulderLeft => Some(JointId::ClavicleLeft),
+            JointId::ElbowLeft => Some(JointId::ShoulderLeft),
+            JointId::WristLeft => Some(JointId::ElbowLeft),
+            JointId::HandLeft | JointId::ThumbLeft => Some(JointId::WristLeft),
+            JointId::HandTipLeft => Some(JointId::HandLeft),
+            JointId::ShoulderRight => Some(JointId::ClavicleRight),
+            JointId::ElbowRight => Some(JointId::ShoulderRight),
+            JointId::WristRight => Some(JointId::ElbowRight),
+            JointId::HandRight | JointId::ThumbRight => Some(JointId::WristRight),
+            JointId::HandTipRight => Some(JointId::HandRight),
+            JointId::KneeLeft => Some(JointId::HipLeft),
+            JointId::AnkleLeft => Some(JointId::KneeLeft),
+            JointId::FootLeft => Some(JointId::AnkleLeft),
+            JointId::KneeRight => Some(JointId::HipRight),
+            JointId::AnkleRight => Some(JointId::KneeRight),
+            JointId::FootRight => Some(JointId::AnkleRight),
+            JointId::Head => Some(JointId::Neck),
+            JointId::Nose | JointId::EyeLeft | JointId::EarLeft | JointId::EyeRight | JointId::EarRight =>
+                Some(JointId::Head),
+        }
+    }
+
+    /// The joints whose parent is this joint, in SDK order.
+    pub fn children(self) -> Vec<JointId> {
+        JointId::ALL.iter().copied().filter(|joint| joint.parent() == Some(self)).collect()
+    }
+}
+
+/// How confident the tracker is in a joint.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum JointConfidenceLevel {
+    /// The joint is out of range (too far from the depth camera).
+    None,
+    /// The joint is not observed (likely occluded) and its pose is predicted.
+    Low,
+    /// The joint is observed with medium confidence.
+    Medium,
+    /// The joint is observed with high confidence. (Not produced by current SDKs.)
+    High,
+}
+
+impl From<u32> for JointConfidenceLevel {
+    fn from(level: u32) -> Self {
+        match level {
+            1 => JointConfidenceLevel::Low,
+            2 => JointConfidenceLevel::Medium,
+            3 => JointConfidenceLevel::High,
+            _ => JointConfidenceLevel::None,
+        }
+    }
+}
+
+/// A joint's pose, in the depth camera's coordinate system.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Joint {
+    /// Position (x, y, z) in millimeters.
+    pub position: [f32; 3],
+    /// Orientation as a normalized quaternion (w, x, y, z).
+    pub orientation: [f32; 4],
+    pub confidence_level: JointConfidenceLevel,
+}
+
+/// The joints of a body.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Skeleton {
+    /// Joints indexed by `JointId::index`.
+    pub joints: [Joint; JOINT_COUNT],
+}
+
+impl Skeleton {
+    pub fn get_joint(&self, joint: JointId) -> &Joint {
+        &self.joints[joint.index()]
+    }
+}
+
+/// A tracked body.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Body {
+    /// An id that stays the same for the body across frames.
+    pub id: u32,
+    pub skeleton: Skeleton,
+}
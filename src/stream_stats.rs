@@ -0,0 +1,395 @@
+use crate::error::StreamStatsError;
+use crate::Capture;
+use crate::DeviceConfiguration;
+use crate::Image;
+
+use k4a_sys_temp as k4a_sys;
+
+/// Default length of the windows `StreamStats` summarizes, in device time.
+const DEFAULT_SUMMARY_INTERVAL_USEC: u64 = 1_000_000;
+
+/// Gaps longer than this many frame periods are counted as dropped frames.
+const DROP_THRESHOLD_PERIODS: f64 = 1.5;
+
+/// The image streams of a capture.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CaptureStream {
+    Color,
+    Depth,
+    Ir,
+}
+
+impl CaptureStream {
+    fn index(self) -> usize {
+        match self {
+            CaptureStream::Color => 0,
+            CaptureStream::Depth => 1,
+            CaptureStream::Ir => 2,
+        }
+    }
+}
+
+/// Statistics of one stream over a summary window.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct StreamSummary {
+    /// Frames received in the window.
+    pub frames: u64,
+    /// Frames missing in the window, judging by gaps in device timestamps.
+    pub dropped: u64,
+    /// Frames received per second of device time.
+    pub fps: f64,
+    /// Mean gap between consecutive device timestamps.
+    pub mean_gap_usec: f64,
+    /// Longest gap between consecutive device timestamps.
+    pub max_gap_usec: u64,
+    /// Mean time from the host receiving a frame (its system timestamp) to it being pushed, if
+    /// known for any frame of the window.
+    pub mean_latency_usec: Option<f64>,
+    /// Longest time from the host receiving a frame to it being pushed, if known.
+    pub max_latency_usec: Option<u64>,
+    /// Frames received since the `StreamStats` was created.
+    pub total_frames: u64,
+    /// Frames dropped since the `StreamStats` was created.
+    pub total_dropped: u64,
+}
+
+/// A snapshot of every stream that delivered frames in a summary window.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct StreamStatsSummary {
+    /// The device timestamp the window starts at.
+    pub start_usec: u64,
+    /// The length of the window in device time.
+    pub window_usec: u64,
+    pub color: Option<StreamSummary>,
+    pub depth: Option<StreamSummary>,
+    pub ir: Option<StreamSummary>,
+}
+
+impl StreamStatsSummary {
+    pub fn get(&self, stream: CaptureStream) -> Option<&StreamSummary> {
+        match stream {
+            CaptureStream::Color => self.color.as_ref(),
+            CaptureStream::Depth => self.depth.as_ref(),
+            CaptureStream::Ir => self.ir.as_ref(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct StreamState {
+    last_timestamp_usec: Option<u64>,
+    total_frames: u64,
+    total_dropped: u64,
+    frames: u64,
+    dropped: u64,
+    gaps: u64,
+    gap_sum_usec: u64,
+    max_gap_usec: u64,
+    latencies: u64,
+    latency_sum_usec: u64,
+    max_latency_usec: u64,
+}
+
+impl StreamState {
+    fn summarize(&self, window_usec: u64) -> Option<StreamSummary> {
+        if self.frames == 0 {
+            return None;
+        }
+
+        let per_second = |count: u64| if window_usec > 0 { count as f64 * 1e6 / window_usec as f64 } else { 0.0 };
+        let mean = |sum: u64, count: u64| if count > 0 { sum as f64 / count as f64 } else { 0.0 };
+
+        Some(StreamSummary {
+            frames: self.frames,
+            dropped: self.dropped,
+            fps: per_second(self.frames),
+            mean_gap_usec: mean(self.gap_sum_usec, self.gaps),
+            max_gap_usec: self.max_gap_usec,
+            mean_latency_usec: Some(mean(self.latency_sum_usec, self.latencies)).filter(|_| self.latencies > 0),
+            max_latency_usec: Some(self.max_latency_usec).filter(|_| self.latencies > 0),
+            total_frames: self.total_frames,
+            total_dropped: self.total_dropped,
+        })
+    }
+
+    fn reset_window(&mut self) {
+        *self = StreamState {
+            last_timestamp_usec: self.last_timestamp_usec,
+            total_frames: self.total_frames,
+            total_dropped: self.total_dropped,
+            ..StreamState::default()
+        };
+    }
+}
+
+/// Tracks whether a consumer keeps up with a device: the effective frame rate of each stream,
+/// gaps between frames, frames dropped and how long frames wait on the host.
+///
+/// Push every capture as it's read. Frames are judged dropped when the device timestamps of
+/// consecutive frames of a stream are further apart than the configured frame period allows.
+/// Every `summary_interval_usec` of device time, the push that ends the window returns a summary
+/// of it (not counting the frames pushed).
+///
+/// Latency is only known for captures pushed with `push_at`, with a host timestamp from the same
+/// clock as image system timestamps (`CLOCK_MONOTONIC` on Linux, `QueryPerformanceCounter` on
+/// Windows).
+pub struct StreamStats {
+    frame_period_usec: f64,
+    /// Length of the windows summaries are made of, in device time.
+    pub summary_interval_usec: u64,
+    window_start_usec: Option<u64>,
+    streams: [StreamState; 3],
+}
+
+impl StreamStats {
+    /// Track streams that deliver `fps` frames per second, which must be positive and finite.
+    pub fn new(fps: f64) -> Result<Self, StreamStatsError> {
+        if !fps.is_finite() || fps <= 0.0 {
+            return Err(StreamStatsError::InvalidParameterError { name: "fps", value: fps });
+        }
+        Ok(Self::with_fps(fps))
+    }
+
+    /// Track the streams of a device started with `configuration`.
+    pub fn from_configuration(configuration: &DeviceConfiguration) -> Self {
+        let fps = match configuration.0.camera_fps {
+            k4a_sys::k4a_fps_t_K4A_FRAMES_PER_SECOND_5 => 5.0,
+            k4a_sys::k4a_fps_t_K4A_FRAMES_PER_SECOND_15 => 15.0,
+            _ => 30.0,
+        };
+        Self::with_fps(fps)
+    }
+
+    fn with_fps(fps: f64) -> Self {
+        Self {
+            frame_period_usec: 1e6 / fps,
+            summary_interval_usec: DEFAULT_SUMMARY_INTERVAL_USEC,
+            window_start_usec: None,
+            streams: Default::default(),
+        }
+    }
+
+    /// Record the images of a capture.
+    pub fn push(&mut self, capture: &Capture) -> Option<StreamStatsSummary> {
+        self.push_capture(capture, None)
+    }
+
+    /// Record the images of a capture read at `host_timestamp_nsec`, also measuring latency.
+    pub fn push_at(&mut self, capture: &Capture, host_timestamp_nsec: u64) -> Option<StreamStatsSummary> {
+        self.push_capture(capture, Some(host_timestamp_nsec))
+    }
+
+    /// Record one frame of a stream, eg. from a recording or another source of frames.
+    pub fn push_frame(&mut self,
+                      stream: CaptureStream,
+                      device_timestamp_usec: u64,
+                      latency_usec: Option<u64>) -> Option<StreamStatsSummary>
+    {
+        let summary = self.end_window_at(device_timestamp_usec);
+        self.record_frame(stream, device_timestamp_usec, latency_usec);
+        summary
+    }
+
+    /// A summary of the current window so far.
+    pub fn get_summary(&self) -> StreamStatsSummary {
+        let start_usec = self.window_start_usec.unwrap_or(0);
+        let end_usec = self.streams.iter()
+            .filter_map(|state| state.last_timestamp_usec)
+            .max()
+            .unwrap_or(start_usec);
+        self.summarize(start_usec, end_usec.saturating_sub(start_usec))
+    }
+
+    fn push_capture(&mut self, capture: &Capture, host_timestamp_nsec: Option<u64>) -> Option<StreamStatsSummary> {
+        let images = [
+            (CaptureStream::Color, capture.get_color_image()),
+            (CaptureStream::Depth, capture.get_depth_image()),
+            (CaptureStream::Ir, capture.get_ir_image()),
+        ];
+
+        // The window is closed before recording, so it doesn't count the frames ending it.
+        let summary = images.iter()
+            .filter_map(|(_, image)| image.as_ref().map(Image::get_device_timestamp_usec))
+            .min()
+            .and_then(|timestamp_usec| self.end_window_at(timestamp_usec));
+
+        for (stream, image) in images.iter() {
+            if let Some(image) = image {
+                let latency_usec = host_timestamp_nsec.map(|host| latency_usec(image, host));
+                self.record_frame(*stream, image.get_device_timestamp_usec(), latency_usec);
+            }
+        }
+        summary
+    }
+
+    fn record_frame(&mut self, stream: CaptureStream, timestamp_usec: u64, latency_usec: Option<u64>) {
+        let frame_period_usec = self.frame_period_usec;
+        let state = &mut self.streams[stream.index()];
+
+        if let Some(last_usec) = state.last_timestamp_usec {
+            // Out of order timestamps (eg. after a restart) start the stream over rather than
+            // counting as a gap.
+            if timestamp_usec > last_usec {
+                let gap_usec = timestamp_usec - last_usec;
+                state.gaps += 1;
+                state.gap_sum_usec += gap_usec;
+                state.max_gap_usec = state.max_gap_usec.max(gap_usec);
+
+                let periods = gap_usec as f64 / frame_period_usec;
+                if periods > DROP_THRESHOLD_PERIODS {
+                    let dropped = periods.round() as u64 - 1;
+                    state.dropped += dropped;
+                    state.total_dropped += dropped;
+                }
+            }
+        }
+        state.last_timestamp_usec = Some(timestamp_usec);
+        state.frames += 1;
+        state.total_frames += 1;
+
+        if let Some(latency_usec) = latency_usec {
+            state.latencies += 1;
+            state.latency_sum_usec += latency_usec;
+            state.max_latency_usec = state.max_latency_usec.max(latency_usec);
+        }
+    }
+
+    /// Close the window if `timestamp_usec` is past its end, starting the next one there.
+    fn end_window_at(&mut self, timestamp_usec: u64) -> Option<StreamStatsSummary> {
+        let start_usec = *self.window_start_usec.get_or_insert(timestamp_usec);
+        // A timestamp before the window (eg. after a restart) starts a new window.
+        if timestamp_usec < start_usec {
+            self.window_start_usec = Some(timestamp_usec);
+            return None;
+        }
+        let window_usec = timestamp_usec - start_usec;
+        if window_usec < self.summary_interval_usec {
+            return None;
+        }
+
+        let summary = self.summarize(start_usec, window_usec);
+        self.window_start_usec = Some(timestamp_usec);
+        for state in self.streams.iter_mut() {
+            state.reset_window();
+        }
+        Some(summary)
+    }
+
+    fn summarize(&self, start_usec: u64, window_usec: u64) -> StreamStatsSummary {
+        StreamStatsSummary {
+            start_usec,
+            window_usec,
+            color: self.streams[CaptureStream::Color.index()].summarize(window_usec),
+            depth: self.streams[CaptureStream::Depth.index()].summarize(window_usec),
+            ir: self.streams[CaptureStream::Ir.index()].summarize(window_usec),
+        }
+    }
+}
+
+/// Time from the host receiving an image to `host_timestamp_nsec`, in microseconds.
+fn latency_usec(image: &Image, host_timestamp_nsec: u64) -> u64 {
+    host_timestamp_nsec.saturating_sub(image.get_system_timestamp_nsec()) / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stats of a 10 fps stream, so frames are 100 ms apart.
+    fn stats() -> StreamStats {
+        StreamStats::new(10.0).unwrap()
+    }
+
+    #[test]
+    fn new_rejects_bad_frame_rates() {
+        for fps in [0.0, -30.0, f64::NAN, f64::INFINITY].iter() {
+            match StreamStats::new(*fps) {
+                Err(StreamStatsError::InvalidParameterError { name: "fps", .. }) => {},
+                _ => panic!("fps {} was accepted", fps),
+            }
+        }
+        assert!(StreamStats::new(30.0).is_ok());
+    }
+
+    #[test]
+    fn gaps_over_one_and_a_half_periods_count_as_drops() {
+        let mut stats = stats();
+        for timestamp_usec in [0, 150_000, 350_000, 650_000].iter() {
+            assert!(stats.push_frame(CaptureStream::Depth, *timestamp_usec, None).is_none());
+        }
+
+        let summary = stats.get_summary();
+        let depth = summary.depth.unwrap();
+        // Gaps of 1.5, 2 and 3 periods drop 0, 1 and 2 frames.
+        assert_eq!(depth.frames, 4);
+        assert_eq!(depth.dropped, 3);
+        assert_eq!(depth.total_dropped, 3);
+        assert_eq!(depth.max_gap_usec, 300_000);
+        assert!((depth.mean_gap_usec - 650_000.0 / 3.0).abs() < 1e-6);
+        assert!(summary.color.is_none());
+        assert!(summary.ir.is_none());
+    }
+
+    #[test]
+    fn windows_roll_over_keeping_totals() {
+        let mut stats = stats();
+
+        // 900 ms of frames, missing the one at 500 ms.
+        for timestamp_usec in (0..10).filter(|i| *i != 5).map(|i| i * 100_000) {
+            assert!(stats.push_frame(CaptureStream::Depth, timestamp_usec, None).is_none());
+        }
+
+        // The frame ending the window isn't part of its summary.
+        let summary = stats.push_frame(CaptureStream::Depth, 1_000_000, None).unwrap();
+        assert_eq!((summary.start_usec, summary.window_usec), (0, 1_000_000));
+        let depth = summary.depth.unwrap();
+        assert_eq!((depth.frames, depth.dropped), (9, 1));
+        assert_eq!((depth.total_frames, depth.total_dropped), (9, 1));
+        assert!((depth.fps - 9.0).abs() < 1e-9);
+
+        // The next window starts at the frame that ended the last one, and misses two frames.
+        for timestamp_usec in [1_300_000, 1_400_000, 1_500_000, 1_600_000, 1_700_000, 1_800_000, 1_900_000].iter() {
+            assert!(stats.push_frame(CaptureStream::Depth, *timestamp_usec, None).is_none());
+        }
+        let summary = stats.push_frame(CaptureStream::Depth, 2_000_000, None).unwrap();
+        assert_eq!((summary.start_usec, summary.window_usec), (1_000_000, 1_000_000));
+        let depth = summary.depth.unwrap();
+        assert_eq!((depth.frames, depth.dropped), (8, 2));
+        assert_eq!((depth.total_frames, depth.total_dropped), (17, 3));
+    }
+
+    #[test]
+    fn out_of_order_timestamps_restart_the_stream() {
+        let mut stats = stats();
+        assert!(stats.push_frame(CaptureStream::Ir, 5_000_000, None).is_none());
+        assert!(stats.push_frame(CaptureStream::Ir, 5_100_000, None).is_none());
+
+        // The device restarted: neither a gap, drops nor the end of a window.
+        assert!(stats.push_frame(CaptureStream::Ir, 100, None).is_none());
+        assert!(stats.push_frame(CaptureStream::Ir, 100_100, None).is_none());
+
+        let summary = stats.get_summary();
+        assert_eq!((summary.start_usec, summary.window_usec), (100, 100_000));
+        let ir = summary.ir.unwrap();
+        assert_eq!(ir.dropped, 0);
+        assert_eq!(ir.max_gap_usec, 100_000);
+        assert!((ir.mean_gap_usec - 100_000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn latency_averages_frames_that_know_it() {
+        let mut stats = stats();
+        stats.push_frame(CaptureStream::Color, 0, Some(1_000));
+        stats.push_frame(CaptureStream::Color, 100_000, None);
+        stats.push_frame(CaptureStream::Color, 200_000, Some(3_000));
+        stats.push_frame(CaptureStream::Depth, 200_000, None);
+
+        let summary = stats.get_summary();
+        let color = summary.color.unwrap();
+        assert_eq!(color.mean_latency_usec, Some(2_000.0));
+        assert_eq!(color.max_latency_usec, Some(3_000));
+        let depth = summary.depth.unwrap();
+        assert_eq!(depth.mean_latency_usec, None);
+        assert_eq!(depth.max_latency_usec, None);
+    }
+}
@@ -0,0 +1,367 @@
+use crate::Capture;
+use crate::Device;
+use crate::DeviceConfiguration;
+use crate::SynchronizationJackStatus;
+use crate::error::SyncedRigError;
+
+use k4a_sys_temp as k4a_sys;
+
+/// Minimum spacing between the depth laser pulses of neighboring devices, as recommended by the
+/// Azure Kinect documentation to avoid interference.
+const MIN_DEPTH_DELAY_STEP_USEC: i32 = 160;
+
+/// Default tolerance when matching subordinate capture timestamps against the master's.
+const DEFAULT_ALIGNMENT_TOLERANCE_USEC: i64 = 100;
+
+/// Default number of captures `SyncedRig::get_capture_set` reads before giving up on alignment.
+const DEFAULT_MAX_ALIGNMENT_READS: u32 = 100;
+
+/// The role a device plays in a wired synchronization rig.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SyncRole {
+    /// Drives the synchronization signal out of its 'out' jack.
+    Master,
+    /// Receives the synchronization signal on its 'in' jack (and passes it along its 'out' jack).
+    Subordinate,
+}
+
+/// A device that belongs to a `SyncedRig`.
+pub struct RigDevice {
+    pub device: Device,
+    pub serial_number: String,
+    pub role: SyncRole,
+    /// The configuration the device was last started with (if any).
+    pub configuration: Option<DeviceConfiguration>,
+}
+
+/// One capture from every device in a `SyncedRig`, taken at the same time.
+pub struct CaptureSet {
+    /// The capture from the master device.
+    pub master: Capture,
+    /// The captures from the subordinate devices, in the order of `SyncedRig::subordinates`.
+    pub subordinates: Vec<Capture>,
+}
+
+/// Several devices daisy-chained with synchronization cables, streaming in lockstep.
+///
+/// Roles are assigned from the synchronization jack statuses: the master is the device with only
+/// its 'out' jack connected, and every device with its 'in' jack connected is a subordinate.
+///
+/// When started, each device is configured with its wired sync mode and has its depth capture
+/// staggered by `depth_delay_step_usec` so that the depth lasers don't interfere with each other.
+/// Subordinates are started before the master, since they must be waiting for the master's
+/// synchronization signal.
+pub struct SyncedRig {
+    master: RigDevice,
+    subordinates: Vec<RigDevice>,
+    /// Spacing between the depth captures of consecutive devices.
+    pub depth_delay_step_usec: i32,
+    /// Allowed deviation of subordinate timestamps from the master's (after subordinate delays).
+    pub alignment_tolerance_usec: i64,
+    /// Maximum number of captures read while trying to align a capture set.
+    pub max_alignment_reads: u32,
+}
+
+impl SyncedRig {
+    /// Open every installed device as a rig.
+    pub fn open_all() -> Result<Self, SyncedRigError> {
+        let mut devices = Vec::new();
+
+        for device_index in 0..Device::get_installed_count() {
+            let device = Device::open(device_index)
+                .map_err(SyncedRigError::OpenError)?;
+            let serial_number = device.get_serial_number()
+                .map_err(SyncedRigError::GetSerialNumberError)?;
+            devices.push((device, serial_number));
+        }
+
+        Self::from_devices(devices)
+    }
+
+    /// Open the devices with the given serial numbers as a rig.
+    pub fn open(serial_numbers: &[&str]) -> Result<Self, SyncedRigError> {
+        let mut devices = Vec::new();
+
+        for serial_number in serial_numbers {
+            let device = Device::open_by_serial(serial_number)
+                .map_err(SyncedRigError::OpenBySerialError)?;
+            devices.push((device, serial_number.to_string()));
+        }
+
+        Self::from_devices(devices)
+    }
+
+    /// Build a rig from already opened devices, assigning roles from their synchronization jacks.
+    pub fn from_devices(devices: Vec<(Device, String)>) -> Result<Self, SyncedRigError> {
+        if devices.is_empty() {
+            return Err(SyncedRigError::NoDevicesError);
+        }
+
+        let statuses = devices.iter()
+            .map(|(device, serial_number)| {
+                device.get_synchronization_jack_status()
+                    .map(|status| (serial_number.as_str(), status))
+                    .map_err(|error| SyncedRigError::GetSyncJackStatusError {
+                        serial_number: serial_number.clone(),
+                        error,
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let roles = assign_roles(&statuses)?;
+
+        let mut master = None;
+        let mut subordinates = Vec::new();
+
+        for ((device, serial_number), role) in devices.into_iter().zip(roles) {
+            let rig_device = RigDevice {
+                device,
+                serial_number,
+                role,
+                configuration: None,
+            };
+
+            match role {
+                SyncRole::Master => master = Some(rig_device),
+                SyncRole::Subordinate => subordinates.push(rig_device),
+            }
+        }
+
+        let master = master.ok_or(SyncedRigError::NoMasterError)?;
+
+        Ok(Self {
+            master,
+            subordinates,
+            depth_delay_step_usec: MIN_DEPTH_DELAY_STEP_USEC,
+            alignment_tolerance_usec: DEFAULT_ALIGNMENT_TOLERANCE_USEC,
+            max_alignment_reads: DEFAULT_MAX_ALIGNMENT_READS,
+        })
+    }
+
+    /// The master device.
+    pub fn master(&self) -> &RigDevice {
+        &self.master
+    }
+
+    /// The subordinate devices.
+    pub fn subordinates(&self) -> &[RigDevice] {
+        &self.subordinates
+    }
+
+    /// Start the cameras of every device, deriving each device's configuration from `base`.
+    ///
+    /// The wired sync mode and depth delay of `base` are overridden for each device. The
+    /// subordinate delay of `base` is applied to every subordinate. The master must have its color
+    /// camera enabled, as it drives the synchronization signal.
+    pub fn start_cameras(&mut self, base: &DeviceConfiguration) -> Result<(), SyncedRigError> {
+        if base.0.color_resolution == k4a_sys::k4a_color_resolution_t_K4A_COLOR_RESOLUTION_OFF {
+            return Err(SyncedRigError::MasterColorCameraDisabledError);
+        }
+
+        for (i, subordinate) in self.subordinates.iter_mut().enumerate() {
+            let mut configuration = base.clone();
+            configuration.0.wired_sync_mode = k4a_sys::k4a_wired_sync_mode_t_K4A_WIRED_SYNC_MODE_SUBORDINATE;
+            configuration.0.depth_delay_off_color_usec =
+                subordinate_depth_delay_usec(base.0.depth_delay_off_color_usec, i, self.depth_delay_step_usec);
+
+            if let Err(error) = subordinate.start_cameras(configuration) {
+                self.stop_cameras();
+                return Err(error);
+            }
+        }
+
+        let mut configuration = base.clone();
+        configuration.0.wired_sync_mode = k4a_sys::k4a_wired_sync_mode_t_K4A_WIRED_SYNC_MODE_MASTER;
+        configuration.0.subordinate_delay_off_master_usec = 0;
+
+        if let Err(error) = self.master.start_cameras(configuration) {
+            self.stop_cameras();
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    /// Stop the cameras of every device, master first.
+    pub fn stop_cameras(&self) {
+        self.master.device.stop_cameras();
+        for subordinate in self.subordinates.iter() {
+            subordinate.device.stop_cameras();
+        }
+    }
+
+    /// Get one capture from every device, aligned by device timestamp.
+    ///
+    /// Captures are compared by color image timestamp (or depth timestamp less the configured depth
+    /// delay, if color is missing). A subordinate capture is expected at the master's timestamp
+    /// plus the subordinate delay. Whichever side lags behind is read again until every capture is
+    /// within `alignment_tolerance_usec`, or `max_alignment_reads` is exceeded.
+    pub fn get_capture_set(&self, timeout_ms: i32) -> Result<CaptureSet, SyncedRigError> {
+        let mut master = self.master.get_timestamped_capture(timeout_ms)?;
+        let mut subordinates = self.subordinates.iter()
+            .map(|subordinate| subordinate.get_timestamped_capture(timeout_ms))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut reads = 1 + subordinates.len() as u32;
+
+        loop {
+            let mut aligned = true;
+
+            for (subordinate, (capture, timestamp)) in self.subordinates.iter().zip(subordinates.iter_mut()) {
+                let expected = master.1 + subordinate.get_subordinate_delay_usec();
+
+                if *timestamp < expected - self.alignment_tolerance_usec {
+                    // The subordinate is behind; drop its capture and read the next one.
+                    let (next_capture, next_timestamp) = subordinate.get_timestamped_capture(timeout_ms)?;
+                    *capture = next_capture;
+                    *timestamp = next_timestamp;
+                    reads += 1;
+                    aligned = false;
+                } else if *timestamp > expected + self.alignment_tolerance_usec {
+                    // The master is behind; read the next master capture and start over.
+                    master = self.master.get_timestamped_capture(timeout_ms)?;
+                    reads += 1;
+                    aligned = false;
+                    break;
+                }
+            }
+
+            if aligned {
+                return Ok(CaptureSet {
+                    master: master.0,
+                    subordinates: subordinates.into_iter().map(|(capture, _)| capture).collect(),
+                });
+            }
+
+            if reads >= self.max_alignment_reads {
+                return Err(SyncedRigError::AlignmentError { reads });
+            }
+        }
+    }
+}
+
+/// The roles of devices with the given serial numbers and synchronization jack statuses, in the
+/// same order.
+///
+/// The master is the device with only its 'out' jack connected, and every device with its 'in'
+/// jack connected is a subordinate. There must be exactly one master.
+fn assign_roles(statuses: &[(&str, SynchronizationJackStatus)]) -> Result<Vec<SyncRole>, SyncedRigError> {
+    let roles = statuses.iter()
+        .map(|(serial_number, status)| match (status.sync_in_jack_connected, status.sync_out_jack_connected) {
+            (true, _) => Ok(SyncRole::Subordinate),
+            (false, true) => Ok(SyncRole::Master),
+            (false, false) => Err(SyncedRigError::UnconnectedDeviceError { serial_number: serial_number.to_string() }),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let masters: Vec<String> = statuses.iter().zip(&roles)
+        .filter(|(_, role)| **role == SyncRole::Master)
+        .map(|((serial_number, _), _)| serial_number.to_string())
+        .collect();
+
+    match masters.len() {
+        0 => Err(SyncedRigError::NoMasterError),
+        1 => Ok(roles),
+        _ => Err(SyncedRigError::MultipleMastersError { serial_numbers: masters }),
+    }
+}
+
+/// The depth delay of the subordinate at `index`, staggered `step_usec` after the master and
+/// every subordinate before it.
+fn subordinate_depth_delay_usec(base_usec: i32, index: usize, step_usec: i32) -> i32 {
+    base_usec + (index as i32 + 1) * step_usec
+}
+
+impl RigDevice {
+    fn start_cameras(&mut self, configuration: DeviceConfiguration) -> Result<(), SyncedRigError> {
+        self.device.start_cameras(&configuration)
+            .map_err(|error| SyncedRigError::StartCamerasError {
+                serial_number: self.serial_number.clone(),
+                error,
+            })?;
+        self.configuration = Some(configuration);
+        Ok(())
+    }
+
+    fn get_subordinate_delay_usec(&self) -> i64 {
+        self.configuration.as_ref()
+            .map(|configuration| configuration.0.subordinate_delay_off_master_usec as i64)
+            .unwrap_or(0)
+    }
+
+    fn get_depth_delay_usec(&self) -> i64 {
+        self.configuration.as_ref()
+            .map(|configuration| configuration.0.depth_delay_off_color_usec as i64)
+            .unwrap_or(0)
+    }
+
+    /// Get a capture along with its color-aligned device timestamp.
+    fn get_timestamped_capture(&self, timeout_ms: i32) -> Result<(Capture, i64), SyncedRigError> {
+        let capture = self.device.get_capture(timeout_ms)
+            .map_err(|error| SyncedRigError::GetCaptureError {
+                serial_number: self.serial_number.clone(),
+                error,
+            })?;
+
+        let timestamp = match capture.get_color_image() {
+            Some(image) => image.get_device_timestamp_usec() as i64,
+            None => capture.get_depth_image()
+                .or_else(|| capture.get_ir_image())
+                .map(|image| image.get_device_timestamp_usec() as i64 - self.get_depth_delay_usec())
+                .ok_or_else(|| SyncedRigError::MissingTimestampError {
+                    serial_number: self.serial_number.clone(),
+                })?,
+        };
+
+        Ok((capture, timestamp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASTER: SynchronizationJackStatus =
+        SynchronizationJackStatus { sync_in_jack_connected: false, sync_out_jack_connected: true };
+    const MIDDLE: SynchronizationJackStatus =
+        SynchronizationJackStatus { sync_in_jack_connected: true, sync_out_jack_connected: true };
+    const LAST: SynchronizationJackStatus =
+        SynchronizationJackStatus { sync_in_jack_connected: true, sync_out_jack_connected: false };
+    const UNCONNECTED: SynchronizationJackStatus =
+        SynchronizationJackStatus { sync_in_jack_connected: false, sync_out_jack_connected: false };
+
+    #[test]
+    fn roles_follow_the_jacks() {
+        let roles = assign_roles(&[("a", LAST), ("b", MASTER), ("c", MIDDLE)]).unwrap();
+        assert_eq!(roles, vec![SyncRole::Subordinate, SyncRole::Master, SyncRole::Subordinate]);
+
+        let roles = assign_roles(&[("a", MASTER)]).unwrap();
+        assert_eq!(roles, vec![SyncRole::Master]);
+    }
+
+    #[test]
+    fn rigs_need_exactly_one_master() {
+        assert!(matches!(assign_roles(&[("a", MIDDLE), ("b", LAST)]), Err(SyncedRigError::NoMasterError)));
+
+        match assign_roles(&[("a", MASTER), ("b", LAST), ("c", MASTER)]) {
+            Err(SyncedRigError::MultipleMastersError { serial_numbers }) =>
+                assert_eq!(serial_numbers, vec!["a".to_string(), "c".to_string()]),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn unconnected_devices_are_rejected() {
+        match assign_roles(&[("a", MASTER), ("b", UNCONNECTED)]) {
+            Err(SyncedRigError::UnconnectedDeviceError { serial_number }) => assert_eq!(serial_number, "b"),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn subordinate_depth_delays_are_staggered() {
+        let delays: Vec<i32> = (0..3).map(|i| subordinate_depth_delay_usec(0, i, MIN_DEPTH_DELAY_STEP_USEC)).collect();
+        assert_eq!(delays, vec![160, 320, 480]);
+
+        assert_eq!(subordinate_depth_delay_usec(50, 1, 200), 450);
+    }
+}
@@ -2,6 +2,11 @@
 
 use crate::Resolution;
 use crate::Calibration;
+use crate::CalibrationType;
+use crate::Image;
+use crate::ImageMut;
+use crate::ImageFormat;
+use crate::error::TransformationError;
 
 use k4a_sys_temp as k4a_sys;
 
@@ -31,6 +36,114 @@ impl Transformation {
         }
     }
 
+    /// Transforms a depth image into a point cloud image.
+    ///
+    /// The depth image must be in the geometry of `camera`: either a depth image straight from the
+    /// depth camera, or one transformed into the color camera with `depth_image_to_color_camera`.
+    /// The output is a `Custom` image of the same size, where each pixel is three little endian
+    /// int16 values: the x, y, and z coordinates of the point in millimeters. Pixels without a
+    /// depth reading are (0, 0, 0).
+    pub fn depth_image_to_point_cloud(&self,
+                                      depth_image: &Image,
+                                      camera: CalibrationType)
+                                      -> Result<Image, TransformationError>
+    {
+        let width = depth_image.get_width_pixels() as u32;
+        let height = depth_image.get_height_pixels() as u32;
+        let mut point_cloud_image = Image::create(ImageFormat::Custom, width, height, width * 6)
+            .map_err(TransformationError::CreateImageError)?;
+
+        self.depth_image_to_point_cloud_into(depth_image, camera, &mut point_cloud_image)?;
+
+        Ok(point_cloud_image.into_image())
+    }
+
+    /// Like `depth_image_to_point_cloud`, but writes into `point_cloud_image`, eg. one from an
+    /// `ImagePool`. It must be a `Custom` image the size of the depth image, with a stride of at
+    /// least 6 bytes per pixel.
+    pub fn depth_image_to_point_cloud_into(&self,
+                                           depth_image: &Image,
+                                           camera: CalibrationType,
+                                           point_cloud_image: &mut ImageMut)
+                                           -> Result<(), TransformationError>
+    {
+        let result = unsafe {
+            k4a_sys::k4a_transformation_depth_image_to_point_cloud(
+                self.transformation,
+                depth_image.get_handle(),
+                camera.to_k4a(),
+                point_cloud_image.get_handle(),
+            )
+        };
+        TransformationError::check(result)
+    }
+
+    /// Transforms a depth image into the geometry of the color camera.
+    pub fn depth_image_to_color_camera(&self, depth_image: &Image) -> Result<Image, TransformationError> {
+        let width = self.color_resolution.width as u32;
+        let height = self.color_resolution.height as u32;
+        let mut transformed_image = Image::create(ImageFormat::Depth16, width, height, width * 2)
+            .map_err(TransformationError::CreateImageError)?;
+
+        self.depth_image_to_color_camera_into(depth_image, &mut transformed_image)?;
+
+        Ok(transformed_image.into_image())
+    }
+
+    /// Like `depth_image_to_color_camera`, but writes into `transformed_image`, eg. one from an
+    /// `ImagePool`. It must be a `Depth16` image of the color camera's resolution.
+    pub fn depth_image_to_color_camera_into(&self,
+                                            depth_image: &Image,
+                                            transformed_image: &mut ImageMut)
+                                            -> Result<(), TransformationError>
+    {
+        let result = unsafe {
+            k4a_sys::k4a_transformation_depth_image_to_color_camera(
+                self.transformation,
+                depth_image.get_handle(),
+                transformed_image.get_handle(),
+            )
+        };
+        TransformationError::check(result)
+    }
+
+    /// Transforms a BGRA32 color image into the geometry of the depth camera.
+    ///
+    /// Pixels of the output that no color pixel maps to are set to zero.
+    pub fn color_image_to_depth_camera(&self,
+                                       depth_image: &Image,
+                                       color_image: &Image)
+                                       -> Result<Image, TransformationError>
+    {
+        let width = depth_image.get_width_pixels() as u32;
+        let height = depth_image.get_height_pixels() as u32;
+        let mut transformed_image = Image::create(ImageFormat::ColorBgra32, width, height, width * 4)
+            .map_err(TransformationError::CreateImageError)?;
+
+        self.color_image_to_depth_camera_into(depth_image, color_image, &mut transformed_image)?;
+
+        Ok(transformed_image.into_image())
+    }
+
+    /// Like `color_image_to_depth_camera`, but writes into `transformed_image`, eg. one from an
+    /// `ImagePool`. It must be a `ColorBgra32` image the size of the depth image.
+    pub fn color_image_to_depth_camera_into(&self,
+                                            depth_image: &Image,
+                                            color_image: &Image,
+                                            transformed_image: &mut ImageMut)
+                                            -> Result<(), TransformationError>
+    {
+        let result = unsafe {
+            k4a_sys::k4a_transformation_color_image_to_depth_camera(
+                self.transformation,
+                depth_image.get_handle(),
+                color_image.get_handle(),
+                transformed_image.get_handle(),
+            )
+        };
+        TransformationError::check(result)
+    }
+
     /// Returns the underlying opaque handle *without* an additional refcount.
     /// Do not deallocate it.
     pub fn get_handle(&self) -> k4a_sys::k4a_transformation_t {
@@ -0,0 +1,319 @@
+//! Turning depth and IR images into viewable 8-bit images.
+
+use crate::error::VisualizationError;
+use crate::DeviceConfiguration;
+use crate::Image;
+use crate::ImageMut;
+use crate::ImageFormat;
+use k4a_sys_temp as k4a_sys;
+
+/// A colormap from near (0.0) to far (1.0).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Colormap {
+    /// Blue through cyan, yellow and red (MATLAB's jet).
+    Jet,
+    /// A perceptually smoother rainbow from dark blue to dark red (Google's turbo).
+    Turbo,
+    /// Black (near) to white (far).
+    Grayscale,
+}
+
+impl Colormap {
+    /// The color (red, green, blue) at `t`, which is clamped to 0.0 ..= 1.0.
+    pub fn map(self, t: f32) -> [u8; 3] {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) as f64 };
+
+        let rgb = match self {
+            Colormap::Jet => [
+                1.5 - (4.0 * t - 3.0).abs(),
+                1.5 - (4.0 * t - 2.0).abs(),
+                1.5 - (4.0 * t - 1.0).abs(),
+            ],
+            Colormap::Turbo => {
+                // Polynomial approximation of turbo by its author, Anton Mikhailov.
+                let t2 = t * t;
+                let t3 = t2 * t;
+                let t4 = t3 * t;
+                let t5 = t4 * t;
+                [
+                    0.13572138 + 4.61539260 * t - 42.66032258 * t2 + 132.13108234 * t3 - 152.94239396 * t4 + 59.28637943 * t5,
+                    0.09140261 + 2.19418839 * t + 4.84296658 * t2 - 14.18503333 * t3 + 4.27729857 * t4 + 2.82956604 * t5,
+                    0.10667330 + 12.64194608 * t - 60.58204836 * t2 + 110.36276771 * t3 - 89.90310912 * t4 + 27.34824973 * t5,
+                ]
+            },
+            Colormap::Grayscale => [t, t, t],
+        };
+
+        [
+            (rgb[0].clamp(0.0, 1.0) * 255.0).round() as u8,
+            (rgb[1].clamp(0.0, 1.0) * 255.0).round() as u8,
+            (rgb[2].clamp(0.0, 1.0) * 255.0).round() as u8,
+        ]
+    }
+}
+
+/// The range of depths, in millimeters, spread across a colormap.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DepthRange {
+    pub min_mm: u16,
+    pub max_mm: u16,
+}
+
+impl DepthRange {
+    pub fn new(min_mm: u16, max_mm: u16) -> Self {
+        Self { min_mm, max_mm }
+    }
+
+    /// The operating range of a depth mode, or None for modes without depth (off and passive IR).
+    ///
+    /// These are the ranges the Azure Kinect viewer uses.
+    pub fn for_depth_mode(depth_mode: k4a_sys::k4a_depth_mode_t) -> Option<Self> {
+        match depth_mode {
+            k4a_sys::k4a_depth_mode_t_K4A_DEPTH_MODE_NFOV_2X2BINNED => Some(Self::new(500, 5800)),
+            k4a_sys::k4a_depth_mode_t_K4A_DEPTH_MODE_NFOV_UNBINNED => Some(Self::new(500, 4000)),
+            k4a_sys::k4a_depth_mode_t_K4A_DEPTH_MODE_WFOV_2X2BINNED => Some(Self::new(250, 3000)),
+            k4a_sys::k4a_depth_mode_t_K4A_DEPTH_MODE_WFOV_UNBINNED => Some(Self::new(250, 2500)),
+            _ => None,
+        }
+    }
+
+    /// The operating range of the configured depth mode.
+    pub fn from_configuration(configuration: &DeviceConfiguration) -> Option<Self> {
+        Self::for_depth_mode(configuration.0.depth_mode)
+    }
+}
+
+/// Map a `Depth16` image to a `ColorBgra32` image with a colormap.
+///
+/// Depths are clamped to `range`. Pixels without depth are black.
+pub fn colorize_depth(image: &Image, range: DepthRange, colormap: Colormap) -> Result<Image, VisualizationError> {
+    let mut output = Image::create(ImageFormat::ColorBgra32,
+                                   image.get_width_pixels() as u32,
+                                   image.get_height_pixels() as u32,
+                                   0)
+        .map_err(VisualizationError::CreateImageError)?;
+
+    colorize_depth_into(image, range, colormap, &mut output)?;
+
+    Ok(output.into_image())
+}
+
+/// Like `colorize_depth`, but writes into `output`, eg. one from an `ImagePool`. It must be a
+/// `ColorBgra32` image the size of the depth image.
+pub fn colorize_depth_into(image: &Image,
+                           range: DepthRange,
+                           colormap: Colormap,
+                           output: &mut ImageMut) -> Result<(), VisualizationError>
+{
+    match image.get_format() {
+        ImageFormat::Depth16 => {},
+        format => return Err(VisualizationError::UnsupportedImageFormatError(format)),
+    }
+    check_output(image, output, ImageFormat::ColorBgra32, 4)?;
+
+    let depth = image.read_u16_pixels();
+    let width = image.get_width_pixels();
+
+    let span = (range.max_mm as f32 - range.min_mm as f32).max(1.0);
+
+    let stride = output.get_stride_bytes();
+    let buffer = output.get_data_mut();
+
+    for (i, value) in depth.iter().enumerate() {
+        let offset = (i / width.max(1)) * stride + (i % width.max(1)) * 4;
+        let [r, g, b] = if *value == 0 {
+            [0, 0, 0]
+        } else {
+            colormap.map((*value as f32 - range.min_mm as f32) / span)
+        };
+        buffer[offset..offset + 4].copy_from_slice(&[b, g, r, 255]);
+    }
+
+    Ok(())
+}
+
+/// Stretch an `Ir16` image to a `Custom8` image, mapping the `low_percentile` brightness (from
+/// 0.0 to 100.0) to black and the `high_percentile` brightness to white.
+///
+/// Percentiles make the contrast robust to specular highlights, which are orders of magnitude
+/// brighter than the rest of a typical IR frame. 1.0 and 99.0 are good defaults.
+pub fn normalize_ir(image: &Image, low_percentile: f32, high_percentile: f32) -> Result<Image, VisualizationError> {
+    let width = image.get_width_pixels() as u32;
+    let mut output = Image::create(ImageFormat::Custom8, width, image.get_height_pixels() as u32, width)
+        .map_err(VisualizationError::CreateImageError)?;
+
+    normalize_ir_into(image, low_percentile, high_percentile, &mut output)?;
+
+    Ok(output.into_image())
+}
+
+/// Like `normalize_ir`, but writes into `output`, eg. one from an `ImagePool`. It must be a
+/// `Custom8` image the size of the IR image.
+pub fn normalize_ir_into(image: &Image,
+                         low_percentile: f32,
+                         high_percentile: f32,
+                         output: &mut ImageMut) -> Result<(), VisualizationError>
+{
+    match image.get_format() {
+        ImageFormat::Ir16 => {},
+        format => return Err(VisualizationError::UnsupportedImageFormatError(format)),
+    }
+    check_output(image, output, ImageFormat::Custom8, 1)?;
+
+    let ir = image.read_u16_pixels();
+    let width = image.get_width_pixels();
+
+    let mut sorted = ir.clone();
+    sorted.sort_unstable();
+    let percentile = |p: f32| -> f32 {
+        if sorted.is_empty() {
+            return 0.0;
+        }
+        let index = ((p.clamp(0.0, 100.0) / 100.0) * (sorted.len() - 1) as f32).round() as usize;
+        sorted[index] as f32
+    };
+
+    let low = percentile(low_percentile);
+    let high = percentile(high_percentile).max(low + 1.0);
+
+    let stride = output.get_stride_bytes();
+    let buffer = output.get_data_mut();
+
+    for (i, value) in ir.iter().enumerate() {
+        let offset = (i / width.max(1)) * stride + i % width.max(1);
+        buffer[offset] = (((*value as f32 - low) / (high - low)).clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+
+    Ok(())
+}
+
+fn check_output(image: &Image,
+                output: &ImageMut,
+                format: ImageFormat,
+                bytes_per_pixel: usize) -> Result<(), VisualizationError>
+{
+    let width = image.get_width_pixels();
+    let height = image.get_height_pixels();
+
+    let format_matches = output.get_format().to_k4a() == format.to_k4a();
+    let size_matches = output.get_width_pixels() == width
+        && output.get_height_pixels() == height
+        && output.get_stride_bytes() >= width * bytes_per_pixel
+        && output.get_size() >= output.get_stride_bytes() * height;
+
+    if !format_matches || !size_matches {
+        return Err(VisualizationError::OutputImageMismatchError { format, width, height });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::tests::padded_image;
+
+    /// A 16-bit image of `values`, with padded rows.
+    fn image_u16(format: ImageFormat, width: usize, values: &[u16]) -> Image {
+        let bytes: Vec<u8> = values.iter().flat_map(|value| value.to_le_bytes().to_vec()).collect();
+        padded_image(format, width, values.len() / width, 2, 6, &bytes)
+    }
+
+    /// The BGRA pixels of a `ColorBgra32` image.
+    fn bgra_pixels(image: &Image) -> Vec<[u8; 4]> {
+        let stride = image.get_stride_bytes();
+        (0..image.get_height_pixels())
+            .flat_map(|y| image.get_data()[y * stride..].chunks_exact(4).take(image.get_width_pixels()))
+            .map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]])
+            .collect()
+    }
+
+    #[test]
+    fn colormaps_span_their_endpoints() {
+        assert_eq!(Colormap::Grayscale.map(0.0), [0, 0, 0]);
+        assert_eq!(Colormap::Grayscale.map(0.5), [128, 128, 128]);
+        assert_eq!(Colormap::Grayscale.map(1.0), [255, 255, 255]);
+        // Jet runs from dark blue to dark red.
+        assert_eq!(Colormap::Jet.map(0.0), [0, 0, 128]);
+        assert_eq!(Colormap::Jet.map(1.0), [128, 0, 0]);
+        // Turbo's polynomial approximation starts at its constant terms and ends dark red.
+        assert_eq!(Colormap::Turbo.map(0.0), [35, 23, 27]);
+        assert_eq!(Colormap::Turbo.map(1.0), [144, 13, 0]);
+    }
+
+    #[test]
+    fn colormaps_clamp_out_of_range_and_nan() {
+        for colormap in [Colormap::Jet, Colormap::Turbo, Colormap::Grayscale].iter() {
+            assert_eq!(colormap.map(-3.0), colormap.map(0.0), "{:?}", colormap);
+            assert_eq!(colormap.map(f32::NAN), colormap.map(0.0), "{:?}", colormap);
+            assert_eq!(colormap.map(7.0), colormap.map(1.0), "{:?}", colormap);
+        }
+    }
+
+    #[test]
+    fn depth_ranges_follow_the_depth_mode() {
+        assert_eq!(DepthRange::for_depth_mode(k4a_sys::k4a_depth_mode_t_K4A_DEPTH_MODE_NFOV_2X2BINNED),
+                   Some(DepthRange::new(500, 5800)));
+        assert_eq!(DepthRange::for_depth_mode(k4a_sys::k4a_depth_mode_t_K4A_DEPTH_MODE_NFOV_UNBINNED),
+                   Some(DepthRange::new(500, 4000)));
+        assert_eq!(DepthRange::for_depth_mode(k4a_sys::k4a_depth_mode_t_K4A_DEPTH_MODE_WFOV_2X2BINNED),
+                   Some(DepthRange::new(250, 3000)));
+        assert_eq!(DepthRange::for_depth_mode(k4a_sys::k4a_depth_mode_t_K4A_DEPTH_MODE_WFOV_UNBINNED),
+                   Some(DepthRange::new(250, 2500)));
+        assert_eq!(DepthRange::for_depth_mode(k4a_sys::k4a_depth_mode_t_K4A_DEPTH_MODE_OFF), None);
+        assert_eq!(DepthRange::for_depth_mode(k4a_sys::k4a_depth_mode_t_K4A_DEPTH_MODE_PASSIVE_IR), None);
+    }
+
+    #[test]
+    fn colorize_depth_clamps_to_the_range_and_blacks_out_holes() {
+        let depth = image_u16(ImageFormat::Depth16, 3, &[0, 100, 1000, 1500, 2000, 9000]);
+        let colorized = colorize_depth(&depth, DepthRange::new(1000, 2000), Colormap::Grayscale).unwrap();
+        assert!(matches!(colorized.get_format(), ImageFormat::ColorBgra32));
+
+        assert_eq!(bgra_pixels(&colorized), vec![
+            [0, 0, 0, 255],
+            [0, 0, 0, 255],
+            [0, 0, 0, 255],
+            [128, 128, 128, 255],
+            [255, 255, 255, 255],
+            [255, 255, 255, 255],
+        ]);
+
+        // Channels are stored blue first.
+        let colorized = colorize_depth(&depth, DepthRange::new(1000, 2000), Colormap::Jet).unwrap();
+        assert_eq!(bgra_pixels(&colorized)[4], [0, 0, 128, 255]);
+        assert_eq!(bgra_pixels(&colorized)[0], [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn normalize_ir_clamps_below_and_above_the_percentiles() {
+        let values: Vec<u16> = (0..100).collect();
+        let ir = image_u16(ImageFormat::Ir16, 10, &values);
+
+        // The 10th and 90th percentiles of 0 ..= 99 are 10 and 89.
+        let normalized = normalize_ir(&ir, 10.0, 90.0).unwrap();
+        assert!(matches!(normalized.get_format(), ImageFormat::Custom8));
+        let stride = normalized.get_stride_bytes();
+        let pixel = |value: usize| normalized.get_data()[value / 10 * stride + value % 10];
+        assert_eq!((pixel(0), pixel(10)), (0, 0));
+        assert_eq!((pixel(89), pixel(99)), (255, 255));
+        assert_eq!(pixel(50), (40.0 / 79.0 * 255.0_f32).round() as u8);
+
+        // Percentiles outside 0 ..= 100 are clamped to the darkest and brightest pixels.
+        let normalized = normalize_ir(&ir, -5.0, 150.0).unwrap();
+        assert_eq!(normalized.get_data()[0], 0);
+        assert_eq!(normalized.get_data()[9 * normalized.get_stride_bytes() + 9], 255);
+    }
+
+    #[test]
+    fn wrong_formats_and_outputs_are_rejected() {
+        let ir = image_u16(ImageFormat::Ir16, 2, &[1, 2, 3, 4]);
+        let depth = image_u16(ImageFormat::Depth16, 2, &[1, 2, 3, 4]);
+        assert!(matches!(colorize_depth(&ir, DepthRange::new(0, 10), Colormap::Jet),
+                         Err(VisualizationError::UnsupportedImageFormatError(_))));
+        assert!(matches!(normalize_ir(&depth, 1.0, 99.0), Err(VisualizationError::UnsupportedImageFormatError(_))));
+
+        let mut too_small = Image::create(ImageFormat::ColorBgra32, 1, 2, 0).unwrap();
+        assert!(matches!(colorize_depth_into(&depth, DepthRange::new(0, 10), Colormap::Jet, &mut too_small),
+                         Err(VisualizationError::OutputImageMismatchError { .. })));
+    }
+}